@@ -0,0 +1,107 @@
+use ark_ff::FftField;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use ministark::bytecode::Inputs;
+use ministark::bytecode::Program;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::expression::Expr;
+use ministark::expression::ExprArena;
+use ministark::utils::FieldVariant;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+use num_traits::Pow;
+
+const BENCHMARK_LDE_SIZE: [usize; 3] = [14, 15, 16];
+
+const LDE_STEP: usize = 4;
+
+/// Constraint mixing base and extension columns, challenges and hints
+fn constraint(trace_len: usize) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq3>>> {
+    use AlgebraicItem::*;
+    let one = Constant(FieldVariant::Fp(Fp::from(1u8)));
+    let base: Expr<AlgebraicItem<FieldVariant<Fp, Fq3>>> = 0.curr() * 1.next() - &one;
+    let permutation = (2.curr() - Challenge(0, 0) - 0.curr()) * 3.next()
+        - (2.next() - Challenge(0, 1) - 1.curr()) * 3.curr();
+    (&base * &base * 0.next() + permutation * Hint(0)) / (X.pow(trace_len) - &one)
+        + (1.curr() - 0.curr()).pow(3)
+}
+
+fn constraint_evaluation_benches(c: &mut Criterion) {
+    let mut rng = ark_std::test_rng();
+    let mut group = c.benchmark_group("constraint_evaluation");
+    group.sample_size(10);
+
+    for d in BENCHMARK_LDE_SIZE {
+        let n = 1 << d;
+        let expr = constraint(n / LDE_STEP);
+        let lde_domain = Radix2EvaluationDomain::<Fp>::new_coset(n, Fp::GENERATOR).unwrap();
+        let x_lde = lde_domain.elements().collect::<Vec<Fp>>();
+        let base_cols = [(); 2].map(|()| (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>());
+        let extension_cols =
+            [(); 2].map(|()| (0..n).map(|_| Fq3::rand(&mut rng)).collect::<Vec<Fq3>>());
+        let challenges = Challenges::new(vec![Fq3::rand(&mut rng), Fq3::rand(&mut rng)]);
+        let hints = [Fq3::rand(&mut rng)];
+
+        group.bench_with_input(BenchmarkId::new("bytecode", n), &n, |b, _| {
+            b.iter(|| {
+                let program = Program::compile(&expr, 2, &challenges, &hints);
+                let inputs = Inputs {
+                    lde_step: LDE_STEP,
+                    x_lde: &x_lde,
+                    base_trace_lde_cols: &[&base_cols[0], &base_cols[1]],
+                    extension_trace_lde_cols: Some(&[&extension_cols[0], &extension_cols[1]]),
+                    periodic_column_ldes: &[],
+                };
+                let mut result = vec![Fq3::zero(); n];
+                program.eval::<512>(&inputs, &mut result);
+                result
+            })
+        });
+
+        // evaluates the expression DAG point by point
+        group.bench_with_input(BenchmarkId::new("arena", n), &n, |b, _| {
+            b.iter(|| {
+                let mut arena = ExprArena::new();
+                let root = arena.insert(&expr);
+                (0..n)
+                    .map(|i| {
+                        let row = |offset: isize| {
+                            (i as isize + LDE_STEP as isize * offset).rem_euclid(n as isize)
+                                as usize
+                        };
+                        arena
+                            .eval(root, &mut |leaf| match *leaf {
+                                AlgebraicItem::X => FieldVariant::Fp(x_lde[i]),
+                                AlgebraicItem::Constant(v) => v,
+                                AlgebraicItem::Challenge(round, i) => {
+                                    FieldVariant::Fq(challenges[(round, i)])
+                                }
+                                AlgebraicItem::Hint(i) => FieldVariant::Fq(hints[i]),
+                                AlgebraicItem::Trace(col @ (0 | 1), offset) => {
+                                    FieldVariant::Fp(base_cols[col][row(offset)])
+                                }
+                                AlgebraicItem::Trace(col, offset) => {
+                                    FieldVariant::Fq(extension_cols[col - 2][row(offset)])
+                                }
+                                AlgebraicItem::Periodic(_) => unreachable!(),
+                            })
+                            .as_fq()
+                    })
+                    .collect::<Vec<Fq3>>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, constraint_evaluation_benches);
+criterion_main!(benches);
@@ -6,7 +6,10 @@ use criterion::criterion_group;
 use criterion::criterion_main;
 use criterion::BenchmarkId;
 use criterion::Criterion;
+use ministark::hash::Blake3HashFn;
 use ministark::hash::ElementHashFn;
+use ministark::hash::Keccak256HashFn;
+use ministark::hash::Rpo256HashFn;
 use ministark::hash::Sha256HashFn;
 use ministark::merkle::MatrixMerkleTree;
 use ministark::merkle::MatrixMerkleTreeImpl;
@@ -41,6 +44,9 @@ fn build_merkle_tree_bench<F: GpuField + Field, H: ElementHashFn<F>>(
 
 fn build_merkle_tree_benches(c: &mut Criterion) {
     build_merkle_tree_bench::<Fp, Sha256HashFn>(c, "Sha256");
+    build_merkle_tree_bench::<Fp, Blake3HashFn>(c, "Blake3");
+    build_merkle_tree_bench::<Fp, Keccak256HashFn>(c, "Keccak256");
+    build_merkle_tree_bench::<Fp, Rpo256HashFn>(c, "Rpo256");
 }
 
 criterion_group!(benches, build_merkle_tree_benches);
@@ -12,6 +12,7 @@ use ministark::air::AirConfig;
 use ministark::challenges::Challenges;
 use ministark::constraints::AlgebraicItem;
 use ministark::constraints::Constraint;
+use ministark::constraints::ConstraintGroup;
 use ministark::constraints::Hint;
 use ministark::constraints::VerifierChallenge;
 use ministark::hints::Hints;
@@ -24,7 +25,8 @@ pub struct BrainfuckAirConfig;
 
 impl AirConfig for BrainfuckAirConfig {
     const NUM_BASE_COLUMNS: usize = 17;
-    const NUM_EXTENSION_COLUMNS: usize = 9;
+    const NUM_EXTENSION_COLUMNS: &'static [usize] = &[9];
+    const NUM_CHALLENGES: &'static [usize] = &[11];
 
     type Fp = Fp;
     type Fq = Fq3;
@@ -82,7 +84,7 @@ impl AirConfig for BrainfuckAirConfig {
             // multiply by `(x - t_(n-1))` to remove the last term
             // NOTE: `x^trace_len - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))`
             // NOTE: `t^(n-1) = t^(-1)`
-            constraint * ((X - last_trace_x) / (X.pow(trace_len) - one))
+            constraint * ((X - &last_trace_x) / (X.pow(trace_len) - &one))
         });
 
         let boundary_constraints = [
@@ -99,7 +101,7 @@ impl AirConfig for BrainfuckAirConfig {
         .map(|constraint| {
             // ensure constraint holds in the first row
             // symbolically divide `(x - t_0)`
-            constraint / (X - first_trace_x)
+            constraint / (X - &first_trace_x)
         });
 
         let terminal_constraints = [
@@ -114,13 +116,18 @@ impl AirConfig for BrainfuckAirConfig {
             // ensure constraint holds in the last row
             // symbolically divide `(x - t_(n-1))`
             // NOTE: `t^(n-1) = t^(-1)`
-            constraint / (X - last_trace_x)
+            constraint / (X - &last_trace_x)
         });
 
+        let transition_constraints = transition_constraints
+            .map(|c| Constraint::new(c).in_group(ConstraintGroup::Transition));
+        let boundary_constraints =
+            boundary_constraints.map(|c| Constraint::new(c).in_group(ConstraintGroup::Boundary));
+        let terminal_constraints =
+            terminal_constraints.map(|c| Constraint::new(c).in_group(ConstraintGroup::Terminal));
         transition_constraints
             .chain(boundary_constraints)
             .chain(terminal_constraints)
-            .map(Constraint::from)
             .collect()
     }
 }
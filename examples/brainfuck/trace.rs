@@ -67,7 +67,9 @@ impl Trace for BrainfuckTrace {
 
     fn build_extension_columns(
         &self,
+        _segment: usize,
         challenges: &Challenges<Self::Fq>,
+        _extension_trace: &[Matrix<Self::Fq>],
     ) -> Option<Matrix<Self::Fq>> {
         let Self {
             processor_base_trace,
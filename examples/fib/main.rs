@@ -1,16 +1,14 @@
 #![feature(allocator_api)]
 
 use ark_ff::One;
-use ark_poly::EvaluationDomain;
-use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalSerialize;
 use ministark::air::AirConfig;
+use ministark::assertions::Assertion;
 use ministark::constraints::AlgebraicItem;
 use ministark::constraints::Constraint;
 use ministark::constraints::ExecutionTraceColumn;
 use ministark::hash::HashFn;
 use ministark::hash::Sha256HashFn;
-use ministark::hints::Hints;
 use ministark::merkle::MatrixMerkleTreeImpl;
 use ministark::random::PublicCoin;
 use ministark::random::PublicCoinImpl;
@@ -22,7 +20,6 @@ use ministark::Matrix;
 use ministark::ProofOptions;
 use ministark::Trace;
 use ministark_gpu::fields::p18446744069414584321::ark::Fp;
-use num_traits::Pow;
 use sha2::Sha256;
 use std::time::Instant;
 
@@ -47,10 +44,6 @@ impl Trace for FibTrace {
     }
 }
 
-enum FibHint {
-    ClaimedNthFibNum = 0,
-}
-
 struct FibAirConfig;
 
 impl AirConfig for FibAirConfig {
@@ -59,30 +52,15 @@ impl AirConfig for FibAirConfig {
     type Fq = Fp;
     type PublicInputs = Fp;
 
-    fn gen_hints(
-        _trace_len: usize,
-        claimed_nth_fib_number: &Fp,
-        _: &ministark::challenges::Challenges<Self::Fq>,
-    ) -> ministark::hints::Hints<Self::Fq> {
-        Hints::new(vec![(
-            FibHint::ClaimedNthFibNum as usize,
-            *claimed_nth_fib_number,
-        )])
+    fn assertions(trace_len: usize, claimed_nth_fib_number: &Fp) -> Vec<Assertion<Fp>> {
+        vec![Assertion::single(7, trace_len - 1, *claimed_nth_fib_number)]
     }
 
     fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
-        use AlgebraicItem::*;
-        let trace_xs = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
-        // NOTE: =1
-        let first_trace_x = Constant(FieldVariant::Fp(trace_xs.element(0)));
-        // NOTE: =trace_xs.group_gen_inv()
-        let last_trace_x = Constant(FieldVariant::Fp(trace_xs.element(trace_len - 1)));
-        let one = Constant(FieldVariant::Fp(Fp::one()));
-
         let boundary_constraints = {
             let v0 = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
-            let v1 = v0 + v0;
-            let v2 = &v1 * v0;
+            let v1 = v0.clone() + &v0;
+            let v2 = &v1 * &v0;
             let v3 = &v1 * &v2;
             let v4 = &v2 * &v3;
             let v5 = &v3 * &v4;
@@ -101,11 +79,8 @@ impl AirConfig for FibAirConfig {
             ]
         }
         .into_iter()
-        .map(|constraint| {
-            // ensure constraint holds in the first row
-            // symbolically divide `(x - t_0)`
-            constraint / (X - first_trace_x)
-        });
+        // ensure constraint holds in the first row
+        .map(|constraint| constraint.first());
 
         let transition_constraints = vec![
             0.next() - 6.curr() * 7.curr(),
@@ -118,28 +93,10 @@ impl AirConfig for FibAirConfig {
             7.next() - 5.next() * 6.next(),
         ]
         .into_iter()
-        .map(|constraint| {
-            // ensure constraints hold in all rows except the last
-            // multiply by `(x - t_(n-1))` to remove the last term
-            // NOTE: `x^trace_len - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))`
-            // NOTE: `t^(n-1) = t^(-1)`
-            constraint * ((X - last_trace_x) / (X.pow(trace_len) - one))
-        });
-
-        let terminal_constraints =
-            vec![7.curr() - AlgebraicItem::Hint(FibHint::ClaimedNthFibNum as usize)]
-                .into_iter()
-                .map(|constraint| {
-                    // ensure constraint holds in the last row
-                    // symbolically divide `(x - t_0)`
-                    constraint / (X - last_trace_x)
-                });
-
-        boundary_constraints
-            .chain(terminal_constraints)
-            .chain(transition_constraints)
-            .map(Constraint::new)
-            .collect()
+        // ensure constraints hold in all rows except the last
+        .map(|constraint| constraint.every_row_except_last(trace_len));
+
+        boundary_constraints.chain(transition_constraints).collect()
     }
 }
 
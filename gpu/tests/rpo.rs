@@ -1,6 +1,7 @@
 #![cfg(all(target_arch = "aarch64", target_os = "macos"))]
 #![feature(allocator_api, array_windows)]
-// TODO: test GPU RPO against CPU rpo. these aren't tests.
+// Benchmarks of the GPU RPO kernels. Digests are checked against the CPU
+// `Rpo256` by `gpu_rpo256_matches_cpu` in ministark's hash tests.
 
 use ark_ff::One;
 use ministark_gpu::fields::p18446744069414584321::ark::Fp;
@@ -1,8 +1,15 @@
+use crate::assertions::Assertion;
 use crate::challenges::Challenges;
+use crate::codegen::GeneratedEvaluator;
 use crate::constraints::AlgebraicItem;
 use crate::constraints::CompositionConstraint;
 use crate::constraints::CompositionItem;
 use crate::constraints::Constraint;
+use crate::constraints::ConstraintId;
+use crate::constraints::Names;
+use crate::debug::lint_constraints;
+use crate::debug::lint_hints;
+use crate::debug::AirLint;
 use crate::expression::Expr;
 use crate::hints::Hints;
 use crate::utils::FieldVariant;
@@ -11,19 +18,54 @@ use crate::Matrix;
 use crate::ProofOptions;
 use crate::StarkExtensionOf;
 use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use ark_ff::FftField;
 use ark_poly::EvaluationDomain;
 use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use core::fmt::Display;
+use core::fmt::Formatter;
 use ministark_gpu::GpuFftField;
 use num_traits::Pow;
-use std::time::Instant;
+use snafu::Snafu;
 
+/// Columns, challenges and constraints of an AIR
+///
+/// # Migrating AIRs with a single extension trace
+///
+/// The extension trace is split into segments that are each followed by a
+/// round of challenges. AIRs written for a single extension trace migrate as
+/// follows:
+/// * `NUM_EXTENSION_COLUMNS = n` becomes `NUM_EXTENSION_COLUMNS = &[n]`
+/// * the challenges the extension trace is built with are declared with
+///   `NUM_CHALLENGES = &[num_challenges]`
+/// * `AlgebraicItem::Challenge(i)` becomes `AlgebraicItem::Challenge(0, i)`.
+///   Implementations of [`VerifierChallenge`] keep the default round `0`.
+/// * [`Trace::build_extension_columns`] is called once per segment and takes
+///   the segment index and the segments built so far
+///
+/// [`VerifierChallenge`]: crate::constraints::VerifierChallenge
+/// [`Trace::build_extension_columns`]: crate::Trace::build_extension_columns
 pub trait AirConfig: Send + Sync + Sized + 'static {
     const NUM_BASE_COLUMNS: usize;
-    const NUM_EXTENSION_COLUMNS: usize = 0;
+    /// Number of columns returned by [`AirConfig::preprocessed_columns`]
+    const NUM_PREPROCESSED_COLUMNS: usize = 0;
+    /// Number of columns in each extension trace segment. Segment `i` is built
+    /// by [`Trace::build_extension_columns`] once the challenges of rounds
+    /// `0..=i` are drawn and is committed to before round `i + 1`. Extension
+    /// columns follow the preprocessed columns in the order of their segments.
+    ///
+    /// [`Trace::build_extension_columns`]: crate::Trace::build_extension_columns
+    const NUM_EXTENSION_COLUMNS: &'static [usize] = &[];
+    /// Number of challenges drawn in each round. Round `0` is drawn after the
+    /// base trace is committed to and round `i` after extension segment `i -
+    /// 1`. Challenges are referenced with [`VerifierChallenge::round`].
+    ///
+    /// [`VerifierChallenge::round`]: crate::constraints::VerifierChallenge::round
+    const NUM_CHALLENGES: &'static [usize] = &[];
 
     type Fp: GpuFftField<FftField = Self::Fp> + FftField;
     type Fq: StarkExtensionOf<Self::Fp>;
@@ -39,13 +81,56 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
         Hints::default()
     }
 
+    /// Values the execution trace must take in specific rows e.g. the inputs
+    /// and outputs of a computation. A constraint is added to the AIR for each
+    /// assertion.
+    fn assertions(
+        _trace_len: usize,
+        _public_inputs: &Self::PublicInputs,
+    ) -> Vec<Assertion<Self::Fp>> {
+        Vec::new()
+    }
+
+    /// Columns that are identical in every proof e.g. selectors and lookup
+    /// tables. Preprocessed columns follow the base columns so column `i` is
+    /// referenced by constraints as column `NUM_BASE_COLUMNS + i` and
+    /// extension columns start at `NUM_BASE_COLUMNS +
+    /// NUM_PREPROCESSED_COLUMNS`. They are committed to once by
+    /// [`ProvingKey::new`].
+    ///
+    /// [`ProvingKey::new`]: crate::keys::ProvingKey::new
+    fn preprocessed_columns(_trace_len: usize) -> Option<Matrix<Self::Fp>> {
+        None
+    }
+
+    /// Names of columns, challenges and hints used in diagnostics. Names can
+    /// be collected from the types implementing [`ExecutionTraceColumn`],
+    /// [`VerifierChallenge`] and [`Hint`]. Virtual columns should be
+    /// registered with [`Names::with_virtual_columns`].
+    ///
+    /// [`ExecutionTraceColumn`]: crate::constraints::ExecutionTraceColumn
+    /// [`VerifierChallenge`]: crate::constraints::VerifierChallenge
+    /// [`Hint`]: crate::constraints::Hint
+    fn names() -> Names {
+        Names::default()
+    }
+
     fn domain_offset() -> Self::Fp {
         Self::Fp::GENERATOR
     }
 
+    /// Constraint evaluator generated by [`crate::codegen::generate`]. The
+    /// prover and verifier use it in place of the interpreter while it matches
+    /// the shape of the composition constraint.
+    fn generated_evaluator() -> Option<GeneratedEvaluator<Self::Fp, Self::Fq>> {
+        None
+    }
+
     /// Combines multiple constraints into a single constraint (the composition
     /// constraint). Constraints are composed with verifiers randomness.
     /// This verifier randomness is expressed symbolically.
+    /// `trace_len` is the degree bound of the trace polynomials which exceeds
+    /// the number of rows when trace polynomials are randomized.
     /// <https://medium.com/starkware/starkdex-deep-dive-the-stark-core-engine-497942d0f0ab>
     fn composition_constraint(
         trace_len: usize,
@@ -68,24 +153,25 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
                 assert!(evaluation_degree <= composition_degree);
                 let degree_adjustment = composition_degree - evaluation_degree;
                 // TODO: if degree_adjustment is 0 then we only need one challenge
-                let constraint = constraint.map_leaves(&mut |&leaf| CompositionItem::Item(leaf));
+                let constraint =
+                    constraint.map_leaves(&mut |leaf| CompositionItem::Item(leaf.clone()));
                 let alpha = composition_coeff.next().unwrap();
                 let beta = composition_coeff.next().unwrap();
                 &constraint * (x.clone().pow(degree_adjustment) * alpha + beta)
             })
             .sum::<Expr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>>();
-        // TODO: remove log and timing
-        let now = Instant::now();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("reuse_shared_nodes").entered();
         let expr = expr.reuse_shared_nodes();
-        println!("Reuse took: {:?}", now.elapsed());
-        CompositionConstraint::new(expr)
+        let metadata = constraints.iter().map(|c| c.metadata().clone()).collect();
+        CompositionConstraint::new(expr).with_metadata(metadata)
     }
 
     // TODO: maybe move this into a constraint evaluator
     #[allow(clippy::too_many_arguments)]
     fn eval_constraint(
         composition_constraint: &CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>>,
-        challenges: &[Self::Fq],
+        challenges: &Challenges<Self::Fq>,
         hints: &[Self::Fq],
         composition_constraint_coeffs: &[Self::Fq],
         lde_step: usize,
@@ -94,13 +180,33 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
         extension_trace_lde_cols: Option<&[&[Self::Fq]]>,
     ) -> Matrix<Self::Fq> {
         let eval_expr = composition_constraint.map_leaves(&mut |leaf| match leaf {
-            CompositionItem::Item(item) => *item,
+            CompositionItem::Item(item) => item.clone(),
             CompositionItem::CompositionCoeff(i) => {
                 AlgebraicItem::Constant(FieldVariant::Fq(composition_constraint_coeffs[*i]))
             }
         });
         // TODO: add back in
         // .reuse_shared_nodes();
+        if let Some(evaluator) = Self::generated_evaluator() {
+            if evaluator.fingerprint == crate::codegen::fingerprint(composition_constraint) {
+                return crate::eval_cpu::eval_generated(
+                    &evaluator,
+                    &eval_expr,
+                    &crate::codegen::constants(composition_constraint),
+                    &crate::codegen::periodic_columns(composition_constraint),
+                    challenges,
+                    hints,
+                    composition_constraint_coeffs,
+                    lde_step,
+                    Self::domain_offset(),
+                    &x_lde,
+                    base_trace_lde_cols,
+                    extension_trace_lde_cols,
+                );
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!("generated constraint evaluator is out of date");
+        }
         // TODO: GPU constraint eval is currently slower than CPU
         // #[cfg(feature = "gpu")]
         // return crate::eval_gpu::eval::<Self::Fp, Self::Fq>(
@@ -128,40 +234,302 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
     }
 }
 
+/// Total number of columns across the extension trace segments
+pub fn num_extension_columns<A: AirConfig>() -> usize {
+    A::NUM_EXTENSION_COLUMNS.iter().sum()
+}
+
 pub fn trace_domain<A: AirConfig>(trace_len: usize) -> Radix2EvaluationDomain<A::Fp> {
     Radix2EvaluationDomain::new(trace_len).unwrap()
 }
 
+/// Low degree extension domain
+pub fn lde_domain<A: AirConfig>(
+    trace_len: usize,
+    lde_blowup_factor: usize,
+) -> Radix2EvaluationDomain<A::Fp> {
+    let offset = A::domain_offset();
+    Radix2EvaluationDomain::new_coset(trace_len * lde_blowup_factor, offset).unwrap()
+}
+
+/// Checks extension trace segments have columns, challenges are drawn in
+/// rounds that follow a trace segment and constraints only use challenges
+/// that are drawn
+fn check_trace_segments<A: AirConfig>(
+    constraints: &[Constraint<FieldVariant<A::Fp, A::Fq>>],
+) -> Result<(), AirError> {
+    if let Some(segment) = A::NUM_EXTENSION_COLUMNS.iter().position(|&n| n == 0) {
+        return Err(AirError::EmptyExtensionSegment { segment });
+    }
+
+    let rounds = A::NUM_CHALLENGES.len();
+    let max_rounds = A::NUM_EXTENSION_COLUMNS.len() + 1;
+    if rounds > max_rounds {
+        return Err(AirError::TooManyChallengeRounds { rounds, max_rounds });
+    }
+
+    let mut result = Ok(());
+    for constraint in constraints {
+        constraint.traverse(&mut |node| {
+            if let &Expr::Leaf(AlgebraicItem::Challenge(round, index)) = node {
+                let num_challenges = A::NUM_CHALLENGES.get(round).copied().unwrap_or(0);
+                if index >= num_challenges && result.is_ok() {
+                    result = Err(AirError::ChallengeOutOfBounds { round, index });
+                }
+            }
+        });
+    }
+    result
+}
+
+/// Returns the constraints of an AIR followed by the constraints of its
+/// assertions
+pub(crate) fn constraints_with_assertions<A: AirConfig>(
+    trace_len: usize,
+    assertions: &[Assertion<A::Fp>],
+) -> Vec<Constraint<FieldVariant<A::Fp, A::Fq>>> {
+    let mut constraints = A::constraints(trace_len);
+    constraints.extend(assertions.iter().map(|a| a.constraint(trace_len)));
+    constraints
+}
+
 pub struct Air<AC: AirConfig> {
     constraints: Vec<Constraint<FieldVariant<AC::Fp, AC::Fq>>>,
     composition_constraint: CompositionConstraint<FieldVariant<AC::Fp, AC::Fq>>,
     ce_blowup_factor: usize,
     trace_len: usize,
+    trace_degree_bound: usize,
     options: ProofOptions,
     public_inputs: AC::PublicInputs,
+    lints: Vec<AirLint>,
 }
 
-impl<C: AirConfig> Air<C> {
-    pub fn new(trace_len: usize, public_inputs: C::PublicInputs, options: ProofOptions) -> Self {
-        let constraints = C::constraints(trace_len);
-        let composition_constraint = C::composition_constraint(trace_len, &constraints);
-        let ce_blowup_factor = composition_constraint.blowup_factor(trace_len);
-        assert!(ce_blowup_factor <= options.lde_blowup_factor.into());
+/// Errors that can occur when constructing an [`Air`]
+#[derive(Debug, Snafu)]
+pub enum AirError {
+    #[snafu(display("trace length must be a power of two, but is `{len}`"))]
+    TraceLengthNotPowerOfTwo { len: usize },
+    #[snafu(display(
+        "constraints require a blowup factor of at least `{required}`, but `{actual}` was \
+         provided (`{constraint}` has the largest degree)"
+    ))]
+    BlowupFactorTooSmall {
+        required: usize,
+        actual: usize,
+        constraint: ConstraintId,
+    },
+    #[snafu(display(
+        "assertion on column `{column}` does not fit in a trace of length `{trace_len}`"
+    ))]
+    AssertionOutOfBounds { column: usize, trace_len: usize },
+    #[snafu(display("expected `{expected}` preprocessed columns, but `{actual}` were provided"))]
+    PreprocessedColumnCountMismatch { expected: usize, actual: usize },
+    #[snafu(display(
+        "preprocessed columns have `{actual}` rows, but the trace has `{expected}` rows"
+    ))]
+    PreprocessedRowCountMismatch { expected: usize, actual: usize },
+    #[snafu(display("extension trace segment `{segment}` has no columns"))]
+    EmptyExtensionSegment { segment: usize },
+    #[snafu(display(
+        "challenges are drawn in `{rounds}` rounds, but there are only `{max_rounds}` trace \
+         segments to follow"
+    ))]
+    TooManyChallengeRounds { rounds: usize, max_rounds: usize },
+    #[snafu(display("challenge `{index}` of round `{round}` is used but not drawn"))]
+    ChallengeOutOfBounds { round: usize, index: usize },
+}
+
+/// Degree of a constraint and how it contributes to the composition polynomial
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintDegree {
+    pub constraint: ConstraintId,
+    pub numerator_degree: usize,
+    pub denominator_degree: usize,
+    /// Blowup factor needed to evaluate the constraint on its own
+    pub blowup_factor: usize,
+    /// Power of `x` the constraint is multiplied by in
+    /// [`AirConfig::composition_constraint`] to reach the composition degree
+    pub degree_adjustment: usize,
+}
+
+/// Degrees of all constraints in an AIR. Displays as a table to help find the
+/// constraints that dominate the cost of proving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DegreeReport {
+    /// Degree bound of the trace polynomials
+    pub trace_degree_bound: usize,
+    pub ce_blowup_factor: usize,
+    pub constraints: Vec<ConstraintDegree>,
+}
 
+impl DegreeReport {
+    fn new(trace_degree_bound: usize, constraints: &[Constraint<impl Sized>]) -> Self {
+        let trace_degree = trace_degree_bound - 1;
+        let blowup_factors = constraints
+            .iter()
+            .map(|c| c.blowup_factor(trace_degree_bound))
+            .collect::<Vec<usize>>();
+        let ce_blowup_factor = blowup_factors.iter().copied().max().unwrap_or(0);
+        let composition_degree = (trace_degree_bound * ce_blowup_factor).saturating_sub(1);
+        let constraints = constraints
+            .iter()
+            .zip(blowup_factors)
+            .enumerate()
+            .map(|(i, (constraint, blowup_factor))| {
+                let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
+                let evaluation_degree = numerator_degree.saturating_sub(denominator_degree);
+                ConstraintDegree {
+                    constraint: constraint.metadata().id(i),
+                    numerator_degree,
+                    denominator_degree,
+                    blowup_factor,
+                    degree_adjustment: composition_degree.saturating_sub(evaluation_degree),
+                }
+            })
+            .collect();
         Self {
+            trace_degree_bound,
+            ce_blowup_factor,
+            constraints,
+        }
+    }
+
+    /// Returns the index of the first constraint with the largest degree
+    pub fn max_degree_constraint(&self) -> Option<usize> {
+        (0..self.constraints.len()).rev().max_by_key(|&i| {
+            let degree = &self.constraints[i];
+            degree
+                .numerator_degree
+                .saturating_sub(degree.denominator_degree)
+        })
+    }
+}
+
+impl Display for DegreeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        writeln!(
+            f,
+            "trace degree bound: {}, constraint evaluation blowup: {}",
+            self.trace_degree_bound, self.ce_blowup_factor
+        )?;
+        let ids = self
+            .constraints
+            .iter()
+            .map(|degree| degree.constraint.to_string());
+        let ids = ids.collect::<Vec<String>>();
+        let width = ids.iter().map(String::len).max().unwrap_or(0).max(10);
+        write!(
+            f,
+            "{:<width$} | {:>12} | {:>12} | {:>6} | {:>10}",
+            "constraint", "numerator", "denominator", "blowup", "adjustment"
+        )?;
+        for (id, degree) in ids.iter().zip(&self.constraints) {
+            write!(
+                f,
+                "\n{:<width$} | {:>12} | {:>12} | {:>6} | {:>10}",
+                id,
+                degree.numerator_degree,
+                degree.denominator_degree,
+                degree.blowup_factor,
+                degree.degree_adjustment
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: AirConfig> Air<C> {
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * the trace length is not a power of two
+    /// * an assertion refers to rows outside the trace
+    /// * an extension trace segment has no columns
+    /// * a constraint uses a challenge that is not drawn
+    /// * the blowup factor is too small for the degree of the constraints
+    ///
+    /// Constraints are simplified with [`Constraint::simplify`] before the
+    /// composition constraint is built. Likely mistakes in the constraints
+    /// that don't prevent proving are found here and kept in [`Air::lints`].
+    pub fn new(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+    ) -> Result<Self, AirError> {
+        if !trace_len.is_power_of_two() {
+            return Err(AirError::TraceLengthNotPowerOfTwo { len: trace_len });
+        }
+
+        // zero-knowledge proofs randomize trace polynomials by adding a random
+        // multiple of the trace domain's vanishing polynomial
+        let trace_degree_bound = if options.zero_knowledge {
+            2 * trace_len
+        } else {
+            trace_len
+        };
+        let assertions = C::assertions(trace_len, &public_inputs);
+        if let Some(assertion) = assertions.iter().find(|a| !a.fits_trace(trace_len)) {
+            return Err(AirError::AssertionOutOfBounds {
+                column: assertion.column(),
+                trace_len,
+            });
+        }
+        let constraints = constraints_with_assertions::<C>(trace_len, &assertions)
+            .iter()
+            .map(Constraint::simplify)
+            .collect::<Vec<_>>();
+        check_trace_segments::<C>(&constraints)?;
+        let composition_constraint = C::composition_constraint(trace_degree_bound, &constraints);
+        let ce_blowup_factor = composition_constraint.blowup_factor(trace_degree_bound);
+        let lde_blowup_factor = usize::from(options.lde_blowup_factor);
+        let required_blowup_factor = trace_degree_bound * ce_blowup_factor / trace_len;
+        if lde_blowup_factor < required_blowup_factor {
+            let mut degrees = DegreeReport::new(trace_degree_bound, &constraints);
+            let max_degree_constraint = degrees.max_degree_constraint().unwrap_or_default();
+            return Err(AirError::BlowupFactorTooSmall {
+                required: required_blowup_factor,
+                actual: lde_blowup_factor,
+                constraint: degrees
+                    .constraints
+                    .swap_remove(max_degree_constraint)
+                    .constraint,
+            });
+        }
+
+        // lints only depend on the constraints so they're found once per AIR
+        let mut lints = lint_constraints::<C>(trace_len, &constraints);
+        if let Some(evaluator) = C::generated_evaluator() {
+            let expected = crate::codegen::fingerprint(&composition_constraint);
+            if evaluator.fingerprint != expected {
+                lints.push(AirLint::OutdatedGeneratedEvaluator {
+                    expected,
+                    actual: evaluator.fingerprint,
+                });
+            }
+        }
+
+        Ok(Self {
             constraints,
             composition_constraint,
             ce_blowup_factor,
             trace_len,
+            trace_degree_bound,
             options,
             public_inputs,
-        }
+            lints,
+        })
     }
 
     pub const fn trace_len(&self) -> usize {
         self.trace_len
     }
 
+    /// Returns the number of coefficients in the committed trace polynomials.
+    /// This is twice the trace length for zero-knowledge proofs.
+    pub const fn trace_degree_bound(&self) -> usize {
+        self.trace_degree_bound
+    }
+
     pub const fn options(&self) -> ProofOptions {
         self.options
     }
@@ -174,28 +542,58 @@ impl<C: AirConfig> Air<C> {
         self.ce_blowup_factor
     }
 
+    /// Number of coefficients of the composition polynomial in each composition
+    /// trace column. Column `i` holds coefficients `i * len..(i + 1) * len`.
+    pub const fn composition_segment_len(&self) -> usize {
+        self.trace_len
+    }
+
+    /// Number of columns the composition polynomial is split into. Columns of
+    /// zero-knowledge proofs have as many random coefficients as coefficients
+    /// of the composition polynomial so there are twice as many.
+    pub const fn num_composition_trace_columns(&self) -> usize {
+        (self.composition_degree() + 1) / self.composition_segment_len()
+    }
+
     /// Returns a degree that all constraint polynomials must be normalized to.
     pub const fn composition_degree(&self) -> usize {
-        let ce_domain_size = self.trace_len * self.ce_blowup_factor();
+        let ce_domain_size = self.trace_degree_bound * self.ce_blowup_factor();
         ce_domain_size - 1
     }
 
-    pub fn num_challenges(&self) -> usize {
-        let mut num_challenges = 0;
-        for constraint in &self.constraints {
-            constraint.traverse(&mut |node| {
-                if let Expr::Leaf(AlgebraicItem::Challenge(i)) = node {
-                    num_challenges = core::cmp::max(num_challenges, *i + 1);
-                }
-            });
-        }
-        num_challenges
+    pub fn constraints(&self) -> &[Constraint<FieldVariant<C::Fp, C::Fq>>] {
+        &self.constraints
+    }
+
+    /// Returns the number of challenges drawn in the given round
+    pub fn num_challenges(&self, round: usize) -> usize {
+        C::NUM_CHALLENGES.get(round).copied().unwrap_or(0)
     }
 
     pub fn gen_hints(&self, challenges: &Challenges<C::Fq>) -> Hints<C::Fq> {
         C::gen_hints(self.trace_len(), self.public_inputs(), challenges)
     }
 
+    /// Degree, blowup factor and composition degree adjustment of each
+    /// constraint
+    pub fn degree_report(&self) -> DegreeReport {
+        DegreeReport::new(self.trace_degree_bound, &self.constraints)
+    }
+
+    /// Likely mistakes in the constraints e.g. columns that aren't
+    /// constrained. These are found by [`Air::new`] and the prover reports
+    /// them in [`ProverReport::lints`].
+    ///
+    /// [`ProverReport::lints`]: crate::prover::ProverReport::lints
+    pub fn lints(&self) -> &[AirLint] {
+        &self.lints
+    }
+
+    /// Finds hints that are unused by the constraints or missing
+    pub fn lint_hints(&self, hints: &Hints<C::Fq>) -> Vec<AirLint> {
+        lint_hints::<C>(&self.constraints, hints)
+    }
+
     pub fn num_composition_constraint_coeffs(&self) -> usize {
         let mut num_coeffs = 0;
         self.composition_constraint.traverse(&mut |node| {
@@ -212,18 +610,15 @@ impl<C: AirConfig> Air<C> {
 
     /// Low degree extension domain
     pub fn lde_domain(&self) -> Radix2EvaluationDomain<C::Fp> {
-        let offset = C::domain_offset();
-        let trace_len = self.trace_len();
-        let lde_blowup_factor = self.lde_blowup_factor();
-        Radix2EvaluationDomain::new_coset(trace_len * lde_blowup_factor, offset).unwrap()
+        lde_domain::<C>(self.trace_len(), self.lde_blowup_factor())
     }
 
     /// Constraint evaluation domain
     pub fn ce_domain(&self) -> Radix2EvaluationDomain<C::Fp> {
         let offset = C::domain_offset();
-        let trace_len = self.trace_len();
+        let trace_degree_bound = self.trace_degree_bound();
         let blowup_factor = self.ce_blowup_factor();
-        Radix2EvaluationDomain::new_coset(trace_len * blowup_factor, offset).unwrap()
+        Radix2EvaluationDomain::new_coset(trace_degree_bound * blowup_factor, offset).unwrap()
     }
 
     /// Low degree extension domain
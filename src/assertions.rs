@@ -0,0 +1,164 @@
+//! Assertions on the values of execution trace cells
+
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use core::panic::Location;
+
+/// Asserts the value of a column in one or more rows of the execution trace.
+/// Assertions usually tie the execution trace to public inputs and are
+/// provided by [`AirConfig::assertions`].
+///
+/// [`AirConfig::assertions`]: crate::air::AirConfig::assertions
+#[derive(Clone, Debug)]
+pub struct Assertion<F> {
+    column: usize,
+    first_step: usize,
+    /// Zero for assertions on a single row
+    stride: usize,
+    values: Vec<F>,
+    /// Where the assertion was created
+    location: &'static Location<'static>,
+}
+
+impl<F: FftField> Assertion<F> {
+    /// Asserts `column` equals `value` in row `step`
+    #[track_caller]
+    pub fn single(column: usize, step: usize, value: F) -> Self {
+        Self {
+            column,
+            first_step: step,
+            stride: 0,
+            values: vec![value],
+            location: Location::caller(),
+        }
+    }
+
+    /// Asserts `column` equals `value` in rows `first_step`, `first_step +
+    /// stride`, `first_step + 2 * stride`, ...
+    ///
+    /// # Panics
+    /// Panics if `stride` is not a power of two or if `first_step` is not less
+    /// than `stride`.
+    #[track_caller]
+    pub fn periodic(column: usize, first_step: usize, stride: usize, value: F) -> Self {
+        assert!(stride.is_power_of_two());
+        assert!(first_step < stride);
+        Self {
+            column,
+            first_step,
+            stride,
+            values: vec![value],
+            location: Location::caller(),
+        }
+    }
+
+    /// Asserts `column` equals `values[i]` in row `first_step + i * stride`.
+    /// The values must cover the entire trace i.e. `stride * values.len()`
+    /// must equal the trace length.
+    ///
+    /// # Panics
+    /// Panics if `stride` or the number of values is not a power of two or if
+    /// `first_step` is not less than `stride`.
+    #[track_caller]
+    pub fn sequence(column: usize, first_step: usize, stride: usize, values: Vec<F>) -> Self {
+        assert!(stride.is_power_of_two());
+        assert!(values.len().is_power_of_two());
+        assert!(first_step < stride);
+        Self {
+            column,
+            first_step,
+            stride,
+            values,
+            location: Location::caller(),
+        }
+    }
+
+    pub const fn column(&self) -> usize {
+        self.column
+    }
+
+    pub const fn first_step(&self) -> usize {
+        self.first_step
+    }
+
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn values(&self) -> &[F] {
+        &self.values
+    }
+
+    /// Returns true if the rows of the assertion are within a trace of length
+    /// `trace_len`
+    pub const fn fits_trace(&self, trace_len: usize) -> bool {
+        match (self.stride, self.values.len()) {
+            (0, _) => self.first_step < trace_len,
+            (stride, 1) => stride <= trace_len,
+            (stride, num_values) => stride * num_values == trace_len,
+        }
+    }
+
+    /// Returns a constraint that holds if the assertion holds. The constraint
+    /// is named after the assertion.
+    ///
+    /// # Panics
+    /// Panics if the assertion does not fit in the trace. See
+    /// [`Assertion::fits_trace`].
+    pub fn constraint<Fq>(&self, trace_len: usize) -> Constraint<FieldVariant<F, Fq>> {
+        assert!(self.fits_trace(trace_len));
+        let column = self.column.curr::<FieldVariant<F, Fq>>();
+        if self.stride == 0 {
+            let value = AlgebraicItem::Constant(FieldVariant::Fp(self.values[0]));
+            return (column - value)
+                .at_row(self.first_step, trace_len)
+                .named_at(self.to_string(), self.location);
+        }
+        // a sequence of values is interpolated into a polynomial in `x` over
+        // the rows of the assertion
+        let rows_offset = Radix2EvaluationDomain::<F>::new(trace_len)
+            .unwrap()
+            .element(self.first_step);
+        let rows_domain =
+            Radix2EvaluationDomain::new_coset(self.values.len(), rows_offset).unwrap();
+        let coeffs = rows_domain.ifft(&self.values);
+        let poly = coeffs
+            .into_iter()
+            .rev()
+            .map(|coeff| Expr::from(AlgebraicItem::Constant(FieldVariant::Fp(coeff))))
+            .reduce(|acc, coeff| acc * AlgebraicItem::X + coeff)
+            .unwrap();
+        (column - poly)
+            .every(self.stride, self.first_step, trace_len)
+            .named_at(self.to_string(), self.location)
+    }
+}
+
+impl<F: Display> Display for Assertion<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match (self.stride, self.values.as_slice()) {
+            (0, [value]) => write!(f, "col{}[{}] = {value}", self.column, self.first_step),
+            (stride, [value]) => write!(
+                f,
+                "col{}[{} + {stride}k] = {value}",
+                self.column, self.first_step
+            ),
+            (stride, values) => write!(
+                f,
+                "col{}[{} + {stride}k] = sequence of {} values",
+                self.column,
+                self.first_step,
+                values.len()
+            ),
+        }
+    }
+}
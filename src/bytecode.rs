@@ -0,0 +1,720 @@
+//! Compiles constraint expressions to a register based bytecode that is
+//! evaluated over chunks of the LDE domain.
+//!
+//! Sub-expressions that only depend on
+//! constants, challenges and hints are folded during compilation and
+//! sub-expressions over the base field are evaluated in the base field.
+
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::PeriodicColumn;
+use crate::eval_cpu::extract_lde_chunk;
+use crate::expression::Expr;
+use crate::expression::ExprArena;
+use crate::expression::Node;
+use crate::utils::FieldVariant;
+use crate::StarkExtensionOf;
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ff::batch_inversion;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_std::cfg_chunks_mut;
+use ministark_gpu::GpuFftField;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A register holding a chunk of evaluations
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    Fp(usize),
+    Fq(usize),
+}
+
+/// Instructions operate on chunks of evaluations.
+///
+/// `dst`, `lhs`, `rhs` and
+/// `src` are register indices in the register file of the named field. Mixed
+/// instructions e.g. [`Instruction::MulFqFp`] take an `Fq` register on the
+/// left and an `Fp` register on the right.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction<Fp, Fq> {
+    /// Loads evaluations of X
+    LoadX {
+        dst: usize,
+    },
+    /// Loads a base trace column shifted by `offset` rows
+    LoadBase {
+        dst: usize,
+        col: usize,
+        offset: isize,
+    },
+    /// Loads an extension trace column shifted by `offset` rows
+    LoadExtension {
+        dst: usize,
+        col: usize,
+        offset: isize,
+    },
+    /// Loads evaluations of a periodic column. `col` indexes into
+    /// [`Program::periodic_columns`]
+    LoadPeriodic {
+        dst: Register,
+        col: usize,
+    },
+    AddFp {
+        dst: usize,
+        lhs: usize,
+        rhs: usize,
+    },
+    AddFq {
+        dst: usize,
+        lhs: usize,
+        rhs: usize,
+    },
+    AddFqFp {
+        dst: usize,
+        lhs: usize,
+        rhs: usize,
+    },
+    AddConstFp {
+        dst: usize,
+        src: usize,
+        value: Fp,
+    },
+    AddConstFq {
+        dst: usize,
+        src: usize,
+        value: Fq,
+    },
+    /// Adds an `Fq` constant to an `Fp` register and writes to an `Fq` register
+    AddConstFpFq {
+        dst: usize,
+        src: usize,
+        value: Fq,
+    },
+    MulFp {
+        dst: usize,
+        lhs: usize,
+        rhs: usize,
+    },
+    MulFq {
+        dst: usize,
+        lhs: usize,
+        rhs: usize,
+    },
+    MulFqFp {
+        dst: usize,
+        lhs: usize,
+        rhs: usize,
+    },
+    MulConstFp {
+        dst: usize,
+        src: usize,
+        value: Fp,
+    },
+    MulConstFq {
+        dst: usize,
+        src: usize,
+        value: Fq,
+    },
+    /// Multiplies an `Fp` register by an `Fq` constant and writes to an `Fq`
+    /// register
+    MulConstFpFq {
+        dst: usize,
+        src: usize,
+        value: Fq,
+    },
+    NegFp {
+        dst: usize,
+        src: usize,
+    },
+    NegFq {
+        dst: usize,
+        src: usize,
+    },
+    /// Inverts all evaluations. Zeros remain zero.
+    InvFp {
+        dst: usize,
+        src: usize,
+    },
+    InvFq {
+        dst: usize,
+        src: usize,
+    },
+    /// Evaluates X^exp. Neighbouring points of the LDE domain differ by a
+    /// factor of the domain generator so only the first point of each chunk is
+    /// exponentiated.
+    PowX {
+        dst: usize,
+        exp: usize,
+    },
+    PowFp {
+        dst: usize,
+        src: usize,
+        exp: usize,
+    },
+    PowFq {
+        dst: usize,
+        src: usize,
+        exp: usize,
+    },
+}
+
+/// Value of a compiled node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value<Fp, Fq> {
+    Constant(FieldVariant<Fp, Fq>),
+    Register(Register),
+}
+
+/// Trace and domain evaluations a [`Program`] is evaluated over
+pub struct Inputs<'a, Fp, Fq> {
+    /// Blowup between the trace and the LDE domain
+    pub lde_step: usize,
+    /// Points of the LDE domain in natural order
+    pub x_lde: &'a [Fp],
+    pub base_trace_lde_cols: &'a [&'a [Fp]],
+    pub extension_trace_lde_cols: Option<&'a [&'a [Fq]]>,
+    /// Evaluations of [`Program::periodic_columns`] over the LDE domain
+    pub periodic_column_ldes: &'a [FieldVariant<Vec<Fp>, Vec<Fq>>],
+}
+
+/// Linear list of instructions produced by [`Program::compile`]
+#[derive(Clone, Debug)]
+pub struct Program<Fp: 'static, Fq: 'static> {
+    instructions: Vec<Instruction<Fp, Fq>>,
+    num_fp_registers: usize,
+    num_fq_registers: usize,
+    periodic_columns: Vec<PeriodicColumn<FieldVariant<Fp, Fq>>>,
+    output: Value<Fp, Fq>,
+}
+
+impl<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>> Program<Fp, Fq> {
+    /// Lowers an expression to instructions. Challenges and hints are
+    /// substituted by their values. Registers are reused once the value they
+    /// hold has no remaining uses.
+    pub fn compile(
+        expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+        num_base_columns: usize,
+        challenges: &Challenges<Fq>,
+        hints: &[Fq],
+    ) -> Self {
+        let mut arena = ExprArena::new();
+        let root = arena.insert(expr);
+        let mut compiler = Compiler::new(arena.len());
+        for (i, node) in arena.nodes().iter().enumerate() {
+            for child in node.children() {
+                compiler.last_use[child.index()] = i;
+            }
+        }
+        compiler.last_use[root.index()] = usize::MAX;
+
+        for (i, node) in arena.nodes().iter().enumerate() {
+            let value = match *node {
+                Node::Leaf(ref leaf) => {
+                    compiler.leaf(leaf.clone(), num_base_columns, challenges, hints)
+                }
+                Node::Neg(a) => {
+                    let a = compiler.operand(a.index(), i);
+                    compiler.neg(a)
+                }
+                Node::Add(a, b) => {
+                    let a = compiler.operand(a.index(), i);
+                    let b = compiler.operand(b.index(), i);
+                    compiler.add(a, b)
+                }
+                Node::Mul(a, b) => {
+                    let a = compiler.operand(a.index(), i);
+                    let b = compiler.operand(b.index(), i);
+                    compiler.mul(a, b)
+                }
+                Node::Div(a, b) => {
+                    // the inverse is written to a new register while `a` is live
+                    let b = if a == b {
+                        compiler.values[b.index()]
+                    } else {
+                        compiler.operand(b.index(), i)
+                    };
+                    let b_inv = compiler.inv(b);
+                    let a = compiler.operand(a.index(), i);
+                    compiler.mul(a, b_inv)
+                }
+                Node::Pow(a, exp) if matches!(arena.node(a), Node::Leaf(AlgebraicItem::X)) => {
+                    compiler.operand(a.index(), i);
+                    compiler.pow_x(exp)
+                }
+                Node::Pow(a, exp) => {
+                    let a = compiler.operand(a.index(), i);
+                    compiler.pow(a, exp)
+                }
+            };
+            compiler.values.push(value);
+        }
+
+        Self {
+            instructions: compiler.instructions,
+            num_fp_registers: compiler.fp_registers.len,
+            num_fq_registers: compiler.fq_registers.len,
+            periodic_columns: compiler.periodic_columns,
+            output: compiler.values[root.index()],
+        }
+    }
+
+    pub fn instructions(&self) -> &[Instruction<Fp, Fq>] {
+        &self.instructions
+    }
+
+    /// Periodic columns in the order they are referenced by
+    /// [`Instruction::LoadPeriodic`]
+    pub fn periodic_columns(&self) -> &[PeriodicColumn<FieldVariant<Fp, Fq>>] {
+        &self.periodic_columns
+    }
+
+    pub const fn num_registers(&self) -> usize {
+        self.num_fp_registers + self.num_fq_registers
+    }
+
+    /// Evaluates the program over every point of the LDE domain
+    pub fn eval<const CHUNK_SIZE: usize>(&self, inputs: &Inputs<'_, Fp, Fq>, result: &mut [Fq]) {
+        cfg_chunks_mut!(result, CHUNK_SIZE)
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                let mut registers = Registers::<Fp, Fq, CHUNK_SIZE>::new(self);
+                self.eval_chunk(inputs, i * CHUNK_SIZE, &mut registers);
+                match self.output {
+                    Value::Constant(v) => chunk.fill(v.as_fq()),
+                    Value::Register(Register::Fp(r)) => {
+                        for (dst, src) in chunk.iter_mut().zip(&registers.fp[r]) {
+                            *dst = Fq::from(*src);
+                        }
+                    }
+                    Value::Register(Register::Fq(r)) => chunk.copy_from_slice(&registers.fq[r]),
+                }
+            });
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn eval_chunk<const N: usize>(
+        &self,
+        inputs: &Inputs<'_, Fp, Fq>,
+        chunk_offset: usize,
+        registers: &mut Registers<Fp, Fq, N>,
+    ) {
+        use Instruction::*;
+        let Registers { fp, fq } = registers;
+        let n = inputs.x_lde.len();
+        let position = |offset: isize| {
+            let shift = isize::try_from(inputs.lde_step).unwrap() * offset;
+            let chunk_offset = isize::try_from(chunk_offset).unwrap();
+            usize::try_from((chunk_offset + shift).rem_euclid(isize::try_from(n).unwrap())).unwrap()
+        };
+        for instruction in &self.instructions {
+            match *instruction {
+                LoadX { dst } => fp[dst] = *extract_lde_chunk(inputs.x_lde, chunk_offset),
+                LoadBase { dst, col, offset } => {
+                    let column = inputs.base_trace_lde_cols[col];
+                    fp[dst] = *extract_lde_chunk(column, position(offset));
+                }
+                LoadExtension { dst, col, offset } => {
+                    let column = inputs.extension_trace_lde_cols.unwrap()[col];
+                    fq[dst] = *extract_lde_chunk(column, position(offset));
+                }
+                LoadPeriodic { dst, col } => match (dst, &inputs.periodic_column_ldes[col]) {
+                    (Register::Fp(dst), FieldVariant::Fp(lde)) => {
+                        fp[dst] = *extract_lde_chunk(lde, chunk_offset);
+                    }
+                    (Register::Fq(dst), FieldVariant::Fq(lde)) => {
+                        fq[dst] = *extract_lde_chunk(lde, chunk_offset);
+                    }
+                    _ => panic!("periodic column {col} has the wrong field"),
+                },
+                AddFp { dst, lhs, rhs } => binary(fp, dst, lhs, rhs, |a, b| a + b),
+                AddFq { dst, lhs, rhs } => binary(fq, dst, lhs, rhs, |a, b| a + b),
+                AddFqFp { dst, lhs, rhs } => {
+                    let rhs = &fp[rhs];
+                    unary(fq, dst, lhs, |a, i| a + rhs[i]);
+                }
+                AddConstFp { dst, src, value } => unary(fp, dst, src, |a, _| a + value),
+                AddConstFq { dst, src, value } => unary(fq, dst, src, |a, _| a + value),
+                AddConstFpFq { dst, src, value } => {
+                    for (v, src) in fq[dst].iter_mut().zip(&fp[src]) {
+                        *v = value + src;
+                    }
+                }
+                MulFp { dst, lhs, rhs } => binary(fp, dst, lhs, rhs, |a, b| a * b),
+                MulFq { dst, lhs, rhs } => binary(fq, dst, lhs, rhs, |a, b| a * b),
+                MulFqFp { dst, lhs, rhs } => {
+                    let rhs = &fp[rhs];
+                    unary(fq, dst, lhs, |a, i| a * rhs[i]);
+                }
+                MulConstFp { dst, src, value } => unary(fp, dst, src, |a, _| a * value),
+                MulConstFq { dst, src, value } => unary(fq, dst, src, |a, _| a * value),
+                MulConstFpFq { dst, src, value } => {
+                    for (v, src) in fq[dst].iter_mut().zip(&fp[src]) {
+                        *v = value * src;
+                    }
+                }
+                NegFp { dst, src } => unary(fp, dst, src, |a, _| -a),
+                NegFq { dst, src } => unary(fq, dst, src, |a, _| -a),
+                InvFp { dst, src } => {
+                    fp[dst] = fp[src];
+                    batch_inversion(&mut fp[dst]);
+                }
+                InvFq { dst, src } => {
+                    fq[dst] = fq[src];
+                    batch_inversion(&mut fq[dst]);
+                }
+                PowX { dst, exp } => {
+                    let x = extract_lde_chunk::<Fp, N>(inputs.x_lde, chunk_offset);
+                    let generator = x[1 % N] / x[0];
+                    let step = generator.pow([exp as u64]);
+                    let mut acc = x[0].pow([exp as u64]);
+                    for v in &mut fp[dst] {
+                        *v = acc;
+                        acc *= step;
+                    }
+                }
+                PowFp { dst, src, exp } => unary(fp, dst, src, |a, _| a.pow([exp as u64])),
+                PowFq { dst, src, exp } => unary(fq, dst, src, |a, _| a.pow([exp as u64])),
+            }
+        }
+    }
+}
+
+/// Writes `op(src[i], i)` to `dst[i]` for every element of register `dst`
+fn unary<F: Copy, const N: usize>(
+    registers: &mut [[F; N]],
+    dst: usize,
+    src: usize,
+    op: impl Fn(F, usize) -> F,
+) {
+    if dst == src {
+        for (i, v) in registers[dst].iter_mut().enumerate() {
+            *v = op(*v, i);
+        }
+    } else {
+        let (dst, src) = split(registers, dst, src);
+        for (i, (v, src)) in dst.iter_mut().zip(src).enumerate() {
+            *v = op(*src, i);
+        }
+    }
+}
+
+/// Writes `op(lhs[i], rhs[i])` to `dst[i]` for every element of register `dst`
+fn binary<F: Copy, const N: usize>(
+    registers: &mut [[F; N]],
+    dst: usize,
+    lhs: usize,
+    rhs: usize,
+    op: impl Fn(F, F) -> F,
+) {
+    if lhs == rhs {
+        unary(registers, dst, lhs, |a, _| op(a, a));
+    } else if dst == lhs {
+        let (dst, rhs) = split(registers, dst, rhs);
+        for (v, rhs) in dst.iter_mut().zip(rhs) {
+            *v = op(*v, *rhs);
+        }
+    } else if dst == rhs {
+        let (dst, lhs) = split(registers, dst, lhs);
+        for (v, lhs) in dst.iter_mut().zip(lhs) {
+            *v = op(*lhs, *v);
+        }
+    } else {
+        let rhs = registers[rhs];
+        unary(registers, dst, lhs, |a, i| op(a, rhs[i]));
+    }
+}
+
+/// Borrows register `dst` mutably alongside a different register `src`
+fn split<T>(registers: &mut [T], dst: usize, src: usize) -> (&mut T, &T) {
+    debug_assert_ne!(dst, src);
+    if dst < src {
+        let (head, tail) = registers.split_at_mut(src);
+        (&mut head[dst], &tail[0])
+    } else {
+        let (head, tail) = registers.split_at_mut(dst);
+        (&mut tail[0], &head[src])
+    }
+}
+
+struct Registers<Fp, Fq, const N: usize> {
+    fp: Vec<[Fp; N]>,
+    fq: Vec<[Fq; N]>,
+}
+
+impl<Fp: Field, Fq: Field, const N: usize> Registers<Fp, Fq, N> {
+    fn new(program: &Program<Fp, Fq>) -> Self {
+        Self {
+            fp: vec![[Fp::ZERO; N]; program.num_fp_registers],
+            fq: vec![[Fq::ZERO; N]; program.num_fq_registers],
+        }
+    }
+}
+
+/// Hands out registers and takes back registers that are no longer used
+#[derive(Default)]
+struct RegisterAllocator {
+    len: usize,
+    free: Vec<usize>,
+}
+
+impl RegisterAllocator {
+    fn alloc(&mut self) -> usize {
+        self.free.pop().unwrap_or_else(|| {
+            self.len += 1;
+            self.len - 1
+        })
+    }
+}
+
+struct Compiler<Fp: 'static, Fq: 'static> {
+    instructions: Vec<Instruction<Fp, Fq>>,
+    values: Vec<Value<Fp, Fq>>,
+    /// Index of the last node that uses each node
+    last_use: Vec<usize>,
+    fp_registers: RegisterAllocator,
+    fq_registers: RegisterAllocator,
+    periodic_columns: Vec<PeriodicColumn<FieldVariant<Fp, Fq>>>,
+}
+
+impl<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>> Compiler<Fp, Fq> {
+    fn new(num_nodes: usize) -> Self {
+        Self {
+            instructions: Vec::new(),
+            values: Vec::with_capacity(num_nodes),
+            last_use: vec![0; num_nodes],
+            fp_registers: RegisterAllocator::default(),
+            fq_registers: RegisterAllocator::default(),
+            periodic_columns: Vec::new(),
+        }
+    }
+
+    /// Returns the value of a child of node `parent`. Registers are freed after
+    /// their last use so the result of `parent` can be written to them. This
+    /// is safe since instructions operate element by element.
+    fn operand(&mut self, child: usize, parent: usize) -> Value<Fp, Fq> {
+        let value = self.values[child];
+        if self.last_use[child] == parent {
+            // prevent freeing the register twice e.g. for `a * a`
+            self.last_use[child] = usize::MAX;
+            if let Value::Register(register) = value {
+                self.free(register);
+            }
+        }
+        value
+    }
+
+    fn free(&mut self, register: Register) {
+        match register {
+            Register::Fp(r) => self.fp_registers.free.push(r),
+            Register::Fq(r) => self.fq_registers.free.push(r),
+        }
+    }
+
+    fn leaf(
+        &mut self,
+        leaf: AlgebraicItem<FieldVariant<Fp, Fq>>,
+        num_base_columns: usize,
+        challenges: &Challenges<Fq>,
+        hints: &[Fq],
+    ) -> Value<Fp, Fq> {
+        match leaf {
+            AlgebraicItem::Constant(v) => Value::Constant(v),
+            AlgebraicItem::Challenge(round, i) => {
+                Value::Constant(FieldVariant::Fq(challenges[(round, i)]))
+            }
+            AlgebraicItem::Hint(i) => Value::Constant(FieldVariant::Fq(hints[i])),
+            AlgebraicItem::X => {
+                let dst = self.fp_registers.alloc();
+                self.instructions.push(Instruction::LoadX { dst });
+                Value::Register(Register::Fp(dst))
+            }
+            AlgebraicItem::Trace(col, offset) if col < num_base_columns => {
+                let dst = self.fp_registers.alloc();
+                self.instructions
+                    .push(Instruction::LoadBase { dst, col, offset });
+                Value::Register(Register::Fp(dst))
+            }
+            AlgebraicItem::Trace(col, offset) => {
+                let dst = self.fq_registers.alloc();
+                let col = col - num_base_columns;
+                self.instructions
+                    .push(Instruction::LoadExtension { dst, col, offset });
+                Value::Register(Register::Fq(dst))
+            }
+            AlgebraicItem::Periodic(column) => {
+                let is_fp = column
+                    .coeffs()
+                    .iter()
+                    .all(|v| matches!(v, FieldVariant::Fp(_)));
+                let col = self
+                    .periodic_columns
+                    .iter()
+                    .position(|c| *c == column)
+                    .unwrap_or_else(|| {
+                        self.periodic_columns.push(column);
+                        self.periodic_columns.len() - 1
+                    });
+                let dst = if is_fp {
+                    Register::Fp(self.fp_registers.alloc())
+                } else {
+                    Register::Fq(self.fq_registers.alloc())
+                };
+                self.instructions
+                    .push(Instruction::LoadPeriodic { dst, col });
+                Value::Register(dst)
+            }
+        }
+    }
+
+    fn neg(&mut self, a: Value<Fp, Fq>) -> Value<Fp, Fq> {
+        match a {
+            Value::Constant(v) => Value::Constant(-v),
+            Value::Register(Register::Fp(src)) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions.push(Instruction::NegFp { dst, src });
+                Value::Register(Register::Fp(dst))
+            }
+            Value::Register(Register::Fq(src)) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions.push(Instruction::NegFq { dst, src });
+                Value::Register(Register::Fq(dst))
+            }
+        }
+    }
+
+    fn add(&mut self, a: Value<Fp, Fq>, b: Value<Fp, Fq>) -> Value<Fp, Fq> {
+        match (a, b) {
+            (Value::Constant(a), Value::Constant(b)) => Value::Constant(a + b),
+            (Value::Register(Register::Fp(src)), Value::Constant(FieldVariant::Fp(value)))
+            | (Value::Constant(FieldVariant::Fp(value)), Value::Register(Register::Fp(src))) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions
+                    .push(Instruction::AddConstFp { dst, src, value });
+                Value::Register(Register::Fp(dst))
+            }
+            (Value::Register(Register::Fp(src)), Value::Constant(FieldVariant::Fq(value)))
+            | (Value::Constant(FieldVariant::Fq(value)), Value::Register(Register::Fp(src))) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions
+                    .push(Instruction::AddConstFpFq { dst, src, value });
+                Value::Register(Register::Fq(dst))
+            }
+            (Value::Register(Register::Fq(src)), Value::Constant(value))
+            | (Value::Constant(value), Value::Register(Register::Fq(src))) => {
+                let dst = self.fq_registers.alloc();
+                let value = value.as_fq();
+                self.instructions
+                    .push(Instruction::AddConstFq { dst, src, value });
+                Value::Register(Register::Fq(dst))
+            }
+            (Value::Register(Register::Fp(lhs)), Value::Register(Register::Fp(rhs))) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions.push(Instruction::AddFp { dst, lhs, rhs });
+                Value::Register(Register::Fp(dst))
+            }
+            (Value::Register(Register::Fq(lhs)), Value::Register(Register::Fq(rhs))) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions.push(Instruction::AddFq { dst, lhs, rhs });
+                Value::Register(Register::Fq(dst))
+            }
+            (Value::Register(Register::Fq(lhs)), Value::Register(Register::Fp(rhs)))
+            | (Value::Register(Register::Fp(rhs)), Value::Register(Register::Fq(lhs))) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions
+                    .push(Instruction::AddFqFp { dst, lhs, rhs });
+                Value::Register(Register::Fq(dst))
+            }
+        }
+    }
+
+    fn mul(&mut self, a: Value<Fp, Fq>, b: Value<Fp, Fq>) -> Value<Fp, Fq> {
+        match (a, b) {
+            (Value::Constant(a), Value::Constant(b)) => Value::Constant(a * b),
+            (Value::Register(Register::Fp(src)), Value::Constant(FieldVariant::Fp(value)))
+            | (Value::Constant(FieldVariant::Fp(value)), Value::Register(Register::Fp(src))) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions
+                    .push(Instruction::MulConstFp { dst, src, value });
+                Value::Register(Register::Fp(dst))
+            }
+            (Value::Register(Register::Fp(src)), Value::Constant(FieldVariant::Fq(value)))
+            | (Value::Constant(FieldVariant::Fq(value)), Value::Register(Register::Fp(src))) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions
+                    .push(Instruction::MulConstFpFq { dst, src, value });
+                Value::Register(Register::Fq(dst))
+            }
+            (Value::Register(Register::Fq(src)), Value::Constant(value))
+            | (Value::Constant(value), Value::Register(Register::Fq(src))) => {
+                let dst = self.fq_registers.alloc();
+                let value = value.as_fq();
+                self.instructions
+                    .push(Instruction::MulConstFq { dst, src, value });
+                Value::Register(Register::Fq(dst))
+            }
+            (Value::Register(Register::Fp(lhs)), Value::Register(Register::Fp(rhs))) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions.push(Instruction::MulFp { dst, lhs, rhs });
+                Value::Register(Register::Fp(dst))
+            }
+            (Value::Register(Register::Fq(lhs)), Value::Register(Register::Fq(rhs))) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions.push(Instruction::MulFq { dst, lhs, rhs });
+                Value::Register(Register::Fq(dst))
+            }
+            (Value::Register(Register::Fq(lhs)), Value::Register(Register::Fp(rhs)))
+            | (Value::Register(Register::Fp(rhs)), Value::Register(Register::Fq(lhs))) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions
+                    .push(Instruction::MulFqFp { dst, lhs, rhs });
+                Value::Register(Register::Fq(dst))
+            }
+        }
+    }
+
+    /// Returns the inverse of a value. The returned register is a temporary
+    /// that is freed once it is consumed by [`Compiler::mul`].
+    fn inv(&mut self, a: Value<Fp, Fq>) -> Value<Fp, Fq> {
+        let value = match a {
+            Value::Constant(v) => return Value::Constant(v.inverse().expect("division by zero")),
+            Value::Register(Register::Fp(src)) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions.push(Instruction::InvFp { dst, src });
+                Value::Register(Register::Fp(dst))
+            }
+            Value::Register(Register::Fq(src)) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions.push(Instruction::InvFq { dst, src });
+                Value::Register(Register::Fq(dst))
+            }
+        };
+        if let Value::Register(register) = value {
+            self.free(register);
+        }
+        value
+    }
+
+    fn pow_x(&mut self, exp: usize) -> Value<Fp, Fq> {
+        let dst = self.fp_registers.alloc();
+        self.instructions.push(Instruction::PowX { dst, exp });
+        Value::Register(Register::Fp(dst))
+    }
+
+    fn pow(&mut self, a: Value<Fp, Fq>, exp: usize) -> Value<Fp, Fq> {
+        match a {
+            Value::Constant(v) => Value::Constant(v.pow([exp as u64])),
+            Value::Register(Register::Fp(src)) => {
+                let dst = self.fp_registers.alloc();
+                self.instructions.push(Instruction::PowFp { dst, src, exp });
+                Value::Register(Register::Fp(dst))
+            }
+            Value::Register(Register::Fq(src)) => {
+                let dst = self.fq_registers.alloc();
+                self.instructions.push(Instruction::PowFq { dst, src, exp });
+                Value::Register(Register::Fq(dst))
+            }
+        }
+    }
+}
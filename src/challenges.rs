@@ -3,23 +3,39 @@ use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
-use core::ops::Deref;
 use core::ops::Index;
 
+/// Verifier challenges grouped by the round they were drawn in. Round `i` is
+/// drawn after trace segment `i` is committed to.
 #[derive(Default, Clone, Debug, CanonicalDeserialize, CanonicalSerialize)]
-pub struct Challenges<F: Field>(Vec<F>);
+pub struct Challenges<F: Field>(Vec<Vec<F>>);
 
 impl<F: Field> Challenges<F> {
+    /// Challenges drawn in a single round
     pub fn new(challenges: Vec<F>) -> Self {
-        Self(challenges)
+        Self(vec![challenges])
+    }
+
+    pub const fn from_rounds(rounds: Vec<Vec<F>>) -> Self {
+        Self(rounds)
+    }
+
+    pub fn push_round(&mut self, challenges: Vec<F>) {
+        self.0.push(challenges);
+    }
+
+    pub const fn num_rounds(&self) -> usize {
+        self.0.len()
     }
-}
 
-impl<F: Field> Deref for Challenges<F> {
-    type Target = Vec<F>;
+    /// Returns the challenges drawn in the given round
+    pub fn round(&self, round: usize) -> &[F] {
+        &self.0[round]
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    /// Returns the challenges of every round in the order they were drawn
+    pub fn iter(&self) -> impl Iterator<Item = &F> {
+        self.0.iter().flatten()
     }
 }
 
@@ -27,6 +43,6 @@ impl<F: Field, C: VerifierChallenge> Index<C> for Challenges<F> {
     type Output = F;
 
     fn index(&self, challenge: C) -> &Self::Output {
-        &self.0[challenge.index()]
+        &self.0[challenge.round()][challenge.index()]
     }
 }
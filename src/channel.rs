@@ -2,6 +2,8 @@ use crate::challenges::Challenges;
 use crate::fri;
 use crate::fri::FriProof;
 use crate::hints::Hints;
+use crate::merkle::MerkleCap;
+use crate::prover::ProvingError;
 use crate::random::PublicCoin;
 use crate::stark::Stark;
 use crate::trace::Queries;
@@ -16,10 +18,10 @@ use std::collections::BTreeSet;
 pub struct ProverChannel<'a, S: Stark> {
     air: &'a Air<S::AirConfig>,
     pub public_coin: S::PublicCoin,
-    base_trace_commitment: S::Digest,
-    extension_trace_commitment: Option<S::Digest>,
-    composition_trace_commitment: S::Digest,
-    fri_layer_commitments: Vec<S::Digest>,
+    base_trace_commitment: MerkleCap<S::Digest>,
+    extension_trace_commitments: Vec<MerkleCap<S::Digest>>,
+    composition_trace_commitment: MerkleCap<S::Digest>,
+    fri_layer_commitments: Vec<MerkleCap<S::Digest>>,
     fri_remainder_coeffs: Vec<S::Fq>,
     execution_trace_ood_evals: Vec<S::Fq>,
     composition_trace_ood_evals: Vec<S::Fq>,
@@ -32,9 +34,9 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         ProverChannel {
             air,
             public_coin,
-            extension_trace_commitment: None,
-            base_trace_commitment: S::Digest::default(),
-            composition_trace_commitment: S::Digest::default(),
+            extension_trace_commitments: Vec::new(),
+            base_trace_commitment: MerkleCap::default(),
+            composition_trace_commitment: MerkleCap::default(),
             execution_trace_ood_evals: Vec::new(),
             composition_trace_ood_evals: Vec::new(),
             fri_layer_commitments: Vec::new(),
@@ -43,18 +45,25 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         }
     }
 
-    pub fn commit_base_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+    /// Binds the proof to the preprocessed columns. The commitment is part of
+    /// the verification key so isn't included in the proof.
+    pub fn commit_preprocessed_trace(&mut self, commitment: &MerkleCap<S::Digest>) {
+        self.public_coin.reseed_with_cap(commitment);
+    }
+
+    pub fn commit_base_trace(&mut self, commitment: MerkleCap<S::Digest>) {
+        self.public_coin.reseed_with_cap(&commitment);
         self.base_trace_commitment = commitment;
     }
 
-    pub fn commit_extension_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
-        self.extension_trace_commitment = Some(commitment);
+    /// Commits to the next extension trace segment
+    pub fn commit_extension_trace(&mut self, commitment: MerkleCap<S::Digest>) {
+        self.public_coin.reseed_with_cap(&commitment);
+        self.extension_trace_commitments.push(commitment);
     }
 
-    pub fn commit_composition_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+    pub fn commit_composition_trace(&mut self, commitment: MerkleCap<S::Digest>) {
+        self.public_coin.reseed_with_cap(&commitment);
         self.composition_trace_commitment = commitment;
     }
 
@@ -73,23 +82,27 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         self.composition_trace_ood_evals = composition_trace_oods;
     }
 
-    pub fn grind_fri_commitments(&mut self) {
+    /// # Errors
+    ///
+    /// Returns an error if no nonce satisfies the grinding factor.
+    pub fn grind_fri_commitments(&mut self) -> Result<(), ProvingError> {
         let grinding_factor = self.air.options().grinding_factor;
         if grinding_factor == 0 {
             // skip if there is no grinding required
-            return;
+            return Ok(());
         }
 
         let nonce = self
             .public_coin
             .grind_proof_of_work(grinding_factor)
-            .expect("nonce not found");
-        assert!(self
+            .ok_or(ProvingError::GrindingExhausted { grinding_factor })?;
+        debug_assert!(self
             .public_coin
             .verify_proof_of_work(grinding_factor, nonce));
 
         self.pow_nonce = nonce;
         self.public_coin.reseed_with_int(self.pow_nonce);
+        Ok(())
     }
 
     pub fn get_fri_query_positions(&mut self) -> BTreeSet<usize> {
@@ -108,7 +121,7 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
             options: self.air.options(),
             trace_len: self.air.trace_len(),
             base_trace_commitment: self.base_trace_commitment,
-            extension_trace_commitment: self.extension_trace_commitment,
+            extension_trace_commitments: self.extension_trace_commitments,
             composition_trace_commitment: self.composition_trace_commitment,
             execution_trace_ood_evals: self.execution_trace_ood_evals,
             composition_trace_ood_evals: self.composition_trace_ood_evals,
@@ -125,8 +138,8 @@ impl<'a, S: Stark> fri::ProverChannel for ProverChannel<'a, S> {
     type Digest = S::Digest;
     type Field = S::Fq;
 
-    fn commit_fri_layer(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+    fn commit_fri_layer(&mut self, commitment: MerkleCap<S::Digest>) {
+        self.public_coin.reseed_with_cap(&commitment);
         self.fri_layer_commitments.push(commitment);
     }
 
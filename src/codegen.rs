@@ -0,0 +1,721 @@
+//! Generates Rust code that evaluates the composition constraint of an AIR.
+//!
+//! Generated code is specialized to the constraints and trace length of an
+//! [`Air`] so the compiler can optimize the evaluation like hand written code.
+//! The constraints remain the single source of truth since the code is
+//! generated from them e.g. in a build script:
+//!
+//! ```ignore
+//! let air = Air::<MyAirConfig>::new(trace_len, public_inputs, options)?;
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{out_dir}/my_air.rs"), ministark::codegen::generate(&air))?;
+//! ```
+//!
+//! The module is included with `include!(concat!(env!("OUT_DIR"),
+//! "/my_air.rs"))` and selected by returning `evaluator()` from
+//! [`AirConfig::generated_evaluator`]. Constants and periodic columns are
+//! inputs of the generated code so it can be reused for AIRs that only differ
+//! in their values e.g. assertions on public inputs. Generated code is only
+//! used while it matches the shape of the composition constraint. Otherwise
+//! constraints are evaluated by the interpreter and [`Air::lints`] reports the
+//! generated code is out of date.
+
+use crate::air::AirConfig;
+use crate::bytecode::Inputs;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::CompositionItem;
+use crate::constraints::PeriodicColumn;
+use crate::eval_cpu::extract_lde_chunk;
+use crate::expression::Expr;
+use crate::expression::ExprArena;
+use crate::expression::Node;
+use crate::expression::NodeId;
+use crate::utils::FieldVariant;
+use crate::Air;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_std::cfg_chunks_mut;
+use core::fmt::Write;
+use core::hash::Hash;
+use core::hash::Hasher;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of evaluations generated LDE evaluators compute at a time
+pub const CHUNK_SIZE: usize = 512;
+
+/// Inputs of a generated LDE evaluator
+pub struct LdeInputs<'a, Fp, Fq: Field> {
+    pub challenges: &'a Challenges<Fq>,
+    pub hints: &'a [Fq],
+    pub composition_coeffs: &'a [Fq],
+    /// Constants ordered by [`constants`]
+    pub constants: &'a [FieldVariant<Fp, Fq>],
+    /// Periodic columns are ordered by [`periodic_columns`]
+    pub lde: Inputs<'a, Fp, Fq>,
+}
+
+/// Inputs of a generated out-of-domain evaluator
+pub struct OodInputs<'a, Fq: Field> {
+    pub x: Fq,
+    pub challenges: &'a Challenges<Fq>,
+    pub hints: &'a [Fq],
+    pub composition_coeffs: &'a [Fq],
+    /// Constants ordered by [`constants`]
+    pub constants: &'a [Fq],
+    /// Evaluations of trace polynomials by column and row offset
+    pub trace_ood_evals: &'a BTreeMap<(usize, isize), Fq>,
+    /// Evaluations of periodic columns ordered by [`periodic_columns`]
+    pub periodic_evals: &'a [Fq],
+}
+
+/// Entry points of a module generated by [`generate`]
+#[derive(Clone, Copy)]
+pub struct GeneratedEvaluator<Fp, Fq: Field> {
+    /// [`fingerprint`] of the composition constraint the code was generated
+    /// from
+    pub fingerprint: u64,
+    /// Evaluates the composition constraint over the LDE domain
+    pub eval_lde: fn(&LdeInputs<'_, Fp, Fq>, &mut [Fq]),
+    /// Evaluates the composition constraint at the out-of-domain point
+    pub eval_ood: fn(&OodInputs<'_, Fq>) -> Fq,
+}
+
+/// Extracts the chunk of an LDE starting at `chunk_offset` shifted by
+/// `row_offset` trace rows
+pub fn lde_chunk<F: Field, const N: usize>(
+    lde: &[F],
+    lde_step: usize,
+    chunk_offset: usize,
+    row_offset: isize,
+) -> Cow<'_, [F; N]> {
+    let n = isize::try_from(lde.len()).unwrap();
+    let shift = isize::try_from(lde_step).unwrap() * row_offset;
+    let position = (isize::try_from(chunk_offset).unwrap() + shift).rem_euclid(n);
+    extract_lde_chunk(lde, usize::try_from(position).unwrap())
+}
+
+/// Returns `[first, first * ratio, first * ratio^2, ...]`
+pub fn geometric<F: Field, const N: usize>(first: F, ratio: F) -> [F; N] {
+    let mut acc = first;
+    [(); N].map(|()| {
+        let value = acc;
+        acc *= ratio;
+        value
+    })
+}
+
+/// Calls `f` with the offset of every chunk of `result`
+pub fn for_each_chunk<F: Field, const N: usize>(
+    result: &mut [F],
+    f: impl Fn(usize, &mut [F; N]) + Send + Sync,
+) {
+    cfg_chunks_mut!(result, N)
+        .enumerate()
+        .for_each(|(i, chunk)| f(i * N, chunk.try_into().unwrap()));
+}
+
+/// Periodic columns of an expression in the order generated code expects
+/// their evaluations
+pub fn periodic_columns<Fp: Copy + Eq + Hash, Fq: Copy + Eq + Hash>(
+    expr: &Expr<CompositionItem<FieldVariant<Fp, Fq>>>,
+) -> Vec<PeriodicColumn<FieldVariant<Fp, Fq>>> {
+    let mut arena = ExprArena::new();
+    arena.insert(expr);
+    let mut columns = Vec::new();
+    for node in arena.nodes() {
+        if let Node::Leaf(CompositionItem::Item(AlgebraicItem::Periodic(col))) = node {
+            if !columns.contains(col) {
+                columns.push(col.clone());
+            }
+        }
+    }
+    columns
+}
+
+/// Constants of an expression in the order generated code expects them
+pub fn constants<Fp: Copy + Eq + Hash, Fq: Copy + Eq + Hash>(
+    expr: &Expr<CompositionItem<FieldVariant<Fp, Fq>>>,
+) -> Vec<FieldVariant<Fp, Fq>> {
+    let mut arena = ExprArena::new();
+    arena.insert(expr);
+    arena
+        .nodes()
+        .iter()
+        .filter_map(|node| match *node {
+            Node::Leaf(CompositionItem::Item(AlgebraicItem::Constant(v))) => Some(v),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Hash of the shape of an expression that is stable across builds. Values of
+/// constants and periodic columns are inputs of generated code so only their
+/// field and size are hashed.
+pub fn fingerprint<Fp: Copy + Eq + Hash, Fq: Copy + Eq + Hash>(
+    expr: &Expr<CompositionItem<FieldVariant<Fp, Fq>>>,
+) -> u64 {
+    let mut arena = ExprArena::new();
+    arena.insert(expr);
+    let mut hasher = Fnv1a::default();
+    for node in arena.nodes() {
+        match node {
+            Node::Leaf(CompositionItem::Item(AlgebraicItem::Constant(v))) => {
+                hasher.write(b"constant");
+                matches!(v, FieldVariant::Fp(_)).hash(&mut hasher);
+            }
+            Node::Leaf(CompositionItem::Item(AlgebraicItem::Periodic(col))) => {
+                hasher.write(b"periodic");
+                col.interval_size().hash(&mut hasher);
+                col.coeffs().len().hash(&mut hasher);
+                is_fp_column(col).hash(&mut hasher);
+            }
+            node => node.hash(&mut hasher),
+        }
+    }
+    hasher.finish()
+}
+
+/// 64-bit FNV-1a. Unlike the standard library's hasher the output is
+/// specified and doesn't change between Rust versions.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+}
+
+/// Generates a module that evaluates the composition constraint of `air` over
+/// the LDE domain for the prover and at the out-of-domain point for the
+/// verifier
+pub fn generate<C: AirConfig>(air: &Air<C>) -> String {
+    // preprocessed columns are evaluated alongside the base columns
+    let num_base_columns = C::NUM_BASE_COLUMNS + C::NUM_PREPROCESSED_COLUMNS;
+    Generator::new(air.composition_constraint(), num_base_columns).module()
+}
+
+const BOUNDS: &str = "where
+    Fp: ::ministark_gpu::GpuFftField<FftField = Fp> + ::ark_ff::FftField,
+    Fq: ::ministark::StarkExtensionOf<Fp>,
+";
+
+const ALLOW: &str = "#[allow(clippy::all, clippy::pedantic, clippy::nursery)]";
+
+struct Generator<Fp: 'static, Fq: 'static> {
+    arena: ExprArena<CompositionItem<FieldVariant<Fp, Fq>>>,
+    root: NodeId,
+    num_base_columns: usize,
+    fingerprint: u64,
+    periodic_columns: Vec<PeriodicColumn<FieldVariant<Fp, Fq>>>,
+    /// Index into [`constants`] of each constant node
+    constant_indices: BTreeMap<usize, usize>,
+    /// Nodes that depend on the point of evaluation
+    is_varying: Vec<bool>,
+    /// Nodes that evaluate to base field elements
+    is_fp: Vec<bool>,
+    /// Number of batch inversions the value of a node depends on. Evaluations
+    /// over the LDE domain are computed in one pass per level.
+    level: Vec<usize>,
+}
+
+impl<Fp: Field, Fq: Field> Generator<Fp, Fq> {
+    fn new(expr: &Expr<CompositionItem<FieldVariant<Fp, Fq>>>, num_base_columns: usize) -> Self {
+        let mut arena = ExprArena::new();
+        let root = arena.insert(expr);
+        let constant_nodes = arena.nodes().iter().enumerate().filter(|(_, node)| {
+            matches!(
+                node,
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Constant(_)))
+            )
+        });
+        let constant_indices = constant_nodes
+            .enumerate()
+            .map(|(k, (i, _))| (i, k))
+            .collect();
+        let mut generator = Self {
+            root,
+            num_base_columns,
+            fingerprint: fingerprint(expr),
+            periodic_columns: periodic_columns(expr),
+            constant_indices,
+            is_varying: Vec::with_capacity(arena.len()),
+            is_fp: Vec::with_capacity(arena.len()),
+            level: Vec::with_capacity(arena.len()),
+            arena,
+        };
+        for i in 0..generator.arena.len() {
+            let node = &generator.arena.nodes()[i];
+            let (is_varying, is_fp) = match node {
+                Node::Leaf(CompositionItem::CompositionCoeff(_)) => (false, false),
+                Node::Leaf(CompositionItem::Item(item)) => match item {
+                    AlgebraicItem::X => (true, true),
+                    AlgebraicItem::Constant(v) => (false, matches!(v, FieldVariant::Fp(_))),
+                    AlgebraicItem::Challenge(..) | AlgebraicItem::Hint(_) => (false, false),
+                    &AlgebraicItem::Trace(col, _) => (true, col < num_base_columns),
+                    AlgebraicItem::Periodic(col) => (true, is_fp_column(col)),
+                },
+                _ => (
+                    node.children().any(|c| generator.is_varying[c.index()]),
+                    node.children().all(|c| generator.is_fp[c.index()]),
+                ),
+            };
+            let level = match *node {
+                Node::Div(a, b) if generator.is_varying[b.index()] => {
+                    generator.level[a.index()].max(generator.level[b.index()] + 1)
+                }
+                _ => node
+                    .children()
+                    .map(|c| generator.level[c.index()])
+                    .max()
+                    .unwrap_or(0),
+            };
+            generator.is_varying.push(is_varying);
+            generator.is_fp.push(is_fp);
+            generator.level.push(level);
+        }
+        generator
+    }
+
+    fn module(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "// @generated by ministark::codegen. Do not edit.").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "pub const FINGERPRINT: u64 = {:#018x};",
+            self.fingerprint
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "pub fn evaluator<Fp, Fq>() -> ::ministark::codegen::GeneratedEvaluator<Fp, Fq>\n{BOUNDS}{{"
+        )
+        .unwrap();
+        writeln!(out, "    ::ministark::codegen::GeneratedEvaluator {{").unwrap();
+        writeln!(out, "        fingerprint: FINGERPRINT,").unwrap();
+        writeln!(out, "        eval_lde: eval_lde::<Fp, Fq>,").unwrap();
+        writeln!(out, "        eval_ood: eval_ood::<Fp, Fq>,").unwrap();
+        writeln!(out, "    }}\n}}\n").unwrap();
+        self.write_lde_evaluator(&mut out);
+        writeln!(out).unwrap();
+        self.write_ood_evaluator(&mut out);
+        out
+    }
+
+    fn ty(&self, id: usize) -> &'static str {
+        if self.is_fp[id] {
+            "Fp"
+        } else {
+            "Fq"
+        }
+    }
+
+    fn is_x(&self, id: NodeId) -> bool {
+        matches!(
+            self.arena.node(id),
+            Node::Leaf(CompositionItem::Item(AlgebraicItem::X))
+        )
+    }
+
+    /// Adds or multiplies two values. The extension field operand goes on the
+    /// left of mixed operations.
+    fn binary(&self, a: usize, a_value: &str, b: usize, b_value: &str, op: char) -> String {
+        if self.is_fp[a] && !self.is_fp[b] {
+            format!("{b_value} {op} {a_value}")
+        } else {
+            format!("{a_value} {op} {b_value}")
+        }
+    }
+
+    /// Marks the nodes that need to be evaluated for every point in the pass
+    /// that computes `id`
+    fn mark(&self, id: NodeId, needed: &mut [bool]) {
+        if !self.is_varying[id.index()] || needed[id.index()] {
+            return;
+        }
+        needed[id.index()] = true;
+        match *self.arena.node(id) {
+            // the denominator is inverted in an earlier pass
+            Node::Div(a, _) => self.mark(a, needed),
+            Node::Pow(a, _) if self.is_x(a) => {}
+            ref node => node.children().for_each(|c| self.mark(c, needed)),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn write_lde_evaluator(&self, out: &mut String) {
+        let nodes = self.arena.nodes();
+        let root = self.root.index();
+
+        writeln!(
+            out,
+            "/// Evaluates the composition constraint over the LDE domain"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "pub fn eval_lde<Fp, Fq>(inputs: &::ministark::codegen::LdeInputs<'_, Fp, Fq>, result: &mut [Fq])\n{BOUNDS}{{"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "    if result.len() >= ::ministark::codegen::CHUNK_SIZE {{"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        eval_lde_chunks::<Fp, Fq, {{ ::ministark::codegen::CHUNK_SIZE }}>(inputs, result)"
+        )
+        .unwrap();
+        writeln!(out, "    }} else {{").unwrap();
+        writeln!(out, "        eval_lde_chunks::<Fp, Fq, 1>(inputs, result)").unwrap();
+        writeln!(out, "    }}\n}}\n").unwrap();
+
+        writeln!(out, "{ALLOW}").unwrap();
+        writeln!(
+            out,
+            "fn eval_lde_chunks<Fp, Fq, const N: usize>(\n    inputs: &::ministark::codegen::LdeInputs<'_, Fp, Fq>,\n    result: &mut [Fq],\n)\n{BOUNDS}{{"
+        )
+        .unwrap();
+
+        // inverses of constant denominators are computed once
+        let mut uniform_denominators = BTreeSet::new();
+        let mut denominators = BTreeMap::<usize, Vec<NodeId>>::new();
+        for node in nodes {
+            if let Node::Div(_, b) = *node {
+                if self.is_varying[b.index()] {
+                    let level = denominators.entry(self.level[b.index()]).or_default();
+                    if !level.contains(&b) {
+                        level.push(b);
+                    }
+                } else {
+                    uniform_denominators.insert(b.index());
+                }
+            }
+        }
+
+        // values that are the same for every point
+        let mut x_powers = BTreeSet::new();
+        for (i, node) in nodes.iter().enumerate() {
+            match *node {
+                Node::Pow(a, exp) if self.is_x(a) => {
+                    x_powers.insert(exp);
+                }
+                _ => {}
+            }
+            if self.is_varying[i] {
+                continue;
+            }
+            let value = match *node {
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Constant(v))) => {
+                    let variant = if matches!(v, FieldVariant::Fp(_)) {
+                        "Fp"
+                    } else {
+                        "Fq"
+                    };
+                    let k = self.constant_indices[&i];
+                    writeln!(
+                        out,
+                        "    let ::ministark::utils::FieldVariant::{variant}(v{i}) = inputs.constants[{k}] else {{ unreachable!() }};"
+                    )
+                    .unwrap();
+                    None
+                }
+                Node::Leaf(ref item) => Some(uniform_leaf(item)),
+                Node::Neg(a) => Some(format!("-v{}", a.index())),
+                Node::Add(a, b) => {
+                    let (a, b) = (a.index(), b.index());
+                    Some(self.binary(a, &format!("v{a}"), b, &format!("v{b}"), '+'))
+                }
+                Node::Mul(a, b) => {
+                    let (a, b) = (a.index(), b.index());
+                    Some(self.binary(a, &format!("v{a}"), b, &format!("v{b}"), '*'))
+                }
+                Node::Div(a, b) => {
+                    let (a, b) = (a.index(), b.index());
+                    Some(self.binary(a, &format!("v{a}"), b, &format!("i{b}"), '*'))
+                }
+                Node::Pow(a, exp) => {
+                    Some(format!("::ark_ff::Field::pow(&v{}, [{exp}])", a.index()))
+                }
+            };
+            if let Some(value) = value {
+                writeln!(out, "    let v{i}: {} = {value};", self.ty(i)).unwrap();
+            }
+            if uniform_denominators.contains(&i) {
+                writeln!(
+                    out,
+                    "    let i{i}: {} = ::ark_ff::Field::inverse(&v{i}).unwrap();",
+                    self.ty(i)
+                )
+                .unwrap();
+            }
+        }
+        if !self.is_varying[root] {
+            let value = if self.is_fp[root] {
+                format!("Fq::from(v{root})")
+            } else {
+                format!("v{root}")
+            };
+            writeln!(out, "    result.fill({value});\n}}").unwrap();
+            return;
+        }
+        if !x_powers.is_empty() {
+            writeln!(out, "    let x_lde = inputs.lde.x_lde;").unwrap();
+            writeln!(out, "    let g = x_lde[1 % x_lde.len()] / x_lde[0];").unwrap();
+            for exp in &x_powers {
+                writeln!(out, "    let g{exp} = ::ark_ff::Field::pow(&g, [{exp}]);").unwrap();
+            }
+        }
+
+        // one pass for each level of batch inversions
+        let mut passes = Vec::new();
+        for level in 0..=self.level[root] {
+            let mut targets = denominators.remove(&level).unwrap_or_default();
+            if level == self.level[root] {
+                targets.push(self.root);
+            }
+            if targets.is_empty() {
+                continue;
+            }
+            let mut needed = vec![false; nodes.len()];
+            for &target in &targets {
+                self.mark(target, &mut needed);
+            }
+            passes.push((targets, needed));
+        }
+        let mut used = vec![false; nodes.len()];
+        for (_, needed) in &passes {
+            for (used, needed) in used.iter_mut().zip(needed) {
+                *used |= needed;
+            }
+        }
+
+        writeln!(
+            out,
+            "    ::ministark::codegen::for_each_chunk::<Fq, N>(result, |offset, chunk| {{"
+        )
+        .unwrap();
+        let mut trace_args = BTreeMap::new();
+        let mut periodic_args = BTreeSet::new();
+        let mut uses_x = false;
+        for (i, node) in nodes.iter().enumerate() {
+            match *node {
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::X)) if used[i] => uses_x = true,
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Trace(col, offset))) if used[i] => {
+                    let num_trace_args = trace_args.len();
+                    trace_args.entry((col, offset)).or_insert(num_trace_args);
+                }
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Periodic(ref col))) if used[i] => {
+                    periodic_args.insert(self.periodic_index(col));
+                }
+                _ => {}
+            }
+        }
+        if uses_x {
+            writeln!(
+                out,
+                "        let x = ::ministark::codegen::lde_chunk::<Fp, N>(inputs.lde.x_lde, 1, offset, 0);"
+            )
+            .unwrap();
+        }
+        for exp in &x_powers {
+            writeln!(out, "        let x{exp} = ::ministark::codegen::geometric::<Fp, N>(::ark_ff::Field::pow(&x_lde[offset], [{exp}]), g{exp});").unwrap();
+        }
+        for (&(col, offset), j) in &trace_args {
+            let column = if col < self.num_base_columns {
+                format!("inputs.lde.base_trace_lde_cols[{col}]")
+            } else {
+                format!(
+                    "inputs.lde.extension_trace_lde_cols.unwrap()[{}]",
+                    col - self.num_base_columns
+                )
+            };
+            let ty = if col < self.num_base_columns {
+                "Fp"
+            } else {
+                "Fq"
+            };
+            writeln!(out, "        let t{j} = ::ministark::codegen::lde_chunk::<{ty}, N>({column}, inputs.lde.lde_step, offset, {offset});").unwrap();
+        }
+        for j in periodic_args {
+            let variant = if is_fp_column(&self.periodic_columns[j]) {
+                "Fp"
+            } else {
+                "Fq"
+            };
+            writeln!(
+                out,
+                "        let ::ministark::utils::FieldVariant::{variant}(p{j}) = &inputs.lde.periodic_column_ldes[{j}] else {{ unreachable!() }};"
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "        let p{j} = ::ministark::codegen::lde_chunk::<{variant}, N>(p{j}, 1, offset, 0);"
+            )
+            .unwrap();
+        }
+
+        for (targets, needed) in passes {
+            let denominators = targets
+                .iter()
+                .filter(|&&t| t != self.root)
+                .map(|t| t.index())
+                .collect::<Vec<_>>();
+            for &b in &denominators {
+                writeln!(
+                    out,
+                    "        let mut d{b} = [<{} as ::ark_ff::Field>::ZERO; N];",
+                    self.ty(b)
+                )
+                .unwrap();
+            }
+            writeln!(out, "        for i in 0..N {{").unwrap();
+            for (i, node) in nodes.iter().enumerate() {
+                if !needed[i] {
+                    continue;
+                }
+                let value = match *node {
+                    Node::Leaf(CompositionItem::Item(AlgebraicItem::X)) => "x[i]".into(),
+                    Node::Leaf(CompositionItem::Item(AlgebraicItem::Trace(col, offset))) => {
+                        format!("t{}[i]", trace_args[&(col, offset)])
+                    }
+                    Node::Leaf(CompositionItem::Item(AlgebraicItem::Periodic(ref col))) => {
+                        format!("p{}[i]", self.periodic_index(col))
+                    }
+                    Node::Leaf(_) => unreachable!(),
+                    Node::Neg(a) => format!("-v{}", a.index()),
+                    Node::Add(a, b) => {
+                        let (a, b) = (a.index(), b.index());
+                        self.binary(a, &format!("v{a}"), b, &format!("v{b}"), '+')
+                    }
+                    Node::Mul(a, b) => {
+                        let (a, b) = (a.index(), b.index());
+                        self.binary(a, &format!("v{a}"), b, &format!("v{b}"), '*')
+                    }
+                    Node::Div(a, b) => {
+                        let (a, b) = (a.index(), b.index());
+                        let inverse = if self.is_varying[b] {
+                            format!("d{b}[i]")
+                        } else {
+                            format!("i{b}")
+                        };
+                        self.binary(a, &format!("v{a}"), b, &inverse, '*')
+                    }
+                    Node::Pow(a, exp) if self.is_x(a) => format!("x{exp}[i]"),
+                    Node::Pow(a, exp) => {
+                        format!("::ark_ff::Field::pow(&v{}, [{exp}])", a.index())
+                    }
+                };
+                writeln!(out, "            let v{i}: {} = {value};", self.ty(i)).unwrap();
+            }
+            for b in &denominators {
+                writeln!(out, "            d{b}[i] = v{b};").unwrap();
+            }
+            if targets.contains(&self.root) {
+                if self.is_fp[root] {
+                    writeln!(out, "            chunk[i] = Fq::from(v{root});").unwrap();
+                } else {
+                    writeln!(out, "            chunk[i] = v{root};").unwrap();
+                }
+            }
+            writeln!(out, "        }}").unwrap();
+            for b in &denominators {
+                writeln!(out, "        ::ark_ff::batch_inversion(&mut d{b});").unwrap();
+            }
+        }
+        writeln!(out, "    }});\n}}").unwrap();
+    }
+
+    fn write_ood_evaluator(&self, out: &mut String) {
+        writeln!(
+            out,
+            "/// Evaluates the composition constraint at the out-of-domain point"
+        )
+        .unwrap();
+        writeln!(out, "{ALLOW}").unwrap();
+        writeln!(
+            out,
+            "pub fn eval_ood<Fp, Fq>(inputs: &::ministark::codegen::OodInputs<'_, Fq>) -> Fq\n{BOUNDS}{{"
+        )
+        .unwrap();
+        for (i, node) in self.arena.nodes().iter().enumerate() {
+            let value = match *node {
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::X)) => "inputs.x".into(),
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Trace(col, offset))) => {
+                    format!("inputs.trace_ood_evals[&({col}, {offset})]")
+                }
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Periodic(ref col))) => {
+                    format!("inputs.periodic_evals[{}]", self.periodic_index(col))
+                }
+                Node::Leaf(CompositionItem::Item(AlgebraicItem::Constant(_))) => {
+                    format!("inputs.constants[{}]", self.constant_indices[&i])
+                }
+                Node::Leaf(ref item) => uniform_leaf(item),
+                Node::Neg(a) => format!("-v{}", a.index()),
+                Node::Add(a, b) => format!("v{} + v{}", a.index(), b.index()),
+                Node::Mul(a, b) => format!("v{} * v{}", a.index(), b.index()),
+                Node::Div(a, b) => format!("v{} / v{}", a.index(), b.index()),
+                Node::Pow(a, exp) => format!("::ark_ff::Field::pow(&v{}, [{exp}])", a.index()),
+            };
+            writeln!(out, "    let v{i}: Fq = {value};").unwrap();
+        }
+        writeln!(out, "    v{}\n}}", self.root.index()).unwrap();
+    }
+
+    fn periodic_index(&self, col: &PeriodicColumn<FieldVariant<Fp, Fq>>) -> usize {
+        self.periodic_columns.iter().position(|c| c == col).unwrap()
+    }
+}
+
+fn is_fp_column<Fp, Fq>(col: &PeriodicColumn<FieldVariant<Fp, Fq>>) -> bool {
+    col.coeffs()
+        .iter()
+        .all(|v| matches!(v, FieldVariant::Fp(_)))
+}
+
+/// Expression of a leaf other than a constant that is the same for every
+/// point of evaluation
+fn uniform_leaf<Fp, Fq>(item: &CompositionItem<FieldVariant<Fp, Fq>>) -> String {
+    match item {
+        CompositionItem::CompositionCoeff(i) => format!("inputs.composition_coeffs[{i}]"),
+        CompositionItem::Item(AlgebraicItem::Challenge(round, i)) => {
+            format!("inputs.challenges[({round}, {i})]")
+        }
+        CompositionItem::Item(AlgebraicItem::Hint(i)) => format!("inputs.hints[{i}]"),
+        CompositionItem::Item(_) => unreachable!(),
+    }
+}
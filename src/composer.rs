@@ -1,3 +1,4 @@
+use crate::air::num_extension_columns;
 use crate::air::AirConfig;
 use crate::utils::divide_out_point_into;
 use crate::utils::divide_out_points_into;
@@ -17,9 +18,12 @@ use std::iter::zip;
 pub struct DeepPolyComposer<'a, A: AirConfig> {
     z: A::Fq,
     air: &'a Air<A>,
+    /// Base trace polynomials followed by the preprocessed polynomials
     base_trace_polys: Matrix<A::Fp>,
+    /// Polynomials of the extension trace segments in order
     extension_trace_polys: Option<Matrix<A::Fq>>,
     composition_trace_polys: Matrix<A::Fq>,
+    masking_poly: Option<GpuVec<A::Fq>>,
 }
 
 impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
@@ -29,6 +33,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
         base_trace_polys: Matrix<A::Fp>,
         extension_trace_polys: Option<Matrix<A::Fq>>,
         composition_trace_polys: Matrix<A::Fq>,
+        masking_poly: Option<GpuVec<A::Fq>>,
     ) -> Self {
         Self {
             z,
@@ -36,6 +41,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            masking_poly,
         }
     }
 
@@ -47,15 +53,18 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            ..
         } = self;
 
         let trace_domain = air.trace_domain();
         let g = trace_domain.group_gen();
         let g_inv = trace_domain.group_gen_inv();
 
-        let num_columns = A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS;
-        let base_column_range = 0..A::NUM_BASE_COLUMNS;
-        let extension_column_range = A::NUM_BASE_COLUMNS..num_columns;
+        // preprocessed columns follow the base columns
+        let num_base_columns = A::NUM_BASE_COLUMNS + A::NUM_PREPROCESSED_COLUMNS;
+        let num_columns = num_base_columns + num_extension_columns::<A>();
+        let base_column_range = 0..num_base_columns;
+        let extension_column_range = num_base_columns..num_columns;
 
         // generate ood evaluations for the execution trace polynomials
         let execution_trace_evals = ark_std::cfg_into_iter!(air.trace_arguments())
@@ -68,7 +77,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
                     horner_evaluate(coeffs, &x)
                 } else if extension_column_range.contains(&col_idx) {
                     let coeffs =
-                        &extension_trace_polys.as_deref().unwrap()[col_idx - A::NUM_BASE_COLUMNS];
+                        &extension_trace_polys.as_deref().unwrap()[col_idx - num_base_columns];
                     horner_evaluate(coeffs, &x)
                 } else {
                     panic!("column is {col_idx} but there are only {num_columns} columns")
@@ -77,9 +86,8 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             .collect();
 
         // generate ood evaluations for the composition trace polynomials
-        let z_n = self.z.pow([composition_trace_polys.num_cols() as u64]);
         let composition_trace_evals = ark_std::cfg_iter!(composition_trace_polys)
-            .map(|column| horner_evaluate(column, &z_n))
+            .map(|column| horner_evaluate(column, z))
             .collect();
 
         (execution_trace_evals, composition_trace_evals)
@@ -93,6 +101,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            masking_poly,
         } = self;
 
         let DeepCompositionCoeffs {
@@ -106,17 +115,18 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
         let g_inv = trace_domain.group_gen_inv();
 
         // divide out OOD point from composition trace polys
-        let z_n = self.z.pow([composition_trace_polys.num_cols() as u64]);
         let composition_trace_quotients = ark_std::cfg_into_iter!(composition_trace_polys.0)
             .zip(composition_trace_alphas)
             .map(|(mut coeffs, alpha)| {
-                divide_out_point_into(&mut coeffs, &z_n, &alpha);
+                divide_out_point_into(&mut coeffs, &z, &alpha);
                 coeffs
             });
 
-        let num_columns = A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS;
-        let base_column_range = 0..A::NUM_BASE_COLUMNS;
-        let extension_column_range = A::NUM_BASE_COLUMNS..num_columns;
+        // preprocessed columns follow the base columns
+        let num_base_columns = A::NUM_BASE_COLUMNS + A::NUM_PREPROCESSED_COLUMNS;
+        let num_columns = num_base_columns + num_extension_columns::<A>();
+        let base_column_range = 0..num_base_columns;
+        let extension_column_range = num_base_columns..num_columns;
         let trace_arguments = air.trace_arguments();
         let execution_trace_xs_and_alphas = |col_idx| {
             let mut xs = Vec::new();
@@ -162,7 +172,9 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
                 .chain(extension_trace_quotients)
                 .collect(),
         );
-        let mut combined_coeffs = GpuVec::try_from(quotients.sum_columns()).unwrap();
+        // summing columns always gives a single column
+        let mut deep_poly = quotients.sum_columns();
+        let combined_coeffs = &mut deep_poly[0];
 
         let chunk_size = 1 << 16;
         if degree_beta.is_zero() {
@@ -176,7 +188,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             // Adjust the degree
             // P(x) * (alpha + x * beta)
             let mut last = A::Fq::zero();
-            for coeff in &mut combined_coeffs {
+            for coeff in combined_coeffs.iter_mut() {
                 let tmp = *coeff;
                 *coeff *= degree_alpha;
                 *coeff += last * degree_beta;
@@ -184,7 +196,14 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             }
         }
 
-        Matrix::new(vec![combined_coeffs])
+        // hide the DEEP composition polynomial (and hence all FRI layers)
+        if let Some(masking_poly) = masking_poly {
+            for (coeff, mask) in zip(combined_coeffs.iter_mut(), masking_poly) {
+                *coeff += mask;
+            }
+        }
+
+        deep_poly
     }
 }
 
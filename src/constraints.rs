@@ -1,8 +1,21 @@
 use crate::expression::Expr;
+use crate::expression::P;
 use crate::utils;
+use crate::utils::FieldVariant;
+use crate::StarkExtensionOf;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
 use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
 use ark_ff::One;
+use ark_ff::PrimeField;
 use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::fmt::Display;
+use core::fmt::Formatter;
 use core::iter::Product;
 use core::iter::Sum;
 use core::ops::Add;
@@ -12,28 +25,36 @@ use core::ops::Div;
 use core::ops::Mul;
 use core::ops::Neg;
 use core::ops::Sub;
+use core::panic::Location;
+use ministark_gpu::GpuFftField;
 use num_traits::Pow;
+use rand::RngCore;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
 
-// TODO: should really remove copy as this type might change in the future
-#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub enum AlgebraicItem<T: 'static> {
     X,
     Constant(T),
-    Challenge(usize),
-    Periodic(PeriodicColumn<'static, T>),
+    Challenge(/* =round */ usize, /* =index */ usize),
+    Periodic(PeriodicColumn<T>),
     Hint(usize),
     Trace(/* =column */ usize, /* =offset */ isize),
 }
 
 impl<T> AlgebraicItem<T> {
     // Returns an upper bound on the item's degree in `x`
-    const fn degree(&self, trace_degree: usize) -> Degree {
+    fn degree(&self, trace_degree: usize) -> Degree {
         use AlgebraicItem::*;
         match &self {
             // TODO: handle implications of a zero?
-            Constant(_) | Challenge(_) | Hint(_) => Degree(0, 0),
+            Constant(_) | Challenge(..) | Hint(_) => Degree(0, 0),
             Trace(_, _) => Degree(trace_degree, 0),
             Periodic(col) => col.degree(trace_degree),
             X => Degree(1, 0),
@@ -103,17 +124,23 @@ forward_ref_binop!(impl< T: Clone > Add, add for AlgebraicItem<T>, AlgebraicItem
 forward_ref_binop!(impl< T: Clone > Sub, sub for AlgebraicItem<T>, AlgebraicItem<T>);
 
 /// A periodic column that repeats itself every `interval_size` many rows.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct PeriodicColumn<'a, T> {
-    coeffs: &'a [T],
+/// Coefficients are reference counted so periodic columns can be computed at
+/// runtime e.g. from public inputs.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PeriodicColumn<T> {
+    coeffs: Arc<[T]>,
     interval_size: usize,
 }
 
-impl<'a, T> PeriodicColumn<'a, T> {
+impl<T> PeriodicColumn<T> {
+    /// Creates a periodic column from the coefficients of a polynomial in
+    /// `x^(trace_len / interval_size)`
+    ///
     /// # Panics
     /// Panics if the number of coefficients or the
     /// interval size is not a power of two.
-    pub const fn new(coeffs: &'a [T], interval_size: usize) -> Self {
+    pub fn new(coeffs: impl Into<Arc<[T]>>, interval_size: usize) -> Self {
+        let coeffs = coeffs.into();
         assert!(coeffs.len().is_power_of_two());
         assert!(interval_size.is_power_of_two());
         assert!(coeffs.len() <= interval_size);
@@ -127,12 +154,12 @@ impl<'a, T> PeriodicColumn<'a, T> {
         self.interval_size
     }
 
-    pub const fn coeffs(&self) -> &'a [T] {
-        self.coeffs
+    pub fn coeffs(&self) -> &[T] {
+        &self.coeffs
     }
 
     /// Returns an upper bound on the preiodic column's degree in `x`
-    const fn degree(&self, trace_degree: usize) -> Degree {
+    fn degree(&self, trace_degree: usize) -> Degree {
         let trace_len = trace_degree + 1;
         assert!(trace_len.is_power_of_two());
         let poly_degree = self.coeffs.len() - 1;
@@ -141,19 +168,163 @@ impl<'a, T> PeriodicColumn<'a, T> {
     }
 }
 
+impl<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
+    PeriodicColumn<FieldVariant<Fp, Fq>>
+{
+    /// Creates a periodic column from its values in the rows of one interval.
+    /// The interval size is the number of values.
+    ///
+    /// # Panics
+    /// Panics if the number of values is not a power of two.
+    pub fn from_evals(evals: &[FieldVariant<Fp, Fq>]) -> Self {
+        let interval_size = evals.len();
+        assert!(interval_size.is_power_of_two());
+        let domain = Radix2EvaluationDomain::<Fp>::new(interval_size).unwrap();
+        let coeffs = if evals.iter().all(|v| matches!(v, FieldVariant::Fp(_))) {
+            let evals = evals
+                .iter()
+                .map(|v| match v {
+                    FieldVariant::Fp(v) => *v,
+                    FieldVariant::Fq(_) => unreachable!(),
+                })
+                .collect::<Vec<Fp>>();
+            domain
+                .ifft(&evals)
+                .into_iter()
+                .map(FieldVariant::Fp)
+                .collect::<Vec<_>>()
+        } else {
+            let evals = evals.iter().map(FieldVariant::as_fq).collect::<Vec<Fq>>();
+            domain
+                .ifft(&evals)
+                .into_iter()
+                .map(FieldVariant::Fq)
+                .collect::<Vec<_>>()
+        };
+        Self::new(coeffs, interval_size)
+    }
+}
+
+/// The rows of the execution trace a constraint applies to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConstraintGroup {
+    /// Applies to the first row
+    Boundary,
+    /// Applies between consecutive rows
+    Transition,
+    /// Applies to the last row
+    Terminal,
+}
+
+impl Display for ConstraintGroup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Boundary => "boundary",
+            Self::Transition => "transition",
+            Self::Terminal => "terminal",
+        })
+    }
+}
+
+/// Optional information about a constraint used in diagnostics
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConstraintMetadata {
+    pub name: Option<Cow<'static, str>>,
+    pub group: Option<ConstraintGroup>,
+    /// Where the constraint was named
+    pub location: Option<&'static Location<'static>>,
+}
+
+impl ConstraintMetadata {
+    /// Identifies the constraint at `index` in [`AirConfig::constraints`]
+    ///
+    /// [`AirConfig::constraints`]: crate::air::AirConfig::constraints
+    pub fn id(&self, index: usize) -> ConstraintId {
+        ConstraintId {
+            index,
+            name: self.name.clone(),
+            group: self.group,
+            location: self.location,
+        }
+    }
+}
+
+/// Refers to a constraint by name when it has one and by index otherwise
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintId {
+    pub index: usize,
+    pub name: Option<Cow<'static, str>>,
+    pub group: Option<ConstraintGroup>,
+    pub location: Option<&'static Location<'static>>,
+}
+
+impl From<usize> for ConstraintId {
+    fn from(index: usize) -> Self {
+        ConstraintMetadata::default().id(index)
+    }
+}
+
+impl Display for ConstraintId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match (&self.name, self.group) {
+            (Some(name), _) => f.write_str(name),
+            (None, Some(group)) => write!(f, "{group} constraint {}", self.index),
+            (None, None) => write!(f, "constraint {}", self.index),
+        }
+    }
+}
+
 #[derive(Clone)]
-pub struct Constraint<T: 'static>(Expr<AlgebraicItem<T>>);
+pub struct Constraint<T: 'static> {
+    expr: Expr<AlgebraicItem<T>>,
+    metadata: ConstraintMetadata,
+}
 
 impl<T> Constraint<T> {
     pub const fn new(expression: Expr<AlgebraicItem<T>>) -> Self {
-        Self(expression)
+        Self {
+            expr: expression,
+            metadata: ConstraintMetadata {
+                name: None,
+                group: None,
+                location: None,
+            },
+        }
+    }
+
+    /// Names the constraint and records the caller's source location
+    #[must_use]
+    #[track_caller]
+    pub fn named(self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.named_at(name, Location::caller())
+    }
+
+    #[must_use]
+    pub(crate) fn named_at(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        location: &'static Location<'static>,
+    ) -> Self {
+        self.metadata.name = Some(name.into());
+        self.metadata.location = Some(location);
+        self
+    }
+
+    #[must_use]
+    pub const fn in_group(mut self, group: ConstraintGroup) -> Self {
+        self.metadata.group = Some(group);
+        self
+    }
+
+    pub const fn metadata(&self) -> &ConstraintMetadata {
+        &self.metadata
     }
 
     /// Calculates an upper bound on the degree in X.
     /// Output is of the form `(numerator_degree, denominator_degree)`
     pub fn degree(&self, trace_degree: usize) -> (usize, usize) {
         let Degree(numerator_degree, denominator_degree) =
-            self.0.eval(&mut |leaf| leaf.degree(trace_degree));
+            self.expr.eval(&mut |leaf| leaf.degree(trace_degree));
         (numerator_degree, denominator_degree)
     }
 
@@ -245,7 +416,7 @@ impl<T> Constraint<T> {
             }
         }
 
-        self.0.eval(&mut |leaf| CheckedEval(Some(f(leaf)))).0
+        self.expr.eval(&mut |leaf| CheckedEval(Some(f(leaf)))).0
     }
 
     // Adapted from https://github.com/0xProject/OpenZKP
@@ -260,6 +431,464 @@ impl<T> Constraint<T> {
     }
 }
 
+impl<T: ConstantField> Constraint<T> {
+    /// Returns an equivalent constraint with redundant structure removed. See
+    /// [`Expr::simplify`].
+    #[must_use]
+    pub fn simplify(&self) -> Self {
+        Self {
+            expr: self.expr.simplify(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// Values that constant leaves of a constraint can be folded over
+pub trait ConstantField:
+    Copy
+    + Ord
+    + Zero
+    + One
+    + Neg<Output = Self>
+    + Add<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Pow<usize, Output = Self>
+{
+}
+
+impl<T> ConstantField for T where
+    T: Copy
+        + Ord
+        + Zero
+        + One
+        + Neg<Output = Self>
+        + Add<Output = Self>
+        + Mul<Output = Self>
+        + Div<Output = Self>
+        + Pow<usize, Output = Self>
+{
+}
+
+impl<T: ConstantField> Expr<AlgebraicItem<T>> {
+    /// Returns an equivalent expression with redundant structure removed:
+    /// * constant subexpressions are folded
+    /// * additions of zero, multiplications by one and `--a` are removed
+    /// * multiplications by zero become zero
+    /// * powers of the same base are merged e.g. `(a^2)^3 * a` becomes `a^7`
+    /// * operands of additions and multiplications are ordered canonically
+    ///
+    /// Sub-expressions shared between nodes remain shared.
+    #[must_use]
+    pub fn simplify(&self) -> Self {
+        simplify_node(self, &mut BTreeMap::new())
+    }
+}
+
+type Node<T> = P<Expr<AlgebraicItem<T>>>;
+type NodeId<T> = *const Expr<AlgebraicItem<T>>;
+
+fn simplify_child<T: ConstantField>(
+    child: &Node<T>,
+    seen: &mut BTreeMap<NodeId<T>, Node<T>>,
+) -> Node<T> {
+    let id = Arc::as_ptr(child);
+    if let Some(node) = seen.get(&id) {
+        return Arc::clone(node);
+    }
+    let node = Arc::new(simplify_node(child, seen));
+    seen.insert(id, Arc::clone(&node));
+    node
+}
+
+fn simplify_node<T: ConstantField>(
+    expr: &Expr<AlgebraicItem<T>>,
+    seen: &mut BTreeMap<NodeId<T>, Node<T>>,
+) -> Expr<AlgebraicItem<T>> {
+    use Expr::*;
+    match expr {
+        Leaf(item) => Leaf(item.clone()),
+        Neg(a) => simplify_neg(simplify_child(a, seen)),
+        Add(a, b) => simplify_add(simplify_child(a, seen), simplify_child(b, seen)),
+        Mul(a, b) => simplify_mul(simplify_child(a, seen), simplify_child(b, seen)),
+        Div(a, b) => simplify_div(simplify_child(a, seen), simplify_child(b, seen)),
+        &Pow(ref a, e) => simplify_pow(simplify_child(a, seen), e),
+    }
+}
+
+fn constant<T: ConstantField>(node: &Node<T>) -> Option<T> {
+    match **node {
+        Expr::Leaf(AlgebraicItem::Constant(c)) => Some(c),
+        _ => None,
+    }
+}
+
+fn unwrap_node<T: ConstantField>(node: &Node<T>) -> Expr<AlgebraicItem<T>> {
+    (**node).clone()
+}
+
+/// Orders the operands of commutative operations
+fn ordered<T: ConstantField>(a: Node<T>, b: Node<T>) -> (Node<T>, Node<T>) {
+    if !Arc::ptr_eq(&a, &b) && a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+fn simplify_neg<T: ConstantField>(a: Node<T>) -> Expr<AlgebraicItem<T>> {
+    if let Some(c) = constant(&a) {
+        return Expr::Leaf(AlgebraicItem::Constant(-c));
+    }
+    let double_negation = match &*a {
+        Expr::Neg(b) => Some(unwrap_node(b)),
+        _ => None,
+    };
+    double_negation.unwrap_or(Expr::Neg(a))
+}
+
+fn simplify_add<T: ConstantField>(a: Node<T>, b: Node<T>) -> Expr<AlgebraicItem<T>> {
+    match (constant(&a), constant(&b)) {
+        (Some(a), Some(b)) => Expr::Leaf(AlgebraicItem::Constant(a + b)),
+        (Some(zero), _) if zero.is_zero() => unwrap_node(&b),
+        (_, Some(zero)) if zero.is_zero() => unwrap_node(&a),
+        _ => {
+            let (a, b) = ordered(a, b);
+            Expr::Add(a, b)
+        }
+    }
+}
+
+/// Splits an expression into a base and exponent
+fn as_power<T: ConstantField>(node: Node<T>) -> (Node<T>, usize) {
+    let power = match &*node {
+        &Expr::Pow(ref base, e) => Some((Arc::clone(base), e)),
+        _ => None,
+    };
+    power.unwrap_or((node, 1))
+}
+
+fn simplify_mul<T: ConstantField>(a: Node<T>, b: Node<T>) -> Expr<AlgebraicItem<T>> {
+    match (constant(&a), constant(&b)) {
+        (Some(a), Some(b)) => Expr::Leaf(AlgebraicItem::Constant(a * b)),
+        (Some(zero), _) | (_, Some(zero)) if zero.is_zero() => {
+            Expr::Leaf(AlgebraicItem::Constant(zero))
+        }
+        (Some(one), _) if one.is_one() => unwrap_node(&b),
+        (_, Some(one)) if one.is_one() => unwrap_node(&a),
+        (Some(_), _) | (_, Some(_)) => {
+            let (a, b) = ordered(a, b);
+            Expr::Mul(a, b)
+        }
+        (None, None) => {
+            let (a_base, a_exp) = as_power(Arc::clone(&a));
+            let (b_base, b_exp) = as_power(Arc::clone(&b));
+            if a_base == b_base {
+                simplify_pow(a_base, a_exp + b_exp)
+            } else {
+                let (a, b) = ordered(a, b);
+                Expr::Mul(a, b)
+            }
+        }
+    }
+}
+
+fn simplify_div<T: ConstantField>(a: Node<T>, b: Node<T>) -> Expr<AlgebraicItem<T>> {
+    match (constant(&a), constant(&b)) {
+        (Some(a), Some(b)) if !b.is_zero() => Expr::Leaf(AlgebraicItem::Constant(a / b)),
+        (_, Some(one)) if one.is_one() => unwrap_node(&a),
+        _ => Expr::Div(a, b),
+    }
+}
+
+fn simplify_pow<T: ConstantField>(a: Node<T>, e: usize) -> Expr<AlgebraicItem<T>> {
+    if e == 0 {
+        return Expr::Leaf(AlgebraicItem::Constant(T::one()));
+    }
+    if let Some(c) = constant(&a) {
+        return Expr::Leaf(AlgebraicItem::Constant(c.pow(e)));
+    }
+    if e == 1 {
+        return unwrap_node(&a);
+    }
+    let (base, base_exp) = as_power(a);
+    Expr::Pow(base, base_exp * e)
+}
+
+/// Constants of an expression that can be lifted into the field `F` to evaluate
+/// the expression at random points
+pub trait IntoField<F> {
+    fn into_field(self) -> F;
+}
+
+impl<F: Field> IntoField<F> for F {
+    fn into_field(self) -> F {
+        self
+    }
+}
+
+impl<Fp: Field, Fq: Field + From<Fp>> IntoField<Fq> for FieldVariant<Fp, Fq> {
+    fn into_field(self) -> Fq {
+        self.as_fq()
+    }
+}
+
+/// Probability that two different expressions are reported as equal by
+/// [`Expr::semantically_eq`] is below `2^-SEMANTIC_EQUALITY_SECURITY_LEVEL`
+pub const SEMANTIC_EQUALITY_SECURITY_LEVEL: usize = 100;
+
+impl<T: Copy + Ord + Hash> Expr<AlgebraicItem<T>> {
+    /// Evaluates the expression with `x` assigned to [`AlgebraicItem::X`] and
+    /// pseudo-random values derived from `x` assigned to all other
+    /// non-constant leaves. Periodic columns are treated as independent
+    /// variables. Returns a hash of the result.
+    ///
+    /// Expressions that are algebraically equal have equal hashes. By the
+    /// Schwartz–Zippel lemma different expressions have equal hashes with
+    /// probability at most `d/|F|` for a random `x` where `d` bounds the
+    /// degree of their difference.
+    pub fn evaluation_hash<F: Field>(&self, x: F) -> u64
+    where
+        T: IntoField<F>,
+    {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        let seed = hasher.finish();
+        let mut hasher = DefaultHasher::new();
+        self.eval_at(x, seed).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns true if both expressions are algebraically equal by comparing
+    /// evaluations at random points in `F`. Enough points are used for the
+    /// Schwartz–Zippel bound to reach [`SEMANTIC_EQUALITY_SECURITY_LEVEL`].
+    pub fn semantically_eq<F: Field>(&self, other: &Self) -> bool
+    where
+        T: IntoField<F>,
+    {
+        self.semantically_eq_with_rng::<F>(other, &mut ChaCha20Rng::from_entropy())
+    }
+
+    /// Same as [`Expr::semantically_eq`] but draws points from `rng` so results
+    /// can be reproduced. Points where either expression divides by zero are
+    /// skipped and expressions are reported unequal if too many points are
+    /// skipped.
+    pub fn semantically_eq_with_rng<F: Field>(&self, other: &Self, rng: &mut impl RngCore) -> bool
+    where
+        T: IntoField<F>,
+    {
+        // the difference has degree at most the sum of both degrees
+        let degree = self.symbolic_degree() + other.symbolic_degree();
+        let num_points = num_evaluation_points::<F>(degree);
+        let mut num_equal_points = 0;
+        for _ in 0..2 * num_points {
+            let x = F::rand(rng);
+            let seed = rng.next_u64();
+            match (self.eval_at(x, seed), other.eval_at(x, seed)) {
+                (Some(a), Some(b)) if a != b => return false,
+                (Some(_), Some(_)) => num_equal_points += 1,
+                _ => {}
+            }
+            if num_equal_points == num_points {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns an equivalent expression where subexpressions that are
+    /// algebraically equal are shared even if they differ structurally e.g.
+    /// `a * (b + c)` and `a * c + b * a`. Subexpressions are compared like
+    /// [`Expr::semantically_eq_with_rng`]. Subexpressions that divide by zero
+    /// at any of the points are never merged.
+    #[must_use]
+    pub fn dedup_semantically<F: Field>(&self, rng: &mut impl RngCore) -> Self
+    where
+        T: IntoField<F>,
+    {
+        // the difference of any two subexpressions has at most twice the degree
+        let num_points = num_evaluation_points::<F>(2 * self.symbolic_degree());
+        let points = (0..num_points)
+            .map(|_| (F::rand(rng), rng.next_u64()))
+            .collect();
+        let mut dedup = SemanticDedup {
+            points,
+            seen: BTreeMap::new(),
+            by_evaluations: HashMap::new(),
+        };
+        let (root, _) = dedup.node(self);
+        drop(dedup);
+        Arc::try_unwrap(root).unwrap_or_else(|root| (*root).clone())
+    }
+
+    /// Bounds the total degree of the expression with every non-constant leaf
+    /// treated as a variable of degree one
+    fn symbolic_degree(&self) -> usize {
+        let mut variable_degree = |leaf: &AlgebraicItem<T>| match leaf {
+            AlgebraicItem::Constant(_) => Degree(0, 0),
+            _ => Degree(1, 0),
+        };
+        let Degree(numerator_degree, denominator_degree) = self.eval(&mut variable_degree);
+        numerator_degree + denominator_degree
+    }
+
+    /// Evaluates the expression at `x` and pseudo-random values for the other
+    /// leaves. Returns None if a denominator evaluates to zero.
+    fn eval_at<F: Field>(&self, x: F, seed: u64) -> Option<F>
+    where
+        T: IntoField<F>,
+    {
+        self.graph_eval(&mut |leaf| PointEval(Some(eval_leaf_at(leaf, x, seed))))
+            .0
+    }
+}
+
+/// Number of random points needed for the Schwartz–Zippel bound of an
+/// expression with the given degree to reach
+/// [`SEMANTIC_EQUALITY_SECURITY_LEVEL`]
+fn num_evaluation_points<F: Field>(degree: usize) -> usize {
+    let field_bits =
+        usize::try_from(u64::from(F::BasePrimeField::MODULUS_BIT_SIZE - 1) * F::extension_degree())
+            .unwrap();
+    let bits_per_point = field_bits
+        .saturating_sub(degree.max(1).next_power_of_two().ilog2() as usize)
+        .max(1);
+    SEMANTIC_EQUALITY_SECURITY_LEVEL.div_ceil(bits_per_point)
+}
+
+/// Assigns `x` to [`AlgebraicItem::X`] and a pseudo-random value derived from
+/// `seed` to all other non-constant leaves
+fn eval_leaf_at<T: Copy + Hash + IntoField<F>, F: Field>(
+    leaf: &AlgebraicItem<T>,
+    x: F,
+    seed: u64,
+) -> F {
+    match leaf {
+        AlgebraicItem::X => x,
+        &AlgebraicItem::Constant(c) => c.into_field(),
+        leaf => {
+            let mut hasher = DefaultHasher::new();
+            (seed, leaf).hash(&mut hasher);
+            F::rand(&mut ChaCha20Rng::seed_from_u64(hasher.finish()))
+        }
+    }
+}
+
+/// Deduplicated node and its evaluations at each point
+type EvaluatedNode<T, F> = (Node<T>, Vec<PointEval<F>>);
+
+/// Merges subexpressions that have equal evaluations at all points
+struct SemanticDedup<T: 'static, F> {
+    points: Vec<(F, u64)>,
+    seen: BTreeMap<NodeId<T>, EvaluatedNode<T, F>>,
+    by_evaluations: HashMap<Vec<F>, Node<T>>,
+}
+
+impl<T: Copy + Ord + Hash + IntoField<F>, F: Field> SemanticDedup<T, F> {
+    fn child(&mut self, child: &Node<T>) -> EvaluatedNode<T, F> {
+        // nodes shared by the expression are only visited once
+        let id = Arc::as_ptr(child);
+        if let Some(entry) = self.seen.get(&id) {
+            return entry.clone();
+        }
+        let entry = self.node(child);
+        self.seen.insert(id, entry.clone());
+        entry
+    }
+
+    fn node(&mut self, expr: &Expr<AlgebraicItem<T>>) -> EvaluatedNode<T, F> {
+        use Expr::*;
+        fn zip<F>(
+            a: Vec<PointEval<F>>,
+            b: Vec<PointEval<F>>,
+            op: impl Fn(PointEval<F>, PointEval<F>) -> PointEval<F>,
+        ) -> Vec<PointEval<F>> {
+            a.into_iter().zip(b).map(|(a, b)| op(a, b)).collect()
+        }
+        let (expr, evals) = match expr {
+            Leaf(leaf) => {
+                let evals = self.points.iter();
+                let evals = evals.map(|&(x, seed)| PointEval(Some(eval_leaf_at(leaf, x, seed))));
+                (Leaf(leaf.clone()), evals.collect())
+            }
+            Neg(a) => {
+                let (a, a_evals) = self.child(a);
+                (Neg(a), a_evals.into_iter().map(|v| -v).collect())
+            }
+            Add(a, b) => {
+                let ((a, a_evals), (b, b_evals)) = (self.child(a), self.child(b));
+                (Add(a, b), zip(a_evals, b_evals, |a, b| a + b))
+            }
+            Mul(a, b) => {
+                let ((a, a_evals), (b, b_evals)) = (self.child(a), self.child(b));
+                (Mul(a, b), zip(a_evals, b_evals, |a, b| a * b))
+            }
+            Div(a, b) => {
+                let ((a, a_evals), (b, b_evals)) = (self.child(a), self.child(b));
+                (Div(a, b), zip(a_evals, b_evals, |a, b| a / b))
+            }
+            &Pow(ref a, e) => {
+                let (a, a_evals) = self.child(a);
+                (Pow(a, e), a_evals.into_iter().map(|v| v.pow(e)).collect())
+            }
+        };
+        let node = match evals.iter().map(|v| v.0).collect::<Option<Vec<F>>>() {
+            Some(key) => Arc::clone(
+                self.by_evaluations
+                    .entry(key)
+                    .or_insert_with(|| Arc::new(expr)),
+            ),
+            None => Arc::new(expr),
+        };
+        (node, evals)
+    }
+}
+
+/// Field element that is None once a division by zero has occurred
+#[derive(Clone)]
+struct PointEval<F>(Option<F>);
+
+impl<F: Field> Neg for PointEval<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(self.0.map(|v| -v))
+    }
+}
+
+impl<F: Field> Add for PointEval<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0.zip(rhs.0).map(|(a, b)| a + b))
+    }
+}
+
+impl<F: Field> Mul for PointEval<F> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0.zip(rhs.0).map(|(a, b)| a * b))
+    }
+}
+
+impl<F: Field> Div for PointEval<F> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let inverse = rhs.0.and_then(|v| v.inverse());
+        Self(self.0.zip(inverse).map(|(a, b)| a * b))
+    }
+}
+
+impl<F: Field> Pow<usize> for PointEval<F> {
+    type Output = Self;
+
+    fn pow(self, rhs: usize) -> Self::Output {
+        Self(self.0.map(|v| v.pow([rhs as u64])))
+    }
+}
+
 impl<T> From<Expr<AlgebraicItem<T>>> for Constraint<T> {
     fn from(value: Expr<AlgebraicItem<T>>) -> Self {
         Self::new(value)
@@ -270,17 +899,17 @@ impl<T: 'static> Deref for Constraint<T> {
     type Target = Expr<AlgebraicItem<T>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.expr
     }
 }
 
 impl<T> DerefMut for Constraint<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.expr
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub enum CompositionItem<T: 'static> {
     Item(AlgebraicItem<T>),
     CompositionCoeff(usize),
@@ -288,7 +917,7 @@ pub enum CompositionItem<T: 'static> {
 
 impl<T> CompositionItem<T> {
     // Returns the item's corresponding degree
-    const fn degree(&self, trace_degree: usize) -> Degree {
+    fn degree(&self, trace_degree: usize) -> Degree {
         match &self {
             Self::Item(item) => item.degree(trace_degree),
             Self::CompositionCoeff(_) => Degree(0, 0),
@@ -303,18 +932,36 @@ impl<T: Zero> Sum<Self> for Expr<CompositionItem<T>> {
     }
 }
 
-pub struct CompositionConstraint<T: 'static>(Expr<CompositionItem<T>>);
+pub struct CompositionConstraint<T: 'static> {
+    expr: Expr<CompositionItem<T>>,
+    metadata: Vec<ConstraintMetadata>,
+}
 
 impl<T: Clone + Copy + Zero + Ord + Hash> CompositionConstraint<T> {
     pub const fn new(expression: Expr<CompositionItem<T>>) -> Self {
-        Self(expression)
+        Self {
+            expr: expression,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Attaches the metadata of the constraints that were composed
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: Vec<ConstraintMetadata>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Metadata of the composed constraints in the order they were composed
+    pub fn constraint_metadata(&self) -> &[ConstraintMetadata] {
+        &self.metadata
     }
 
     /// Calculates an upper bound on the degree in X.
     /// Output is of the form `(numerator_degree, denominator_degree)`
     pub fn degree(&self, trace_degree: usize) -> (usize, usize) {
         let Degree(numerator_degree, denominator_degree) =
-            self.0.eval(&mut |leaf| leaf.degree(trace_degree));
+            self.expr.eval(&mut |leaf| leaf.degree(trace_degree));
         (numerator_degree, denominator_degree)
     }
 
@@ -331,7 +978,7 @@ impl<T> Deref for CompositionConstraint<T> {
     type Target = Expr<CompositionItem<T>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.expr
     }
 }
 
@@ -349,6 +996,11 @@ const fn blowup_factor(
 pub trait Hint {
     fn index(&self) -> usize;
 
+    /// Name of the hint used in diagnostics
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+
     fn hint<T>(&self) -> Expr<AlgebraicItem<T>> {
         AlgebraicItem::Hint(self.index()).into()
     }
@@ -361,13 +1013,24 @@ impl Hint for usize {
 }
 
 pub trait VerifierChallenge {
-    /// Get the challenge index
+    /// Get the challenge index within its round
     fn index(&self) -> usize;
 
+    /// Round the challenge is drawn in. Round `i` is drawn after trace segment
+    /// `i` is committed to so only later segments can depend on the challenge.
+    fn round(&self) -> usize {
+        0
+    }
+
+    /// Name of the challenge used in diagnostics
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+
     /// Symbolic representation of a challenge
     // TODO: terrible name. Needs refactoring
     fn challenge<T>(&self) -> Expr<AlgebraicItem<T>> {
-        AlgebraicItem::Challenge(self.index()).into()
+        AlgebraicItem::Challenge(self.round(), self.index()).into()
     }
 }
 
@@ -377,12 +1040,120 @@ impl VerifierChallenge for usize {
     }
 }
 
+/// A challenge of the form `(round, index)`
+impl VerifierChallenge for (usize, usize) {
+    fn index(&self) -> usize {
+        self.1
+    }
+
+    fn round(&self) -> usize {
+        self.0
+    }
+}
+
+/// Restricts an expression to rows of the execution trace by dividing it by
+/// the polynomial that vanishes on those rows. Each returns a constraint that
+/// requires the expression to be zero on the rows.
+impl<Fp: FftField, Fq> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+    /// Constrains the first row
+    pub fn first(self) -> Constraint<FieldVariant<Fp, Fq>> {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        Constraint::new(self / (X - one)).in_group(ConstraintGroup::Boundary)
+    }
+
+    /// Constrains the last row
+    pub fn last(self, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        let last_x = row_x(trace_len - 1, trace_len);
+        Constraint::new(self / (AlgebraicItem::X - last_x)).in_group(ConstraintGroup::Terminal)
+    }
+
+    /// Constrains a single row
+    ///
+    /// # Panics
+    /// Panics if the row is outside the trace.
+    pub fn at_row(self, row: usize, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        assert!(row < trace_len);
+        Constraint::new(self / (AlgebraicItem::X - row_x(row, trace_len)))
+    }
+
+    /// Constrains every row except the last. Used for transition constraints
+    /// that read the next row.
+    pub fn every_row_except_last(self, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.every_except_last(1, 0, trace_len)
+            .in_group(ConstraintGroup::Transition)
+    }
+
+    /// Constrains rows `offset`, `offset + step`, `offset + 2 * step`, ...
+    ///
+    /// # Panics
+    /// Panics if `step` is not a power of two no larger than the trace length
+    /// or if `offset` is not less than `step`.
+    pub fn every(
+        self,
+        step: usize,
+        offset: usize,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        Constraint::new(self / strided_vanishing_poly(step, offset, trace_len))
+    }
+
+    /// Constrains the same rows as [`Expr::every`] except the last of them.
+    /// Used for transition constraints over [`VirtualColumn`]s.
+    ///
+    /// # Panics
+    /// Panics if `step` is not a power of two no larger than the trace length
+    /// or if `offset` is not less than `step`.
+    pub fn every_except_last(
+        self,
+        step: usize,
+        offset: usize,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        let vanishing_poly = strided_vanishing_poly(step, offset, trace_len);
+        let last_x = row_x(trace_len - step + offset, trace_len);
+        Constraint::new(self * (AlgebraicItem::X - last_x) / vanishing_poly)
+    }
+}
+
+/// Returns the polynomial that vanishes on rows `offset`, `offset + step`,
+/// `offset + 2 * step`, ...
+fn strided_vanishing_poly<Fp: FftField, Fq>(
+    step: usize,
+    offset: usize,
+    trace_len: usize,
+) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    assert!(step.is_power_of_two() && step <= trace_len);
+    assert!(offset < step);
+    // NOTE: the `x` coordinates of the rows are the roots of
+    // `x^(n/step) - t_offset^(n/step)` e.g. `x^n - 1` for every row
+    let num_rows = trace_len / step;
+    let offset_x = row_x(offset * num_rows, trace_len);
+    if num_rows == 1 {
+        X - offset_x
+    } else {
+        X.pow(num_rows) - offset_x
+    }
+}
+
+/// Returns the `x` coordinate of a row in the trace domain
+fn row_x<Fp: FftField, Fq>(row: usize, trace_len: usize) -> AlgebraicItem<FieldVariant<Fp, Fq>> {
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    AlgebraicItem::Constant(FieldVariant::Fp(trace_domain.element(row)))
+}
+
 /// An interface for types that can symbolically represent a column of an
 /// execution trace
 pub trait ExecutionTraceColumn {
     /// Returns the execution trace column index
     fn index(&self) -> usize;
 
+    /// Name of the column used in diagnostics e.g. `processor.ip`
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+
     // Create a constraint element for the current cycle
     fn curr<T>(&self) -> Expr<AlgebraicItem<T>> {
         self.offset(0)
@@ -396,6 +1167,39 @@ pub trait ExecutionTraceColumn {
     fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
         AlgebraicItem::Trace(self.index(), offset).into()
     }
+
+    /// Constrains the column to be zero in the first row. See [`Expr::first`].
+    fn first<Fp: FftField, Fq>(&self) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr().first()
+    }
+
+    fn last<Fp: FftField, Fq>(&self, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr().last(trace_len)
+    }
+
+    fn at_row<Fp: FftField, Fq>(
+        &self,
+        row: usize,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr().at_row(row, trace_len)
+    }
+
+    fn every_row_except_last<Fp: FftField, Fq>(
+        &self,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr().every_row_except_last(trace_len)
+    }
+
+    fn every<Fp: FftField, Fq>(
+        &self,
+        step: usize,
+        offset: usize,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr().every(step, offset, trace_len)
+    }
 }
 
 impl ExecutionTraceColumn for usize {
@@ -404,6 +1208,207 @@ impl ExecutionTraceColumn for usize {
     }
 }
 
+/// A logical column interleaved into a physical column of the execution trace
+///
+/// Row `i` of the virtual column is stored in row `offset + i * step` of the
+/// physical column. This packs columns that only need a value every `step`
+/// rows into a single column.
+///
+/// Virtual columns are read with [`AlgebraicItem::Trace`] leaves whose offset
+/// is relative to rows `0`, `step`, `2 * step`, ... of the physical column.
+/// The row helpers of [`ExecutionTraceColumn`] are in terms of the rows of the
+/// virtual column and divide by vanishing polynomials over these rows. To
+/// constrain expressions that read virtual columns use [`Expr::every`] and
+/// [`Expr::every_except_last`] with the step and an offset of `0`. Register
+/// virtual columns with [`Names::with_virtual_columns`] so diagnostics refer to
+/// them rather than the physical column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VirtualColumn {
+    column: usize,
+    step: usize,
+    offset: usize,
+    name: Option<&'static str>,
+}
+
+impl VirtualColumn {
+    /// # Panics
+    /// Panics if `step` is not a power of two or if `offset` is not less than
+    /// `step`.
+    pub const fn new(column: usize, step: usize, offset: usize) -> Self {
+        assert!(step.is_power_of_two());
+        assert!(offset < step);
+        Self {
+            column,
+            step,
+            offset,
+            name: None,
+        }
+    }
+
+    /// Sets the name of the column used in diagnostics
+    #[must_use]
+    pub const fn named(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub const fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Returns the row of the physical column that stores the first row of the
+    /// virtual column
+    pub const fn first_row(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the row of the physical column that stores row `row` of the
+    /// virtual column
+    pub const fn physical_row(&self, row: usize) -> usize {
+        self.offset + row * self.step
+    }
+
+    /// Returns the number of rows of the virtual column in a trace of length
+    /// `trace_len`
+    pub const fn num_rows(&self, trace_len: usize) -> usize {
+        trace_len / self.step
+    }
+}
+
+impl ExecutionTraceColumn for VirtualColumn {
+    fn index(&self) -> usize {
+        self.column
+    }
+
+    fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
+        let offset = self.offset as isize + offset * self.step as isize;
+        AlgebraicItem::Trace(self.column, offset).into()
+    }
+
+    fn last<Fp: FftField, Fq>(&self, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.at_row(self.num_rows(trace_len) - 1, trace_len)
+            .in_group(ConstraintGroup::Terminal)
+    }
+
+    fn at_row<Fp: FftField, Fq>(
+        &self,
+        row: usize,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr().at_row(row * self.step, trace_len)
+    }
+
+    fn every_row_except_last<Fp: FftField, Fq>(
+        &self,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr()
+            .every_except_last(self.step, 0, trace_len)
+            .in_group(ConstraintGroup::Transition)
+    }
+
+    fn every<Fp: FftField, Fq>(
+        &self,
+        step: usize,
+        offset: usize,
+        trace_len: usize,
+    ) -> Constraint<FieldVariant<Fp, Fq>> {
+        self.curr()
+            .every(step * self.step, offset * self.step, trace_len)
+    }
+}
+
+/// Names of the columns, challenges and hints of an AIR used in diagnostics
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Names {
+    columns: BTreeMap<usize, &'static str>,
+    challenges: BTreeMap<(usize, usize), &'static str>,
+    hints: BTreeMap<usize, &'static str>,
+    virtual_columns: BTreeMap<usize, Vec<VirtualColumn>>,
+}
+
+impl Names {
+    #[must_use]
+    pub fn with_columns<C: ExecutionTraceColumn>(
+        mut self,
+        columns: impl IntoIterator<Item = C>,
+    ) -> Self {
+        for column in columns {
+            if let Some(name) = column.name() {
+                self.columns.insert(column.index(), name);
+            }
+        }
+        self
+    }
+
+    /// Registers virtual columns so they are printed by name with offsets in
+    /// rows of the virtual column and lints check these offsets rather than
+    /// offsets in rows of the physical column
+    #[must_use]
+    pub fn with_virtual_columns(
+        mut self,
+        columns: impl IntoIterator<Item = VirtualColumn>,
+    ) -> Self {
+        for column in columns {
+            let col = column.index();
+            self.virtual_columns.entry(col).or_default().push(column);
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn with_challenges<C: VerifierChallenge>(
+        mut self,
+        challenges: impl IntoIterator<Item = C>,
+    ) -> Self {
+        for challenge in challenges {
+            if let Some(name) = challenge.name() {
+                let id = (challenge.round(), challenge.index());
+                self.challenges.insert(id, name);
+            }
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn with_hints<H: Hint>(mut self, hints: impl IntoIterator<Item = H>) -> Self {
+        for hint in hints {
+            if let Some(name) = hint.name() {
+                self.hints.insert(hint.index(), name);
+            }
+        }
+        self
+    }
+
+    pub fn column(&self, col: usize) -> Option<&'static str> {
+        self.columns.get(&col).copied()
+    }
+
+    pub fn challenge(&self, round: usize, index: usize) -> Option<&'static str> {
+        self.challenges.get(&(round, index)).copied()
+    }
+
+    pub fn hint(&self, index: usize) -> Option<&'static str> {
+        self.hints.get(&index).copied()
+    }
+
+    /// Returns the registered virtual column that row `offset` of physical
+    /// column `col` belongs to and the offset in rows of the virtual column
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn virtual_column(&self, col: usize, offset: isize) -> Option<(VirtualColumn, isize)> {
+        self.virtual_columns.get(&col)?.iter().find_map(|column| {
+            let step = column.step as isize;
+            let offset = offset - column.offset as isize;
+            (offset % step == 0).then_some((*column, offset / step))
+        })
+    }
+}
+
 /// Degree of the form `(numerator_degree, denominator_degree)`
 struct Degree(pub usize, pub usize);
 
@@ -1,128 +1,536 @@
 //! Tools for debugging issues that may arrive with AIR or STARK
-//! TODO:
 
+use crate::air::constraints_with_assertions;
+use crate::air::num_extension_columns;
+use crate::air::AirConfig;
 use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ConstraintId;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Names;
+use crate::expression::Expr;
 use crate::hints::Hints;
 use crate::stark::Stark;
+use crate::utils::horner_evaluate;
+use crate::utils::FieldVariant;
 use crate::Matrix;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_std::UniformRand;
+use core::fmt::Debug;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 
-/// Checks AIR constraints are valid
-pub const fn default_validate_constraints<S: Stark>(
-    _this: &S,
-    _challenges: &Challenges<S::Fq>,
-    _hints: &Hints<S::Fq>,
-    _base_trace: &Matrix<S::Fp>,
-    _extension_trace: Option<&Matrix<S::Fq>>,
-) {
-    // ```text
-    // TODO: move constraint checking from air.rs into here
-    // #[cfg(all(feature = "std", debug_assertions))]
-    // fn validate_constraints(
-    //     &self,
-    //     challenges: &Challenges<C::Fq>,
-    //     hints: &Hints<C::Fq>,
-    //     base_trace: &crate::Matrix<C::Fp>,
-    //     extension_trace: Option<&crate::Matrix<C::Fq>>,
-    // ) {
-    //     use AlgebraicItem::*;
-    //     use Expr::*;
-
-    //     let num_execution_trace_columns = C::NUM_BASE_COLUMNS +
-    // C::NUM_EXTENSION_COLUMNS;     let mut col_indicies = vec![false;
-    // num_execution_trace_columns];     let mut challenge_indicies =
-    // vec![false; challenges.len()];     let mut hint_indicies =
-    // vec![false; hints.len()];
-
-    //     for constraint in &self.constraints {
-    //         constraint.traverse(&mut |node| match node {
-    //             Leaf(Challenge(i)) => challenge_indicies[*i] = true,
-    //             Leaf(Trace(i, _)) => col_indicies[*i] = true,
-    //             Leaf(Hint(i)) => hint_indicies[*i] = true,
-    //             _ => {}
-    //         })
-    //     }
-
-    //     for (index, exists) in col_indicies.into_iter().enumerate() {
-    //         if !exists {
-    //             // TODO: make assertion
-    //             println!("WARN: no constraints for execution trace column
-    // {index}");         }
-    //     }
-
-    //     for (index, exists) in challenge_indicies.into_iter().enumerate()
-    // {         if !exists {
-    //             // TODO: make assertion
-    //             println!("WARN: challenge at index {index} never used");
-    //         }
-    //     }
-
-    //     for (index, exists) in hint_indicies.into_iter().enumerate() {
-    //         if !exists {
-    //             // TODO: make assertion
-    //             println!("WARN: hint at index {index} never used");
-    //         }
-    //     }
-
-    //     let trace_domain = self.trace_domain();
-    //     let base_column_range = Self::base_column_range();
-    //     let extension_column_range = Self::extension_column_range();
-
-    //     // helper function to get a value from the execution trace
-    //     let get_trace_value = |row: usize, col: usize, offset: isize| {
-    //         let pos = (row as isize +
-    // offset).rem_euclid(trace_domain.size() as isize) as usize;
-    // if base_column_range.contains(&col) {
-    // FieldVariant::Fp(base_trace.0[col][pos])         } else if
-    // extension_column_range.contains(&col) {             let col =
-    // col - C::NUM_BASE_COLUMNS;
-    // FieldVariant::Fq(extension_trace.unwrap().0[col][pos])
-    //         } else {
-    //             unreachable!("requested column {col} does not exist")
-    //         }
-    //     };
-
-    //     for (c_idx, constraint) in
-    // self.constraints().into_iter().enumerate() {         for
-    // (row, x) in trace_domain.elements().enumerate() {
-    // let is_valid = constraint                 .check(&mut |leaf|
-    // match leaf {                     X => FieldVariant::Fp(x),
-    //                     &Hint(i) => FieldVariant::Fq(hints[i]),
-    //                     &Challenge(i) => FieldVariant::Fq(challenges[i]),
-    //                     &Trace(col, offset) => get_trace_value(row, col,
-    // offset),                     &Constant(c) => c,
-    //                 })
-    //                 .is_some();
-
-    //             if !is_valid {
-    //                 let mut vals = vec![format!("x = {x}")];
-    //                 constraint.traverse(&mut |node| match *node {
-    //                     // get a description of each leaf node
-    //                     Leaf(Trace(col, offset)) => vals.push(format!(
-    //                         "Trace(col={col:0>3}, offset={offset:0>3}) =
-    // {}",                         get_trace_value(row, col,
-    // offset)                     )),
-    //                     Leaf(Challenge(i)) => {
-    //                         vals.push(format!("Challenge({i}) = {}",
-    // challenges[i]))                     }
-    //                     Leaf(Hint(i)) => vals.push(format!("Hint({i}) =
-    // {}", hints[i])),                     // skip tree nodes
-    //                     _ => (),
-    //                 });
-
-    //                 vals.sort();
-    //                 vals.dedup();
-
-    //                 // TODO: display constraint? eprintln!("Constraint
-    // is:\n{constraint}\n");                 #[cfg(feature = "std")]
-    //                 eprint!("Constraint {c_idx} does not evaluate to a
-    // low degree polynomial. ");                 #[cfg(feature =
-    // "std")]                 eprintln!("Divide by zero occurs at
-    // row {row}.\n");                 #[cfg(feature = "std")]
-    //                 eprintln!("Expression values:\n{}", vals.join("\n"));
-    //                 panic!();
-    //             }
-    //         }
-    //     }
-    // }
-    // ```
+/// Maximum number of failing rows reported for each constraint
+pub const MAX_VIOLATIONS_PER_CONSTRAINT: usize = 4;
+
+/// Constraints are not expected to reach further than this many rows from the
+/// current row
+pub const MAX_TRACE_OFFSET: usize = 64;
+
+/// A likely mistake in an AIR found by inspecting its constraints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AirLint {
+    /// The column is not referenced by any constraint
+    UnconstrainedColumn {
+        col: usize,
+        name: Option<&'static str>,
+    },
+    /// The challenge is drawn by the verifier but no constraint uses it
+    UnusedChallenge {
+        round: usize,
+        index: usize,
+        name: Option<&'static str>,
+    },
+    /// The hint is generated but no constraint uses it
+    UnusedHint {
+        index: usize,
+        name: Option<&'static str>,
+    },
+    /// A constraint uses a hint that is not generated
+    MissingHint {
+        index: usize,
+        name: Option<&'static str>,
+    },
+    /// A constraint reads a row further than [`MAX_TRACE_OFFSET`] from the
+    /// current row or wraps around the entire trace. The offset of a virtual
+    /// column registered in [`Names`] is in rows of the virtual column.
+    TraceOffsetOutOfRange {
+        constraint: ConstraintId,
+        col: usize,
+        name: Option<&'static str>,
+        offset: isize,
+    },
+    /// The constraint has degree zero so can not depend on the trace
+    ZeroDegreeConstraint { constraint: ConstraintId },
+    /// The constraint is algebraically equal to an earlier constraint
+    DuplicateConstraint {
+        constraint: ConstraintId,
+        duplicate_of: ConstraintId,
+    },
+    /// The generated constraint evaluator doesn't match the composition
+    /// constraint so constraints are evaluated by the interpreter
+    OutdatedGeneratedEvaluator { expected: u64, actual: u64 },
+}
+
+impl Display for AirLint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            &Self::UnconstrainedColumn { col, name } => {
+                let column = Symbol("column", col, name);
+                write!(f, "{column} is not referenced by any constraint")
+            }
+            &Self::UnusedChallenge { round, index, name } => {
+                let challenge = ChallengeSymbol(round, index, name);
+                write!(f, "{challenge} is not used by any constraint")
+            }
+            &Self::UnusedHint { index, name } => {
+                let hint = Symbol("hint", index, name);
+                write!(f, "{hint} is not used by any constraint")
+            }
+            &Self::MissingHint { index, name } => {
+                let hint = Symbol("hint", index, name);
+                write!(f, "{hint} is used but not generated")
+            }
+            Self::TraceOffsetOutOfRange {
+                constraint,
+                col,
+                name,
+                offset,
+            } => {
+                let column = Symbol("column", *col, *name);
+                write!(
+                    f,
+                    "{constraint} reads {column} at out of range offset {offset}"
+                )
+            }
+            Self::ZeroDegreeConstraint { constraint } => {
+                write!(f, "{constraint} has degree zero")
+            }
+            Self::DuplicateConstraint {
+                constraint,
+                duplicate_of,
+            } => write!(f, "{constraint} is equivalent to {duplicate_of}"),
+            Self::OutdatedGeneratedEvaluator { expected, actual } => write!(
+                f,
+                "generated constraint evaluator has fingerprint {actual:#x} but the composition \
+                 constraint has fingerprint {expected:#x}"
+            ),
+        }
+    }
+}
+
+/// Displays a column or hint by name if it has one
+struct Symbol(&'static str, usize, Option<&'static str>);
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let Self(kind, index, name) = *self;
+        match name {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{kind} {index}"),
+        }
+    }
+}
+
+/// Displays a challenge by name if it has one. Challenges of the first round
+/// are displayed without their round.
+struct ChallengeSymbol(usize, usize, Option<&'static str>);
+
+impl Display for ChallengeSymbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let Self(round, index, name) = *self;
+        match name {
+            Some(name) => f.write_str(name),
+            None if round == 0 => write!(f, "challenge {index}"),
+            None => write!(f, "challenge {index} of round {round}"),
+        }
+    }
+}
+
+/// Finds columns, challenges, trace offsets and duplicate constraints that are
+/// likely mistakes in the AIR. Under-constrained columns in particular are a
+/// common soundness bug.
+pub fn lint_constraints<A: AirConfig>(
+    trace_len: usize,
+    constraints: &[Constraint<FieldVariant<A::Fp, A::Fq>>],
+) -> Vec<AirLint> {
+    let names = A::names();
+    let num_columns =
+        A::NUM_BASE_COLUMNS + A::NUM_PREPROCESSED_COLUMNS + num_extension_columns::<A>();
+    let mut used_columns = vec![false; num_columns];
+    let mut used_challenges = A::NUM_CHALLENGES
+        .iter()
+        .map(|&num_challenges| vec![false; num_challenges])
+        .collect::<Vec<Vec<bool>>>();
+    let mut lints = Vec::new();
+
+    for (i, constraint) in constraints.iter().enumerate() {
+        constraint.traverse(&mut |node| match *node {
+            Expr::Leaf(AlgebraicItem::Trace(col, offset)) => {
+                if let Some(used) = used_columns.get_mut(col) {
+                    *used = true;
+                }
+                // offsets of virtual columns are in rows of the virtual column
+                let (name, virtual_offset) = match names.virtual_column(col, offset) {
+                    Some((column, virtual_offset)) => (column.name(), virtual_offset),
+                    None => (names.column(col), offset),
+                };
+                let wraps = offset.unsigned_abs() >= trace_len;
+                if virtual_offset.unsigned_abs() > MAX_TRACE_OFFSET || wraps {
+                    let lint = AirLint::TraceOffsetOutOfRange {
+                        constraint: constraint.metadata().id(i),
+                        col,
+                        name,
+                        offset: virtual_offset,
+                    };
+                    if !lints.contains(&lint) {
+                        lints.push(lint);
+                    }
+                }
+            }
+            Expr::Leaf(AlgebraicItem::Challenge(round, index)) => {
+                let used = used_challenges
+                    .get_mut(round)
+                    .and_then(|r| r.get_mut(index));
+                if let Some(used) = used {
+                    *used = true;
+                }
+            }
+            _ => {}
+        });
+
+        let (numerator_degree, _) = constraint.degree(trace_len - 1);
+        if numerator_degree == 0 {
+            let constraint = constraint.metadata().id(i);
+            lints.push(AirLint::ZeroDegreeConstraint { constraint });
+        }
+    }
+
+    // only constraints with equal evaluation hashes need to be compared. the
+    // rng is seeded so lints are the same every run
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let x = A::Fq::rand(&mut rng);
+    let mut constraints_by_hash = BTreeMap::<u64, Vec<usize>>::new();
+    for (i, constraint) in constraints.iter().enumerate() {
+        let candidates = constraints_by_hash
+            .entry(constraint.evaluation_hash(x))
+            .or_default();
+        let duplicate_of = candidates
+            .iter()
+            .find(|&&j| constraints[j].semantically_eq_with_rng(constraint, &mut rng));
+        if let Some(&j) = duplicate_of {
+            lints.push(AirLint::DuplicateConstraint {
+                constraint: constraint.metadata().id(i),
+                duplicate_of: constraints[j].metadata().id(j),
+            });
+        } else {
+            candidates.push(i);
+        }
+    }
+
+    let unconstrained_columns = used_columns
+        .into_iter()
+        .enumerate()
+        .filter(|(_, used)| !used);
+    lints.extend(unconstrained_columns.map(|(col, _)| {
+        let name = names.column(col);
+        AirLint::UnconstrainedColumn { col, name }
+    }));
+    let unused_challenges = used_challenges
+        .into_iter()
+        .enumerate()
+        .flat_map(|(round, used)| {
+            used.into_iter()
+                .enumerate()
+                .map(move |(i, u)| (round, i, u))
+        })
+        .filter(|&(_, _, used)| !used);
+    lints.extend(unused_challenges.map(|(round, index, _)| {
+        let name = names.challenge(round, index);
+        AirLint::UnusedChallenge { round, index, name }
+    }));
+    lints
+}
+
+/// Finds hints that are generated but unused or used but not generated
+pub fn lint_hints<A: AirConfig>(
+    constraints: &[Constraint<FieldVariant<A::Fp, A::Fq>>],
+    hints: &Hints<A::Fq>,
+) -> Vec<AirLint> {
+    let names = A::names();
+    let mut used_hints = BTreeSet::new();
+    for constraint in constraints {
+        constraint.traverse(&mut |node| {
+            if let Expr::Leaf(AlgebraicItem::Hint(index)) = *node {
+                used_hints.insert(index);
+            }
+        });
+    }
+
+    let unused_hints = (0..hints.len()).filter(|index| !used_hints.contains(index));
+    let missing_hints = used_hints.iter().filter(|&&index| index >= hints.len());
+    unused_hints
+        .map(|index| AirLint::UnusedHint {
+            index,
+            name: names.hint(index),
+        })
+        .chain(missing_hints.map(|&index| AirLint::MissingHint {
+            index,
+            name: names.hint(index),
+        }))
+        .collect()
+}
+
+/// A row of the execution trace where a constraint does not hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolation<Fp, Fq> {
+    pub constraint: ConstraintId,
+    pub row: usize,
+    /// Trace cells read by the constraint as `(column, offset, value)`. Offsets
+    /// are relative to `row` and wrap around the end of the trace.
+    pub trace_cells: Vec<(usize, isize, FieldVariant<Fp, Fq>)>,
+    /// Challenges read by the constraint as `((round, index), value)`
+    pub challenges: Vec<((usize, usize), Fq)>,
+    /// Hints read by the constraint as `(index, value)`
+    pub hints: Vec<(usize, Fq)>,
+}
+
+/// Rows of the execution trace that do not satisfy the AIR constraints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolations<Fp, Fq> {
+    pub violations: Vec<ConstraintViolation<Fp, Fq>>,
+    /// Names of the columns, challenges and hints used when displaying
+    pub names: Names,
+}
+
+impl<Fp: Display, Fq: Display> Display for ConstraintViolations<Fp, Fq> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let names = &self.names;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            let constraint = &violation.constraint;
+            write!(f, "{constraint} failed at row {}", violation.row)?;
+            if let Some(location) = constraint.location {
+                write!(f, " ({location})")?;
+            }
+            for &(col, offset, ref value) in &violation.trace_cells {
+                let column = Symbol("column", col, names.column(col));
+                write!(f, "\n  {column}[{offset}] = {value}")?;
+            }
+            for &((round, index), ref value) in &violation.challenges {
+                let name = names.challenge(round, index);
+                let challenge = ChallengeSymbol(round, index, name);
+                write!(f, "\n  {challenge} = {value}")?;
+            }
+            for &(index, ref value) in &violation.hints {
+                let hint = Symbol("hint", index, names.hint(index));
+                write!(f, "\n  {hint} = {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Fp: Debug + Display, Fq: Debug + Display> std::error::Error for ConstraintViolations<Fp, Fq> {}
+
+/// Checks the AIR constraints hold over every row of the execution trace
+///
+/// # Errors
+///
+/// Returns the first failing rows of each constraint that does not hold.
+pub fn default_validate_constraints<S: Stark>(
+    this: &S,
+    challenges: &Challenges<S::Fq>,
+    hints: &Hints<S::Fq>,
+    base_trace: &Matrix<S::Fp>,
+    extension_trace: &[Matrix<S::Fq>],
+) -> Result<(), ConstraintViolations<S::Fp, S::Fq>> {
+    let trace_len = base_trace.num_rows();
+    let assertions = S::AirConfig::assertions(trace_len, &this.get_public_inputs());
+    let constraints = constraints_with_assertions::<S::AirConfig>(trace_len, &assertions);
+    let preprocessed_trace = S::AirConfig::preprocessed_columns(trace_len);
+    check_constraints::<S::AirConfig>(
+        constraints.iter().enumerate(),
+        challenges,
+        hints,
+        base_trace,
+        preprocessed_trace.as_ref(),
+        extension_trace,
+    )
+}
+
+/// Checks the constraints that only read from the base and preprocessed
+/// columns. These don't depend on verifier challenges so can be checked before
+/// the base trace is committed to.
+///
+/// # Errors
+///
+/// Returns the first failing rows of each constraint that does not hold.
+pub fn validate_base_constraints<A: AirConfig>(
+    constraints: &[Constraint<FieldVariant<A::Fp, A::Fq>>],
+    base_trace: &Matrix<A::Fp>,
+) -> Result<(), ConstraintViolations<A::Fp, A::Fq>> {
+    let base_constraints = constraints
+        .iter()
+        .enumerate()
+        .filter(|(_, constraint)| is_base_constraint::<A>(constraint));
+    let preprocessed_trace = A::preprocessed_columns(base_trace.num_rows());
+    check_constraints::<A>(
+        base_constraints,
+        &Challenges::default(),
+        &[],
+        base_trace,
+        preprocessed_trace.as_ref(),
+        &[],
+    )
+}
+
+/// Checks the constraints skipped by [`validate_base_constraints`] i.e. those
+/// that read from the extension columns, challenges or hints. Together the two
+/// check every constraint exactly once.
+///
+/// # Errors
+///
+/// Returns the first failing rows of each constraint that does not hold.
+pub fn validate_extension_constraints<A: AirConfig>(
+    constraints: &[Constraint<FieldVariant<A::Fp, A::Fq>>],
+    challenges: &Challenges<A::Fq>,
+    hints: &[A::Fq],
+    base_trace: &Matrix<A::Fp>,
+    extension_trace: &[Matrix<A::Fq>],
+) -> Result<(), ConstraintViolations<A::Fp, A::Fq>> {
+    let extension_constraints = constraints
+        .iter()
+        .enumerate()
+        .filter(|(_, constraint)| !is_base_constraint::<A>(constraint));
+    let preprocessed_trace = A::preprocessed_columns(base_trace.num_rows());
+    check_constraints::<A>(
+        extension_constraints,
+        challenges,
+        hints,
+        base_trace,
+        preprocessed_trace.as_ref(),
+        extension_trace,
+    )
+}
+
+/// Returns true if the constraint only reads from the base and preprocessed
+/// columns
+fn is_base_constraint<A: AirConfig>(constraint: &Constraint<FieldVariant<A::Fp, A::Fq>>) -> bool {
+    let num_base_columns = A::NUM_BASE_COLUMNS + A::NUM_PREPROCESSED_COLUMNS;
+    let mut is_base_constraint = true;
+    constraint.traverse(&mut |node| match node {
+        Expr::Leaf(AlgebraicItem::Challenge(..) | AlgebraicItem::Hint(_)) => {
+            is_base_constraint = false;
+        }
+        &Expr::Leaf(AlgebraicItem::Trace(col, _)) if col >= num_base_columns => {
+            is_base_constraint = false;
+        }
+        _ => {}
+    });
+    is_base_constraint
+}
+
+/// Evaluates each constraint over the trace domain and collects the first
+/// [`MAX_VIOLATIONS_PER_CONSTRAINT`] rows where it does not hold
+fn check_constraints<'a, A: AirConfig>(
+    constraints: impl IntoIterator<Item = (usize, &'a Constraint<FieldVariant<A::Fp, A::Fq>>)>,
+    challenges: &Challenges<A::Fq>,
+    hints: &[A::Fq],
+    base_trace: &Matrix<A::Fp>,
+    preprocessed_trace: Option<&Matrix<A::Fp>>,
+    extension_trace: &[Matrix<A::Fq>],
+) -> Result<(), ConstraintViolations<A::Fp, A::Fq>> {
+    use AlgebraicItem::*;
+    let trace_len = base_trace.num_rows();
+    let trace_domain = crate::air::trace_domain::<A>(trace_len);
+    // preprocessed columns follow the base columns
+    let num_base_columns = A::NUM_BASE_COLUMNS + A::NUM_PREPROCESSED_COLUMNS;
+    // extension columns of all segments in order
+    let extension_columns = extension_trace
+        .iter()
+        .flat_map(|segment| segment.iter())
+        .collect::<Vec<_>>();
+
+    // gets a value from the execution trace. offsets wrap around the trace
+    let trace_value = |row: usize, col: usize, offset: isize| {
+        #[allow(clippy::cast_possible_wrap)]
+        let pos = (row as isize + offset).rem_euclid(trace_len as isize) as usize;
+        if col < A::NUM_BASE_COLUMNS {
+            FieldVariant::Fp(base_trace.0[col][pos])
+        } else if col < num_base_columns {
+            let preprocessed_trace = preprocessed_trace.expect("preprocessed trace is missing");
+            FieldVariant::Fp(preprocessed_trace.0[col - A::NUM_BASE_COLUMNS][pos])
+        } else {
+            let column = extension_columns.get(col - num_base_columns);
+            FieldVariant::Fq(column.expect("extension trace is missing")[pos])
+        }
+    };
+
+    let mut violations = Vec::new();
+    for (index, constraint) in constraints {
+        let failing_rows = (0..trace_len)
+            .filter(|&row| {
+                let x = trace_domain.element(row);
+                let result = constraint.check(&mut |leaf| match leaf {
+                    X => FieldVariant::Fp(x),
+                    &Constant(c) => c,
+                    &Challenge(round, i) => FieldVariant::Fq(challenges[(round, i)]),
+                    &Hint(i) => FieldVariant::Fq(hints[i]),
+                    Periodic(col) => {
+                        let point = x.pow([(trace_len / col.interval_size()) as u64]);
+                        let coeffs = col.coeffs().iter().map(FieldVariant::as_fq);
+                        let coeffs = coeffs.collect::<Vec<A::Fq>>();
+                        FieldVariant::Fq(horner_evaluate(&coeffs, &A::Fq::from(point)))
+                    }
+                    &Trace(col, offset) => trace_value(row, col, offset),
+                });
+                result.is_none()
+            })
+            .take(MAX_VIOLATIONS_PER_CONSTRAINT);
+
+        let mut challenge_ids = BTreeSet::new();
+        let mut hint_ids = BTreeSet::new();
+        constraint.traverse(&mut |node| match *node {
+            Expr::Leaf(Challenge(round, i)) => _ = challenge_ids.insert((round, i)),
+            Expr::Leaf(Hint(i)) => _ = hint_ids.insert(i),
+            _ => {}
+        });
+
+        for row in failing_rows {
+            violations.push(ConstraintViolation {
+                constraint: constraint.metadata().id(index),
+                row,
+                trace_cells: constraint
+                    .trace_arguments()
+                    .into_iter()
+                    .map(|(col, offset)| (col, offset, trace_value(row, col, offset)))
+                    .collect(),
+                challenges: challenge_ids
+                    .iter()
+                    .map(|&id| (id, challenges[id]))
+                    .collect(),
+                hints: hint_ids.iter().map(|&i| (i, hints[i])).collect(),
+            });
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ConstraintViolations {
+            violations,
+            names: A::names(),
+        })
+    }
 }
@@ -1,3 +1,8 @@
+use crate::bytecode::Inputs;
+use crate::bytecode::Program;
+use crate::challenges::Challenges;
+use crate::codegen::GeneratedEvaluator;
+use crate::codegen::LdeInputs;
 use crate::constraints::AlgebraicItem;
 use crate::constraints::PeriodicColumn;
 use crate::expression::Expr;
@@ -6,33 +11,20 @@ use crate::utils::GpuAllocator;
 use crate::Matrix;
 use crate::StarkExtensionOf;
 use alloc::borrow::Cow;
-use alloc::boxed::Box;
 use alloc::vec::Vec;
-use ark_ff::batch_inversion;
 use ark_ff::FftField;
 use ark_ff::Field;
 use ark_poly::domain::DomainCoeff;
 use ark_poly::EvaluationDomain;
 use ark_poly::Radix2EvaluationDomain;
-use ark_std::cfg_chunks_mut;
-use core::iter::zip;
-use core::ops::Add;
-use core::ops::AddAssign;
-use core::ops::Div;
-use core::ops::Mul;
-use core::ops::MulAssign;
-use core::ops::Neg;
 use ministark_gpu::GpuFftField;
 use ministark_gpu::GpuField;
-use num_traits::Pow;
-#[cfg(feature = "parallel")]
-use rayon::prelude::*;
 use std::collections::BTreeMap;
 
 #[allow(clippy::too_many_arguments)]
 pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>(
     expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
-    challenges: &[Fq],
+    challenges: &Challenges<Fq>,
     hints: &[Fq],
     lde_step: usize,
     domain_offset: Fp,
@@ -79,7 +71,7 @@ fn eval_impl<
     const CHUNK_SIZE: usize,
 >(
     expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
-    challenges: &[Fq],
+    challenges: &Challenges<Fq>,
     hints: &[Fq],
     lde_step: usize,
     domain_offset: Fp,
@@ -88,65 +80,90 @@ fn eval_impl<
     extension_trace_lde_cols: Option<&[&[Fq]]>,
     result: &mut [Fq],
 ) {
-    use AlgebraicItem::*;
     let n = result.len();
-    #[allow(clippy::cast_possible_wrap)]
-    let step = lde_step as isize;
     let trace_len = n / lde_step;
     let num_base_columns = base_trace_lde_cols.len();
-    let num_extension_columns = extension_trace_lde_cols.map_or(0, <[_]>::len);
-    let base_column_range = 0..num_base_columns;
-    let extension_column_range = num_base_columns..num_base_columns + num_extension_columns;
-    let periodic_column_evals_map =
-        build_periodic_column_evals_map(expr, domain_offset, trace_len, lde_step, CHUNK_SIZE);
-    cfg_chunks_mut!(result, CHUNK_SIZE)
-        .enumerate()
-        .for_each(|(i, chunk)| {
-            let chunk_offset = CHUNK_SIZE * i;
-            let chunk_res: [Fq; CHUNK_SIZE] = expr
-                .graph_eval(&mut |leaf| match *leaf {
-                    X => EvalItem::Evals(Box::new(FieldVariant::Fp(extract_lde_chunk(
-                        x_lde,
-                        chunk_offset,
-                    )))),
-                    Constant(v) => EvalItem::Constant(v),
-                    Challenge(i) => EvalItem::Constant(FieldVariant::Fq(challenges[i])),
-                    Hint(i) => EvalItem::Constant(FieldVariant::Fq(hints[i])),
-                    Trace(col_idx, row_offset) => {
-                        let shift = step * row_offset;
-                        let chunk_offset = isize::try_from(chunk_offset).unwrap();
-                        #[allow(clippy::cast_possible_wrap)]
-                        let position = (chunk_offset + shift).rem_euclid(n as isize) as usize;
-                        if base_column_range.contains(&col_idx) {
-                            let column = &base_trace_lde_cols[col_idx];
-                            EvalItem::Evals(Box::new(FieldVariant::Fp(extract_lde_chunk(
-                                column, position,
-                            ))))
-                        } else if extension_column_range.contains(&col_idx) {
-                            let extension_column_idx = col_idx - num_base_columns;
-                            let column = &extension_trace_lde_cols.unwrap()[extension_column_idx];
-                            EvalItem::Evals(Box::new(FieldVariant::Fq(extract_lde_chunk(
-                                column, position,
-                            ))))
-                        } else {
-                            panic!("invalid column {col_idx}")
-                        }
-                    }
-                    Periodic(col) => {
-                        let lde = periodic_column_evals_map.get(&col).unwrap();
-                        match lde {
-                            FieldVariant::Fp(lde) => EvalItem::Evals(Box::new(FieldVariant::Fp(
-                                extract_lde_chunk(lde, chunk_offset),
-                            ))),
-                            FieldVariant::Fq(lde) => EvalItem::Evals(Box::new(FieldVariant::Fq(
-                                extract_lde_chunk(lde, chunk_offset),
-                            ))),
-                        }
-                    }
-                })
-                .into_fq_array();
-            chunk.copy_from_slice(&chunk_res);
-        });
+    let program = Program::compile(expr, num_base_columns, challenges, hints);
+    let periodic_column_ldes = build_periodic_column_ldes(
+        expr,
+        program.periodic_columns(),
+        domain_offset,
+        trace_len,
+        lde_step,
+        CHUNK_SIZE,
+    );
+    let inputs = Inputs {
+        lde_step,
+        x_lde,
+        base_trace_lde_cols,
+        extension_trace_lde_cols,
+        periodic_column_ldes: &periodic_column_ldes,
+    };
+    program.eval::<CHUNK_SIZE>(&inputs, result);
+}
+
+/// Evaluates an expression with code generated by [`crate::codegen`]
+#[allow(clippy::too_many_arguments)]
+pub fn eval_generated<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>(
+    evaluator: &GeneratedEvaluator<Fp, Fq>,
+    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    constants: &[FieldVariant<Fp, Fq>],
+    periodic_columns: &[PeriodicColumn<FieldVariant<Fp, Fq>>],
+    challenges: &Challenges<Fq>,
+    hints: &[Fq],
+    composition_coeffs: &[Fq],
+    lde_step: usize,
+    domain_offset: Fp,
+    x_lde: &[Fp],
+    base_trace_lde_cols: &[&[Fp]],
+    extension_trace_lde_cols: Option<&[&[Fq]]>,
+) -> Matrix<Fq> {
+    let n = x_lde.len();
+    let periodic_column_ldes = build_periodic_column_ldes(
+        expr,
+        periodic_columns,
+        domain_offset,
+        n / lde_step,
+        lde_step,
+        crate::codegen::CHUNK_SIZE,
+    );
+    let inputs = LdeInputs {
+        challenges,
+        hints,
+        composition_coeffs,
+        constants,
+        lde: Inputs {
+            lde_step,
+            x_lde,
+            base_trace_lde_cols,
+            extension_trace_lde_cols,
+            periodic_column_ldes: &periodic_column_ldes,
+        },
+    };
+    let mut result = Vec::with_capacity_in(n, GpuAllocator);
+    result.resize(n, Fq::zero());
+    (evaluator.eval_lde)(&inputs, &mut result);
+    Matrix::new(vec![result])
+}
+
+/// Evaluates periodic columns over the LDE domain in the order of `columns`
+fn build_periodic_column_ldes<
+    Fp: GpuFftField<FftField = Fp> + FftField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    columns: &[PeriodicColumn<FieldVariant<Fp, Fq>>],
+    domain_offset: Fp,
+    trace_len: usize,
+    lde_step: usize,
+    min_len: usize,
+) -> Vec<FieldVariant<Vec<Fp>, Vec<Fq>>> {
+    let mut periodic_column_evals_map =
+        build_periodic_column_evals_map(expr, domain_offset, trace_len, lde_step, min_len);
+    columns
+        .iter()
+        .map(|col| periodic_column_evals_map.remove(col).unwrap())
+        .collect()
 }
 
 /// Extracts a chunk of evaluations from a low-degree-extension
@@ -184,10 +201,10 @@ pub fn build_periodic_column_evals_map<
     trace_len: usize,
     blowup_factor: usize,
     min_domain_size: usize,
-) -> BTreeMap<PeriodicColumn<'static, FieldVariant<Fp, Fq>>, FieldVariant<Vec<Fp>, Vec<Fq>>> {
+) -> BTreeMap<PeriodicColumn<FieldVariant<Fp, Fq>>, FieldVariant<Vec<Fp>, Vec<Fq>>> {
     let mut res = BTreeMap::new();
     expr.traverse(&mut |node| {
-        if let &Expr::Leaf(AlgebraicItem::Periodic(col)) = node {
+        if let Expr::Leaf(AlgebraicItem::Periodic(col)) = node {
             let interval_size = col.interval_size();
             let coeffs = col.coeffs();
             let is_fp = |&v| match v {
@@ -203,29 +220,29 @@ pub fn build_periodic_column_evals_map<
                         FieldVariant::Fq(_) => unreachable!(),
                     })
                     .collect();
-                let col = PeriodicColumn::new(&coeffs, interval_size);
+                let col = PeriodicColumn::new(coeffs, interval_size);
                 let lde = eval_periodic_column(
                     domain_offset,
                     trace_len,
                     blowup_factor,
-                    col,
+                    &col,
                     min_domain_size,
                 );
                 FieldVariant::Fp(lde)
             } else {
                 let coeffs: Vec<Fq> = coeffs.iter().map(FieldVariant::as_fq).collect();
-                let col = PeriodicColumn::new(&coeffs, interval_size);
+                let col = PeriodicColumn::new(coeffs, interval_size);
                 let lde = eval_periodic_column(
                     domain_offset,
                     trace_len,
                     blowup_factor,
-                    col,
+                    &col,
                     min_domain_size,
                 );
                 FieldVariant::Fq(lde)
             };
 
-            res.insert(col, lde);
+            res.insert(col.clone(), lde);
         }
     });
     res
@@ -236,7 +253,7 @@ pub fn eval_periodic_column<F: GpuField + Field + DomainCoeff<F::FftField>>(
     domain_offset: F::FftField,
     trace_len: usize,
     blowup_factor: usize,
-    periodic_column: PeriodicColumn<'_, F>,
+    periodic_column: &PeriodicColumn<F>,
     min_len: usize,
 ) -> Vec<F>
 where
@@ -254,240 +271,3 @@ where
     }
     evals
 }
-
-#[derive(Clone)]
-#[allow(clippy::type_complexity)]
-enum EvalItem<'a, Fp: Field, Fq: Field, const N: usize> {
-    Constant(FieldVariant<Fp, Fq>),
-    Evals(Box<FieldVariant<Cow<'a, [Fp; N]>, Cow<'a, [Fq; N]>>>),
-}
-
-impl<'a, Fp: Field, Fq: Field + From<Fp>, const N: usize> EvalItem<'a, Fp, Fq, N> {
-    pub fn into_fq_array(self) -> [Fq; N] {
-        match self {
-            // TODO: think if constant case should be considered. maybe panic?
-            Self::Constant(v) => match v {
-                FieldVariant::Fp(v) => [v.into(); N],
-                FieldVariant::Fq(v) => [v; N],
-            },
-            Self::Evals(evals) => match *evals {
-                FieldVariant::Fp(evals) => evals.into_owned().map(|v| Fq::from(v)),
-                FieldVariant::Fq(evals) => evals.into_owned(),
-            },
-        }
-    }
-
-    fn inverse(self) -> Self {
-        match self {
-            Self::Constant(v) => Self::Constant(v.inverse().unwrap()),
-            Self::Evals(evals) => match *evals {
-                FieldVariant::Fp(mut evals) => {
-                    batch_inversion(evals.to_mut());
-                    Self::Evals(Box::new(FieldVariant::Fp(evals)))
-                }
-                FieldVariant::Fq(mut evals) => {
-                    batch_inversion(evals.to_mut());
-                    Self::Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-            },
-        }
-    }
-}
-
-impl<
-        'a,
-        Fp: Field,
-        Fq: Field + From<Fp> + Add<Fp, Output = Fq> + AddAssign<Fp>,
-        const N: usize,
-    > Add for EvalItem<'a, Fp, Fq, N>
-{
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        use EvalItem::*;
-        match (self, rhs) {
-            (Constant(lhs), Constant(rhs)) => Constant(lhs + rhs),
-            (Evals(evals), Constant(c)) | (Constant(c), Evals(evals)) => match (*evals, c) {
-                (FieldVariant::Fp(mut evals), FieldVariant::Fp(c)) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v += c);
-                    Evals(Box::new(FieldVariant::Fp(evals)))
-                }
-                (FieldVariant::Fp(evals), FieldVariant::Fq(c)) => {
-                    let evals = Cow::Owned(evals.into_owned().map(|v| Fq::from(v) + c));
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-                (FieldVariant::Fq(mut evals), FieldVariant::Fp(c)) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v += c);
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-                (FieldVariant::Fq(mut evals), FieldVariant::Fq(c)) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v += c);
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-            },
-            (Evals(lhs), Evals(rhs)) => match (*lhs, *rhs) {
-                (FieldVariant::Fq(mut lhs), FieldVariant::Fp(rhs))
-                | (FieldVariant::Fp(rhs), FieldVariant::Fq(mut lhs)) => {
-                    zip(lhs.to_mut(), &*rhs).for_each(|(lhs, rhs)| *lhs += *rhs);
-                    Evals(Box::new(FieldVariant::Fq(lhs)))
-                }
-                (FieldVariant::Fq(lhs), FieldVariant::Fq(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
-                        (lhs, rhs)
-                    } else {
-                        (rhs, lhs)
-                    };
-                    zip(lhs.to_mut(), &*rhs).for_each(|(lhs, rhs)| *lhs += *rhs);
-                    Evals(Box::new(FieldVariant::Fq(lhs)))
-                }
-                (FieldVariant::Fp(lhs), FieldVariant::Fp(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
-                        (lhs, rhs)
-                    } else {
-                        (rhs, lhs)
-                    };
-                    zip(lhs.to_mut(), &*rhs).for_each(|(lhs, rhs)| *lhs += *rhs);
-                    Evals(Box::new(FieldVariant::Fp(lhs)))
-                }
-            },
-        }
-    }
-}
-
-impl<
-        'a,
-        Fp: Field,
-        Fq: Field + From<Fp> + Mul<Fp, Output = Fq> + MulAssign<Fp>,
-        const N: usize,
-    > Mul for EvalItem<'a, Fp, Fq, N>
-{
-    type Output = Self;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        use EvalItem::*;
-        // Don't mult if one side is `1`
-        // let (lhs, rhs) = match (self, rhs) {
-        //     (Constant(c), other) | (other, Constant(c)) => {
-        //         if c.is_one() {
-        //             return other;
-        //         }
-        //         (Constant(c), other)
-        //     }
-        //     (lhs, rhs) => (lhs, rhs),
-        // };
-
-        match (self, rhs) {
-            (Constant(lhs), Constant(rhs)) => Constant(lhs * rhs),
-            (Evals(evals), Constant(c)) | (Constant(c), Evals(evals)) => match (*evals, c) {
-                (FieldVariant::Fp(mut evals), FieldVariant::Fp(c)) => {
-                    for v in evals.to_mut() {
-                        *v *= c;
-                    }
-                    Evals(Box::new(FieldVariant::Fp(evals)))
-                }
-                (FieldVariant::Fp(evals), FieldVariant::Fq(c)) => {
-                    let evals = Cow::Owned(evals.into_owned().map(|v| Fq::from(v) * c));
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-                (FieldVariant::Fq(mut evals), FieldVariant::Fp(c)) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v *= c);
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-                (FieldVariant::Fq(mut evals), FieldVariant::Fq(c)) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v *= c);
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-            },
-            (Evals(lhs), Evals(rhs)) => match (*lhs, *rhs) {
-                (FieldVariant::Fq(mut lhs), FieldVariant::Fp(rhs))
-                | (FieldVariant::Fp(rhs), FieldVariant::Fq(mut lhs)) => {
-                    zip(lhs.to_mut(), &*rhs).for_each(|(lhs, rhs)| *lhs *= *rhs);
-                    Evals(Box::new(FieldVariant::Fq(lhs)))
-                }
-                (FieldVariant::Fq(lhs), FieldVariant::Fq(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
-                        (lhs, rhs)
-                    } else {
-                        (rhs, lhs)
-                    };
-                    zip(lhs.to_mut(), &*rhs).for_each(|(lhs, rhs)| *lhs *= *rhs);
-                    Evals(Box::new(FieldVariant::Fq(lhs)))
-                }
-                (FieldVariant::Fp(lhs), FieldVariant::Fp(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
-                        (lhs, rhs)
-                    } else {
-                        (rhs, lhs)
-                    };
-                    zip(lhs.to_mut(), &*rhs).for_each(|(lhs, rhs)| *lhs *= *rhs);
-                    Evals(Box::new(FieldVariant::Fp(lhs)))
-                }
-            },
-        }
-    }
-}
-
-impl<
-        'a,
-        Fp: Field,
-        Fq: Field + From<Fp> + Mul<Fp, Output = Fq> + MulAssign<Fp>,
-        const N: usize,
-    > Div for EvalItem<'a, Fp, Fq, N>
-{
-    type Output = Self;
-
-    #[allow(clippy::suspicious_arithmetic_impl)]
-    fn div(self, rhs: Self) -> Self::Output {
-        self * rhs.inverse()
-    }
-}
-
-// TODO: add type safety to all the GPU shaders
-// could create TypedBuffer which is metal::Buffer wrapper with phantom data
-impl<'a, Fp: Field, Fq: Field, const N: usize> Pow<usize> for EvalItem<'a, Fp, Fq, N> {
-    type Output = Self;
-
-    fn pow(self, exp: usize) -> Self::Output {
-        use EvalItem::*;
-        match self {
-            Constant(v) => Constant(v.pow(exp)),
-            Evals(evals) => match *evals {
-                FieldVariant::Fp(mut evals) => {
-                    evals
-                        .to_mut()
-                        .iter_mut()
-                        .for_each(|v| *v = v.pow([exp as u64]));
-                    Evals(Box::new(FieldVariant::Fp(evals)))
-                }
-                FieldVariant::Fq(mut evals) => {
-                    evals
-                        .to_mut()
-                        .iter_mut()
-                        .for_each(|v| *v = v.pow([exp as u64]));
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-            },
-        }
-    }
-}
-
-impl<'a, Fp: Field, Fq: Field, const N: usize> Neg for EvalItem<'a, Fp, Fq, N> {
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        use EvalItem::*;
-        match self {
-            Constant(v) => Constant(-v),
-            Evals(evals) => match *evals {
-                FieldVariant::Fp(mut evals) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v = -*v);
-                    Evals(Box::new(FieldVariant::Fp(evals)))
-                }
-                FieldVariant::Fq(mut evals) => {
-                    evals.to_mut().iter_mut().for_each(|v| *v = -*v);
-                    Evals(Box::new(FieldVariant::Fq(evals)))
-                }
-            },
-        }
-    }
-}
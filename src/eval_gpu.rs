@@ -1,5 +1,6 @@
 #![cfg(feature = "gpu")]
 
+use crate::challenges::Challenges;
 use crate::constraints::AlgebraicItem;
 use crate::expression::Expr;
 use crate::utils::FieldType;
@@ -45,7 +46,7 @@ use num_traits::Pow;
 
 pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>(
     expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
-    challenges: &[Fq],
+    challenges: &Challenges<Fq>,
     hints: &[Fq],
     lde_step: usize,
     x_lde: GpuVec<Fp>,
@@ -82,11 +83,11 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
         &Constant(v) => {
             EvaluationItem::new_constant(&lde_calculator, &lde_cache, command_buffer, v)
         }
-        &Challenge(i) => EvaluationItem::new_constant(
+        &Challenge(round, i) => EvaluationItem::new_constant(
             &lde_calculator,
             &lde_cache,
             command_buffer,
-            FieldVariant::Fq(challenges[i]),
+            FieldVariant::Fq(challenges[(round, i)]),
         ),
         &Hint(i) => EvaluationItem::new_constant(
             &lde_calculator,
@@ -109,7 +110,7 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
             };
             EvaluationItem::new_lde(&lde_calculator, &lde_cache, command_buffer, lde, j * step)
         }
-        &Periodic(_col) => {
+        Periodic(_) => {
             todo!()
         }
         X => {
@@ -978,7 +979,7 @@ mod tests {
                 .eval(&mut |leaf| match leaf {
                     AlgebraicItem::X => FieldVariant::Fp(lde_domain.element(i)),
                     &AlgebraicItem::Constant(c) => c,
-                    AlgebraicItem::Challenge(_) | AlgebraicItem::Hint(_) => unreachable!(),
+                    AlgebraicItem::Challenge(..) | AlgebraicItem::Hint(_) => unreachable!(),
                     &AlgebraicItem::Trace(c, o) => match c {
                         0 => FieldVariant::Fp(base_lde.0[0][i + o.unsigned_abs() * lde_step]),
                         1 => FieldVariant::Fq(extension_lde.0[0][i + o.unsigned_abs() * lde_step]),
@@ -1,7 +1,8 @@
 // Implementation is adapted from RationalExpression in https://github.com/0xProject/OpenZKP
-#![allow(clippy::arc_with_non_send_sync)]
 
 use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
 use ark_ff::One;
 use ark_std::Zero;
 use core::cmp::Ordering;
@@ -18,18 +19,19 @@ use core::ops::Neg;
 use core::ops::Sub;
 use core::ops::SubAssign;
 use num_traits::Pow;
-use std::cell::RefCell;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
-use std::ptr::addr_of;
-use std::rc::Rc;
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::RwLock;
 
-pub type P<T> = Arc<RwLock<T>>;
+pub type P<T> = Arc<T>;
 
 /// Expression
-#[derive(Clone)]
+///
+/// Expressions are trees of reference counted nodes so cloning is cheap and
+/// nodes shared with `clone` stay shared. Structurally equal nodes that were
+/// built separately are only merged when the expression is lowered into an
+/// [`ExprArena`] e.g. by [`Expr::reuse_shared_nodes`], the bytecode compiler
+/// or code generation.
+#[derive(Clone, Debug)]
 pub enum Expr<T> {
     Leaf(T),
     Neg(P<Expr<T>>),
@@ -39,9 +41,6 @@ pub enum Expr<T> {
     Pow(P<Expr<T>>, usize),
 }
 
-// unsafe impl<T: Send> Send for Expr<T> {}
-// unsafe impl<T: Sync> Sync for Expr<T> {}
-
 impl<T> Expr<T> {
     // Adapted from https://github.com/0xProject/OpenZKP
     /// Applies a bottom-up traversal.
@@ -49,10 +48,10 @@ impl<T> Expr<T> {
         use Expr::*;
         match self {
             // Tree types are recursed first
-            Neg(a) | Pow(a, _) => a.as_ref().read().unwrap().traverse(f),
+            Neg(a) | Pow(a, _) => a.traverse(f),
             Add(a, b) | Mul(a, b) | Div(a, b) => {
-                a.as_ref().read().unwrap().traverse(f);
-                b.as_ref().read().unwrap().traverse(f);
+                a.traverse(f);
+                b.traverse(f);
             }
             Leaf(_) => {}
         }
@@ -68,24 +67,13 @@ impl<T> Expr<T> {
         T: Clone,
     {
         use Expr::*;
-        // TODO: consider function argument for leaf nodes
-        // TODO: why can't the copiler do this as a param
         let res = match self {
             // Tree types are recursed first
-            Add(a, b) => Add(
-                Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))),
-                Arc::new(RwLock::new(b.as_ref().read().unwrap().map(f))),
-            ),
-            Neg(a) => Neg(Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f)))),
-            Mul(a, b) => Mul(
-                Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))),
-                Arc::new(RwLock::new(b.as_ref().read().unwrap().map(f))),
-            ),
-            Div(a, b) => Div(
-                Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))),
-                Arc::new(RwLock::new(b.as_ref().read().unwrap().map(f))),
-            ),
-            Pow(a, e) => Pow(Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))), *e),
+            Add(a, b) => Add(Arc::new(a.map(f)), Arc::new(b.map(f))),
+            Neg(a) => Neg(Arc::new(a.map(f))),
+            Mul(a, b) => Mul(Arc::new(a.map(f)), Arc::new(b.map(f))),
+            Div(a, b) => Div(Arc::new(a.map(f)), Arc::new(b.map(f))),
+            Pow(a, e) => Pow(Arc::new(a.map(f)), *e),
 
             // Leaf types are mapped as is.
             Leaf(v) => Leaf(v.clone()),
@@ -95,265 +83,109 @@ impl<T> Expr<T> {
     }
 
     /// Applies a bottom-up traversal.
-    /// The closure is given mutable access to the nodes.
-    pub fn traverse_mut(&mut self, f: &mut impl FnMut(&mut Self)) {
+    /// The closure is given mutable access to the nodes. Nodes shared within
+    /// the expression are visited once and remain shared. Nodes shared with
+    /// other expressions are copied before they are modified.
+    pub fn traverse_mut(&mut self, f: &mut impl FnMut(&mut Self))
+    where
+        T: Clone,
+    {
+        self.traverse_mut_node(&mut BTreeMap::new(), f);
+    }
+
+    fn traverse_mut_child(
+        this: &mut P<Self>,
+        seen: &mut BTreeMap<*const Self, P<Self>>,
+        f: &mut impl FnMut(&mut Self),
+    ) where
+        T: Clone,
+    {
+        let id = Arc::as_ptr(this);
+        if let Some(node) = seen.get(&id) {
+            *this = Arc::clone(node);
+            return;
+        }
+        Arc::make_mut(this).traverse_mut_node(seen, f);
+        seen.insert(id, Arc::clone(this));
+    }
+
+    fn traverse_mut_node(
+        &mut self,
+        seen: &mut BTreeMap<*const Self, P<Self>>,
+        f: &mut impl FnMut(&mut Self),
+    ) where
+        T: Clone,
+    {
         use Expr::*;
         match self {
             // Tree types are recursed first
             Add(a, b) | Mul(a, b) | Div(a, b) => {
-                a.write().unwrap().traverse_mut(f);
-                b.write().unwrap().traverse_mut(f);
+                Self::traverse_mut_child(a, seen, f);
+                Self::traverse_mut_child(b, seen, f);
             }
-            Neg(a) | Pow(a, _) => a.write().unwrap().traverse_mut(f),
+            Neg(a) | Pow(a, _) => Self::traverse_mut_child(a, seen, f),
             Leaf(_) => {}
         }
 
         f(self);
     }
 
-    // TODO: change this to self
-    fn _map_leaves<U>(
+    fn map_leaves_child<U>(
         this: &P<Self>,
-        sl: &mut BTreeMap<*const T, P<Expr<U>>>,
-        sn: &mut BTreeMap<*const Self, P<Expr<U>>>,
+        seen: &mut BTreeMap<*const Self, P<Expr<U>>>,
         f: &mut impl FnMut(&T) -> U,
-    ) -> P<Expr<U>>
-    where
-        T: Ord,
-    {
-        // The lint's suggestion "Using Option::map_or_else" doesn't work because
-        // `.insert` requires mutable access to seen (which is already borrowed).
-        #[allow(clippy::option_if_let_else)]
-        if let Some(node) = sn.get(&addr_of!(*this.read().unwrap())) {
-            Arc::clone(node)
-        } else {
-            use Expr::*;
-            let res = Arc::new(RwLock::new(match &*this.read().unwrap() {
-                Leaf(l) => {
-                    if let Some(leaf) = sl.get(&addr_of!(*l)) {
-                        return Arc::clone(leaf);
-                    }
-
-                    let res = Arc::new(RwLock::new(Leaf(f(l))));
-                    sl.insert(addr_of!(*l), Arc::clone(&res));
-                    sn.insert(addr_of!(*this.read().unwrap()), Arc::clone(&res));
-                    return res;
-                }
-                Add(a, b) => Add(
-                    Self::_map_leaves(a, sl, sn, f),
-                    Self::_map_leaves(b, sl, sn, f),
-                ),
-                Neg(a) => Neg(Self::_map_leaves(a, sl, sn, f)),
-                Pow(a, e) => Pow(Self::_map_leaves(a, sl, sn, f), *e),
-                Mul(a, b) => Mul(
-                    Self::_map_leaves(a, sl, sn, f),
-                    Self::_map_leaves(b, sl, sn, f),
-                ),
-                Div(a, b) => Div(
-                    Self::_map_leaves(a, sl, sn, f),
-                    Self::_map_leaves(b, sl, sn, f),
-                ),
-            }));
-            sn.insert(addr_of!(*this.read().unwrap()), Arc::clone(&res));
-            res
+    ) -> P<Expr<U>> {
+        let id = Arc::as_ptr(this);
+        if let Some(node) = seen.get(&id) {
+            return Arc::clone(node);
         }
+        let node = Arc::new(Self::map_leaves_node(this, seen, f));
+        seen.insert(id, Arc::clone(&node));
+        node
     }
 
-    // Maps leaves and retains internal structure
-    #[allow(clippy::many_single_char_names)]
-    pub fn map_leaves<U>(&self, f: &mut impl FnMut(&T) -> U) -> Expr<U>
-    where
-        T: Ord,
-    {
+    fn map_leaves_node<U>(
+        &self,
+        seen: &mut BTreeMap<*const Self, P<Expr<U>>>,
+        f: &mut impl FnMut(&T) -> U,
+    ) -> Expr<U> {
         use Expr::*;
-        let mut leafs_map = BTreeMap::new();
-        let mut nodes_map = BTreeMap::new();
-        let l = &mut leafs_map;
-        let m = &mut nodes_map;
         match self {
-            Leaf(v) => Leaf(f(v)),
-            Add(a, b) => Add(Self::_map_leaves(a, l, m, f), Self::_map_leaves(b, l, m, f)),
-            Neg(a) => Neg(Self::_map_leaves(a, l, m, f)),
-            Mul(a, b) => Mul(Self::_map_leaves(a, l, m, f), Self::_map_leaves(b, l, m, f)),
-            Div(a, b) => Div(Self::_map_leaves(a, l, m, f), Self::_map_leaves(b, l, m, f)),
-            Pow(a, e) => Pow(Self::_map_leaves(a, l, m, f), *e),
+            Leaf(l) => Leaf(f(l)),
+            Add(a, b) => Add(
+                Self::map_leaves_child(a, seen, f),
+                Self::map_leaves_child(b, seen, f),
+            ),
+            Neg(a) => Neg(Self::map_leaves_child(a, seen, f)),
+            Pow(a, e) => Pow(Self::map_leaves_child(a, seen, f), *e),
+            Mul(a, b) => Mul(
+                Self::map_leaves_child(a, seen, f),
+                Self::map_leaves_child(b, seen, f),
+            ),
+            Div(a, b) => Div(
+                Self::map_leaves_child(a, seen, f),
+                Self::map_leaves_child(b, seen, f),
+            ),
         }
     }
 
+    /// Maps leaves and retains internal structure. Nodes that are shared
+    /// remain shared in the output.
+    pub fn map_leaves<U>(&self, f: &mut impl FnMut(&T) -> U) -> Expr<U> {
+        self.map_leaves_node(&mut BTreeMap::new(), f)
+    }
+
+    /// Returns an equivalent expression where structurally equal
+    /// sub-expressions are represented by a single shared node.
     /// Inspired by <https://neptune.cash/learn/speed-up-stark-provers-with-multicircuits/>
-    /// Runtime: O(n log n) where n is the number of edges TODO: check
-    #[allow(clippy::too_many_lines)]
+    /// Runtime: O(n) where n is the number of nodes
     pub fn reuse_shared_nodes(&self) -> Self
     where
-        T: Ord + Clone + Hash,
+        T: Clone + Eq + Hash,
     {
-        type Id = u64;
-        type SeenSet<T> = Rc<RefCell<BTreeMap<Id, P<Expr<T>>>>>;
-
-        struct IdNode<T> {
-            node: P<Expr<T>>,
-            seen: SeenSet<T>,
-            id: Id,
-        }
-
-        impl<T: Hash + Clone> IdNode<T> {
-            fn new_leaf(leaf: &T, seen: SeenSet<T>) -> Self {
-                // `id` is the hash of the leaf
-                let mut hasher = DefaultHasher::new();
-                ("leaf", leaf).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this leaf, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Leaf(leaf.clone())));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
-            }
-        }
-
-        impl<T> Add for IdNode<T> {
-            type Output = Self;
-
-            fn add(self, rhs: Self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("add", self.id, rhs.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this node
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Add(self.node, rhs.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
-            }
-        }
-
-        impl<T> Mul for IdNode<T> {
-            type Output = Self;
-
-            fn mul(self, rhs: Self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("mul", self.id, rhs.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Mul(self.node, rhs.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
-            }
-        }
-
-        impl<T> Div for IdNode<T> {
-            type Output = Self;
-
-            fn div(self, rhs: Self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("div", self.id, rhs.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Div(self.node, rhs.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
-            }
-        }
-
-        impl<T> Neg for IdNode<T> {
-            type Output = Self;
-
-            fn neg(self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("neg", self.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Neg(self.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
-            }
-        }
-
-        impl<T> Pow<usize> for IdNode<T> {
-            type Output = Self;
-
-            fn pow(self, exp: usize) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("pow", self.id, exp).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Pow(self.node, exp)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
-            }
-        }
-
-        let seen = Rc::new(RefCell::new(BTreeMap::new()));
-        let res = self.eval(&mut |leaf| IdNode::new_leaf(leaf, Rc::clone(&seen)));
-        // Drop references
-        drop((seen, res.seen));
-        Arc::into_inner(res.node).unwrap().into_inner().unwrap()
+        let mut arena = ExprArena::new();
+        let root = arena.insert(self);
+        arena.to_expr(root)
     }
 
     // Adapted from https://github.com/0xProject/OpenZKP
@@ -369,20 +201,22 @@ impl<T> Expr<T> {
             + Pow<usize, Output = U>,
     {
         use Expr::*;
-        // #[cfg(feature = "std")]
-        // println!("brr");
         match self {
             Leaf(a) => f(a),
-            Add(a, b) => a.read().unwrap().eval(f) + b.read().unwrap().eval(f),
-            Neg(a) => -a.read().unwrap().eval(f),
-            Mul(a, b) => a.read().unwrap().eval(f) * b.read().unwrap().eval(f),
-            Div(a, b) => a.read().unwrap().eval(f) / b.read().unwrap().eval(f),
-            Pow(a, e) => a.read().unwrap().eval(f).pow(*e),
+            Add(a, b) => a.eval(f) + b.eval(f),
+            Neg(a) => -a.eval(f),
+            Mul(a, b) => a.eval(f) * b.eval(f),
+            Div(a, b) => a.eval(f) / b.eval(f),
+            Pow(a, e) => a.eval(f).pow(*e),
         }
     }
 
-    fn _graph_eval<U>(this: &P<Expr<Option<U>>>) -> U
+    /// Evaluates an expression graph bottom up
+    /// Structurally equal sub-expressions are only evaluated once. Use
+    /// [`ExprArena::eval`] to evaluate the same expression many times.
+    pub fn graph_eval<U>(&self, f: &mut impl FnMut(&T) -> U) -> U
     where
+        T: Clone + Eq + Hash,
         U: Clone
             + Add<Output = U>
             + Neg<Output = U>
@@ -390,25 +224,174 @@ impl<T> Expr<T> {
             + Mul<Output = U>
             + Pow<usize, Output = U>,
     {
-        use Expr::*;
-        let mut node = this.write().unwrap();
-        let res = match core::mem::take(&mut *node) {
-            Leaf(v) => v.unwrap(),
-            Neg(a) => -Self::_graph_eval(&a),
-            Add(a, b) => Self::_graph_eval(&a) + Self::_graph_eval(&b),
-            Mul(a, b) => Self::_graph_eval(&a) * Self::_graph_eval(&b),
-            Div(a, b) => Self::_graph_eval(&a) / Self::_graph_eval(&b),
-            Pow(a, e) => Self::_graph_eval(&a).pow(e),
+        let mut arena = ExprArena::new();
+        let root = arena.insert(self);
+        arena.eval(root, f)
+    }
+}
+
+/// Index of a node in an [`ExprArena`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub const fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Node of an [`ExprArena`]. Children are referenced by their id.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Node<T> {
+    Leaf(T),
+    Neg(NodeId),
+    Add(NodeId, NodeId),
+    Mul(NodeId, NodeId),
+    Div(NodeId, NodeId),
+    Pow(NodeId, usize),
+}
+
+impl<T> Node<T> {
+    pub fn children(&self) -> impl Iterator<Item = NodeId> {
+        use Node::*;
+        let (a, b) = match *self {
+            Leaf(_) => (None, None),
+            Neg(a) | Pow(a, _) => (Some(a), None),
+            Add(a, b) | Mul(a, b) | Div(a, b) => (Some(a), Some(b)),
         };
-        *node = Leaf(Some(res.clone()));
-        res
+        a.into_iter().chain(b)
     }
+}
 
-    /// Evaluates an expression graph bottom up
-    /// Intermediate results are cached to prevent re-evaluation
-    pub fn graph_eval<U>(&self, f: &mut impl FnMut(&T) -> U) -> U
+/// Hash-consed expression DAG. Structurally equal sub-expressions are stored
+/// once and nodes are stored in topological order i.e. children are always
+/// stored before their parents.
+#[derive(Clone, Debug)]
+pub struct ExprArena<T> {
+    nodes: Vec<Node<T>>,
+    ids: HashMap<Node<T>, NodeId>,
+}
+
+impl<T> ExprArena<T> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node<T> {
+        &self.nodes[id.0]
+    }
+
+    /// Returns all nodes in topological order
+    pub fn nodes(&self) -> &[Node<T>] {
+        &self.nodes
+    }
+
+    pub const fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<T: Clone + Eq + Hash> ExprArena<T> {
+    /// Adds a node to the arena unless an equal node already exists. Returns
+    /// the id of the node.
+    pub fn intern(&mut self, node: Node<T>) -> NodeId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+        id
+    }
+
+    /// Adds all nodes of an expression to the arena. Returns the id of the root
+    pub fn insert(&mut self, expr: &Expr<T>) -> NodeId {
+        self.insert_node(expr, &mut BTreeMap::new())
+    }
+
+    fn insert_child(
+        &mut self,
+        child: &P<Expr<T>>,
+        seen: &mut BTreeMap<*const Expr<T>, NodeId>,
+    ) -> NodeId {
+        // nodes shared by the expression are only visited once
+        let ptr = Arc::as_ptr(child);
+        if let Some(&id) = seen.get(&ptr) {
+            return id;
+        }
+        let id = self.insert_node(child, seen);
+        seen.insert(ptr, id);
+        id
+    }
+
+    fn insert_node(
+        &mut self,
+        expr: &Expr<T>,
+        seen: &mut BTreeMap<*const Expr<T>, NodeId>,
+    ) -> NodeId {
+        let node = match expr {
+            Expr::Leaf(v) => Node::Leaf(v.clone()),
+            Expr::Neg(a) => Node::Neg(self.insert_child(a, seen)),
+            Expr::Add(a, b) => Node::Add(self.insert_child(a, seen), self.insert_child(b, seen)),
+            Expr::Mul(a, b) => Node::Mul(self.insert_child(a, seen), self.insert_child(b, seen)),
+            Expr::Div(a, b) => Node::Div(self.insert_child(a, seen), self.insert_child(b, seen)),
+            &Expr::Pow(ref a, e) => Node::Pow(self.insert_child(a, seen), e),
+        };
+        self.intern(node)
+    }
+
+    /// Converts a node back into an expression. Nodes with multiple parents
+    /// become shared nodes of the expression.
+    pub fn to_expr(&self, root: NodeId) -> Expr<T> {
+        let mut exprs: Vec<Option<P<Expr<T>>>> = vec![None; root.0 + 1];
+        for id in self.reachable(root) {
+            let child = |child: NodeId| Arc::clone(exprs[child.0].as_ref().unwrap());
+            let expr = match self.nodes[id.0] {
+                Node::Leaf(ref v) => Expr::Leaf(v.clone()),
+                Node::Neg(a) => Expr::Neg(child(a)),
+                Node::Add(a, b) => Expr::Add(child(a), child(b)),
+                Node::Mul(a, b) => Expr::Mul(child(a), child(b)),
+                Node::Div(a, b) => Expr::Div(child(a), child(b)),
+                Node::Pow(a, e) => Expr::Pow(child(a), e),
+            };
+            exprs[id.0] = Some(Arc::new(expr));
+        }
+        let root = exprs[root.0].take().unwrap();
+        drop(exprs);
+        Arc::try_unwrap(root).unwrap_or_else(|root| (*root).clone())
+    }
+}
+
+impl<T> ExprArena<T> {
+    /// Returns the ids of all nodes the root depends on in topological order
+    fn reachable(&self, root: NodeId) -> Vec<NodeId> {
+        let mut is_reachable = vec![false; root.0 + 1];
+        is_reachable[root.0] = true;
+        for i in (0..=root.0).rev() {
+            if is_reachable[i] {
+                for child in self.nodes[i].children() {
+                    is_reachable[child.0] = true;
+                }
+            }
+        }
+        (0..=root.0)
+            .filter(|&i| is_reachable[i])
+            .map(NodeId)
+            .collect()
+    }
+
+    /// Evaluates the expression rooted at `root` bottom up. Each node is
+    /// evaluated once and intermediate results are dropped after their last
+    /// use.
+    pub fn eval<U>(&self, root: NodeId, f: &mut impl FnMut(&T) -> U) -> U
     where
-        T: Ord + Copy,
         U: Clone
             + Add<Output = U>
             + Neg<Output = U>
@@ -416,16 +399,42 @@ impl<T> Expr<T> {
             + Mul<Output = U>
             + Pow<usize, Output = U>,
     {
-        use Expr::*;
-        let res = self.map_leaves(&mut |l| Some(f(l)));
-        match res {
-            Leaf(a) => a.unwrap(),
-            Add(a, b) => Self::_graph_eval(&a) + Self::_graph_eval(&b),
-            Neg(a) => -Self::_graph_eval(&a),
-            Mul(a, b) => Self::_graph_eval(&a) * Self::_graph_eval(&b),
-            Div(a, b) => Self::_graph_eval(&a) / Self::_graph_eval(&b),
-            Pow(a, e) => Self::_graph_eval(&a).pow(e),
+        let reachable = self.reachable(root);
+        let mut num_uses = vec![0usize; root.0 + 1];
+        num_uses[root.0] = 1;
+        for &id in &reachable {
+            for child in self.nodes[id.0].children() {
+                num_uses[child.0] += 1;
+            }
+        }
+
+        let mut values: Vec<Option<U>> = vec![None; root.0 + 1];
+        for id in reachable {
+            let mut take = |child: NodeId| {
+                num_uses[child.0] -= 1;
+                if num_uses[child.0] == 0 {
+                    values[child.0].take().unwrap()
+                } else {
+                    values[child.0].clone().unwrap()
+                }
+            };
+            let value = match self.nodes[id.0] {
+                Node::Leaf(ref v) => f(v),
+                Node::Neg(a) => -take(a),
+                Node::Add(a, b) => take(a) + take(b),
+                Node::Mul(a, b) => take(a) * take(b),
+                Node::Div(a, b) => take(a) / take(b),
+                Node::Pow(a, e) => take(a).pow(e),
+            };
+            values[id.0] = Some(value);
         }
+        values[root.0].take().unwrap()
+    }
+}
+
+impl<T> Default for ExprArena<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -440,15 +449,13 @@ impl<T: Eq> Eq for Expr<T> {}
 impl<T: PartialEq> PartialEq for Expr<T> {
     fn eq(&self, other: &Self) -> bool {
         use Expr::*;
+        let eq = |a: &P<Self>, b: &P<Self>| Arc::ptr_eq(a, b) || a == b;
         match (self, other) {
             (Add(l0, l1), Add(r0, r1))
             | (Mul(l0, l1), Mul(r0, r1))
-            | (Div(l0, l1), Div(r0, r1)) => {
-                *l0.read().unwrap() == *r0.read().unwrap()
-                    && *l1.read().unwrap() == *r1.read().unwrap()
-            }
-            (Neg(l0), Neg(r0)) => *l0.read().unwrap() == *r0.read().unwrap(),
-            (Pow(l0, l1), Pow(r0, r1)) => *l0.read().unwrap() == *r0.read().unwrap() && l1 == r1,
+            | (Div(l0, l1), Div(r0, r1)) => eq(l0, r0) && eq(l1, r1),
+            (Neg(l0), Neg(r0)) => eq(l0, r0),
+            (Pow(l0, l1), Pow(r0, r1)) => eq(l0, r0) && l1 == r1,
             (Leaf(l0), Leaf(r0)) => l0 == r0,
             _ => false,
         }
@@ -465,19 +472,21 @@ impl<T: Ord> Ord for Expr<T> {
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         use Expr::*;
         use Ordering::*;
+        // shared subexpressions are only compared once
+        let cmp = |a: &P<Self>, b: &P<Self>| {
+            if Arc::ptr_eq(a, b) {
+                Equal
+            } else {
+                a.cmp(b)
+            }
+        };
         match (self, other) {
-            (Pow(a, b), Pow(c, d)) => a
-                .read()
-                .unwrap()
-                .cmp(&c.read().unwrap())
-                .then_with(|| b.cmp(d)),
+            (Pow(a, b), Pow(c, d)) => cmp(a, c).then_with(|| b.cmp(d)),
             (Leaf(a), Leaf(b)) => a.cmp(b),
-            (Neg(a), Neg(b)) => a.read().unwrap().cmp(&b.read().unwrap()),
-            (Add(a, b), Add(c, d)) | (Mul(a, b), Mul(c, d)) | (Div(a, b), Div(c, d)) => a
-                .read()
-                .unwrap()
-                .cmp(&c.read().unwrap())
-                .then_with(|| b.read().unwrap().cmp(&d.read().unwrap())),
+            (Neg(a), Neg(b)) => cmp(a, b),
+            (Add(a, b), Add(c, d)) | (Mul(a, b), Mul(c, d)) | (Div(a, b), Div(c, d)) => {
+                cmp(a, c).then_with(|| cmp(b, d))
+            }
             (_, Leaf(_)) => Greater,
             (Leaf(_), _) => Less,
             (_, Add(_, _)) => Greater,
@@ -526,7 +535,7 @@ impl<T> Mul<Self> for Expr<T> {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self {
-        Self::Mul(Arc::new(RwLock::new(self)), Arc::new(RwLock::new(rhs)))
+        Self::Mul(Arc::new(self), Arc::new(rhs))
     }
 }
 
@@ -542,7 +551,7 @@ impl<T> Div<Self> for Expr<T> {
     type Output = Self;
 
     fn div(self, rhs: Self) -> Self {
-        Self::Div(Arc::new(RwLock::new(self)), Arc::new(RwLock::new(rhs)))
+        Self::Div(Arc::new(self), Arc::new(rhs))
     }
 }
 
@@ -558,7 +567,7 @@ impl<T> Add<Self> for Expr<T> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self {
-        Self::Add(Arc::new(RwLock::new(self)), Arc::new(RwLock::new(rhs)))
+        Self::Add(Arc::new(self), Arc::new(rhs))
     }
 }
 
@@ -583,7 +592,7 @@ impl<T> Neg for Expr<T> {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
-        Self::Neg(Arc::new(RwLock::new(self)))
+        Self::Neg(Arc::new(self))
     }
 }
 
@@ -650,7 +659,7 @@ impl<T> Sub<T> for Expr<T> {
     type Output = Self;
 
     fn sub(self, rhs: T) -> Self::Output {
-        self + Self::Neg(Arc::new(RwLock::new(Self::Leaf(rhs))))
+        self + Self::Neg(Arc::new(Self::Leaf(rhs)))
     }
 }
 
@@ -659,7 +668,7 @@ impl<T: Clone> Sub<&T> for &Expr<T> {
     type Output = Expr<T>;
 
     fn sub(self, rhs: &T) -> Self::Output {
-        self + Expr::Neg(Arc::new(RwLock::new(Expr::Leaf(rhs.clone()))))
+        self + Expr::Neg(Arc::new(Expr::Leaf(rhs.clone())))
     }
 }
 
@@ -676,7 +685,7 @@ impl<T> Pow<usize> for Expr<T> {
     type Output = Self;
 
     fn pow(self, rhs: usize) -> Self::Output {
-        Self::Pow(Arc::new(RwLock::new(self)), rhs)
+        Self::Pow(Arc::new(self), rhs)
     }
 }
 
@@ -684,7 +693,7 @@ impl<T: Clone> Pow<usize> for &Expr<T> {
     type Output = Expr<T>;
 
     fn pow(self, rhs: usize) -> Self::Output {
-        Expr::Pow(Arc::new(RwLock::new(self.clone())), rhs)
+        Expr::Pow(Arc::new(self.clone()), rhs)
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::hash::Digest;
 use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleCap;
 use crate::merkle::MerkleTree;
 use crate::random::PublicCoin;
 use crate::utils::GpuAllocator;
@@ -20,9 +21,11 @@ use ark_serialize::CanonicalSerialize;
 use ministark_gpu::prelude::*;
 use ministark_gpu::utils::bit_reverse;
 use ministark_gpu::utils::bit_reverse_index;
+use rand::RngCore;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
+use std::array;
 use std::iter::zip;
 use std::marker::PhantomData;
 
@@ -31,6 +34,7 @@ pub struct FriOptions {
     folding_factor: usize,
     max_remainder_coeffs: usize,
     blowup_factor: usize,
+    merkle_cap_height: u32,
 }
 
 impl FriOptions {
@@ -43,9 +47,16 @@ impl FriOptions {
             folding_factor,
             max_remainder_coeffs,
             blowup_factor,
+            merkle_cap_height: 0,
         }
     }
 
+    /// Sets the height of the merkle caps layers are committed to
+    pub const fn with_merkle_cap_height(mut self, merkle_cap_height: u32) -> Self {
+        self.merkle_cap_height = merkle_cap_height;
+        self
+    }
+
     pub const fn num_layers(&self, mut domain_size: usize) -> usize {
         let mut num_layers = 0;
         while domain_size > self.max_remainder_coeffs * self.blowup_factor {
@@ -99,14 +110,18 @@ struct FriLayer<F: GpuField, M: MerkleTree> {
 pub struct LayerProof<F: Field, D: Digest, M: MatrixMerkleTree<F>> {
     pub flattenend_rows: Vec<F>,
     pub merkle_proof: M::Proof,
-    pub commitment: D,
+    pub commitment: MerkleCap<D>,
 }
 
 impl<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>> LayerProof<F, D, M>
 where
     F::FftField: FftField,
 {
-    pub fn new<const N: usize>(rows: Vec<[F; N]>, merkle_proof: M::Proof, commitment: D) -> Self {
+    pub fn new<const N: usize>(
+        rows: Vec<[F; N]>,
+        merkle_proof: M::Proof,
+        commitment: MerkleCap<D>,
+    ) -> Self {
         Self {
             flattenend_rows: rows.into_iter().flatten().collect(),
             merkle_proof,
@@ -114,10 +129,22 @@ where
         }
     }
 
-    pub fn verify<const N: usize>(self, positions: &[usize]) -> Result<(), merkle::Error> {
+    pub fn verify<const N: usize>(
+        self,
+        height: u32,
+        cap_height: u32,
+        positions: &[usize],
+    ) -> Result<(), merkle::Error> {
         let commitment = &self.commitment;
         let (rows, _remainder) = &self.flattenend_rows.as_chunks::<N>();
-        M::verify_rows(commitment, positions, rows, self.merkle_proof)
+        M::verify_rows(
+            commitment,
+            height,
+            cap_height,
+            positions,
+            rows,
+            self.merkle_proof,
+        )
     }
 }
 
@@ -145,19 +172,23 @@ where
         }
     }
 
-    pub fn into_proof(self, positions: &[usize]) -> FriProof<F, D, M> {
+    /// # Errors
+    ///
+    /// Returns an error if a query position is out of bounds.
+    pub fn into_proof(self, positions: &[usize]) -> Result<FriProof<F, D, M>, merkle::Error> {
         let folding_factor = self.options.folding_factor;
         // let (last_layer, initial_layers) = self.layers.split_last().unwrap();
         let mut proof_layers = Vec::new();
+        let cap_height = self.options.merkle_cap_height;
         let mut positions = positions.to_vec();
         for layer in &self.layers {
             positions = fold_positions(&positions, folding_factor);
             proof_layers.push(match folding_factor {
-                2 => query_layer::<F, D, M, 2>(layer, &positions),
-                4 => query_layer::<F, D, M, 4>(layer, &positions),
-                6 => query_layer::<F, D, M, 6>(layer, &positions),
-                8 => query_layer::<F, D, M, 8>(layer, &positions),
-                16 => query_layer::<F, D, M, 16>(layer, &positions),
+                2 => query_layer::<F, D, M, 2>(layer, cap_height, &positions)?,
+                4 => query_layer::<F, D, M, 4>(layer, cap_height, &positions)?,
+                6 => query_layer::<F, D, M, 6>(layer, cap_height, &positions)?,
+                8 => query_layer::<F, D, M, 8>(layer, cap_height, &positions)?,
+                16 => query_layer::<F, D, M, 16>(layer, cap_height, &positions)?,
                 _ => unimplemented!("folding factor {folding_factor} is not supported"),
             });
         }
@@ -173,21 +204,23 @@ where
         // j];     }
         // }
 
-        FriProof::new(proof_layers, self.remainder_coeffs)
+        Ok(FriProof::new(proof_layers, self.remainder_coeffs))
     }
 
     pub fn build_layers(
         &mut self,
         channel: &mut impl ProverChannel<Field = F, Digest = D>,
         mut evaluations: GpuVec<F>,
+        mut rng: Option<&mut impl RngCore>,
     ) {
         assert!(self.layers.is_empty());
         for _ in 0..self.options.num_layers(evaluations.len()) {
+            let rng = rng.as_deref_mut();
             evaluations = match self.options.folding_factor {
-                2 => self.build_layer::<2>(channel, evaluations),
-                4 => self.build_layer::<4>(channel, evaluations),
-                8 => self.build_layer::<8>(channel, evaluations),
-                16 => self.build_layer::<16>(channel, evaluations),
+                2 => self.build_layer::<2>(channel, evaluations, rng),
+                4 => self.build_layer::<4>(channel, evaluations, rng),
+                8 => self.build_layer::<8>(channel, evaluations, rng),
+                16 => self.build_layer::<16>(channel, evaluations, rng),
                 folding_factor => unreachable!("folding factor {folding_factor} not supported"),
             }
         }
@@ -200,6 +233,7 @@ where
         &mut self,
         channel: &mut impl ProverChannel<Field = F, Digest = D>,
         evaluations: GpuVec<F>,
+        rng: Option<&mut impl RngCore>,
     ) -> GpuVec<F> {
         // Each layer requires decommitting to `folding_factor` many evaluations e.g.
         // `folding_factor = 2` decommits to an evaluation for LHS_i and RHS_i
@@ -213,8 +247,8 @@ where
         assert!(remainder.is_empty());
 
         let matrix = Matrix::from_arrays(cosets);
-        let merkle_tree = M::from_matrix(&matrix);
-        channel.commit_fri_layer(merkle_tree.root());
+        let merkle_tree: M = merkle::commit_rows(&matrix, rng);
+        channel.commit_fri_layer(merkle_tree.cap(self.options.merkle_cap_height));
 
         self.layers.push(FriLayer {
             merkle_tree,
@@ -276,7 +310,7 @@ where
     F::FftField: FftField,
 {
     options: FriOptions,
-    layer_commitments: Vec<D>,
+    layer_commitments: Vec<MerkleCap<D>>,
     pub layer_alphas: Vec<F>,
     proof: FriProof<F, D, M>,
     domain: Radix2EvaluationDomain<F::FftField>,
@@ -305,9 +339,8 @@ where
         let mut layer_commitments = Vec::new();
         let mut layer_codeword_len = domain_size;
         for (i, layer) in proof.layers.iter().enumerate() {
-            // TODO: batch merkle tree proofs
-            // get the merkle root from the first merkle path
-            public_coin.reseed_with_digest(&layer.commitment);
+            // TODO: batch merkle tree proofs across layers
+            public_coin.reseed_with_cap(&layer.commitment);
             let alpha = public_coin.draw();
             layer_alphas.push(alpha);
             layer_commitments.push(layer.commitment.clone());
@@ -372,6 +405,8 @@ where
             // verify the layer values against the layer's commitment
             M::verify_rows(
                 &layer_commitment,
+                (domain_size / N).ilog2(),
+                self.options.merkle_cap_height,
                 &folded_positions,
                 rows,
                 layer.merkle_proof,
@@ -480,7 +515,7 @@ pub trait ProverChannel {
     type Digest: Digest;
     type Field: GpuField;
 
-    fn commit_fri_layer(&mut self, layer_root: Self::Digest);
+    fn commit_fri_layer(&mut self, layer_cap: MerkleCap<Self::Digest>);
 
     fn commit_remainder(&mut self, remainder_coeffs: &[Self::Field]);
 
@@ -649,16 +684,28 @@ pub fn get_query_values<F: Field, const N: usize>(
 
 fn query_layer<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>, const N: usize>(
     layer: &FriLayer<F, M>,
+    cap_height: u32,
     positions: &[usize],
-) -> LayerProof<F, D, M>
+) -> Result<LayerProof<F, D, M>, merkle::Error>
 where
     F::FftField: FftField,
 {
-    let merkle_proof = layer.merkle_tree.prove_rows(positions).unwrap();
+    let merkle_proof = layer.merkle_tree.prove_rows(cap_height, positions)?;
     let mut rows: Vec<[F; N]> = Vec::new();
+    let num_rows = layer.evaluations.num_rows();
     for &position in positions {
-        let row = layer.evaluations.get_row(position).unwrap();
-        rows.push(row.try_into().unwrap());
+        if position >= num_rows {
+            return Err(merkle::Error::LeafIndexOutOfBounds {
+                i: position,
+                n: num_rows,
+            });
+        }
+        // layers are committed to as a matrix with `N` columns
+        rows.push(array::from_fn(|i| layer.evaluations[i][position]));
     }
-    LayerProof::new(rows, merkle_proof, layer.merkle_tree.root())
+    Ok(LayerProof::new(
+        rows,
+        merkle_proof,
+        layer.merkle_tree.cap(cap_height),
+    ))
 }
@@ -1,10 +1,17 @@
+mod rpo;
+
 use crate::utils::SerdeOutput;
 use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
 use core::fmt::Debug;
 use digest::Digest as _;
+pub use rpo::Rpo256;
+pub use rpo::Rpo256Digest;
+pub use rpo::Rpo256HashFn;
 use sha2::Sha256;
+use sha3::Keccak256;
 
 /// Trait inspired by Winterfell: <https://github.com/facebook/winterfell/blob/main/crypto/src/hash/mod.rs#L33>
 pub trait HashFn: Send + Sync + 'static {
@@ -24,6 +31,17 @@ pub trait HashFn: Send + Sync + 'static {
     /// construction of Merkle trees.
     fn merge(v0: &Self::Digest, v1: &Self::Digest) -> Self::Digest;
 
+    /// Returns a hash of several digests. This method is intended for use in
+    /// construction of Merkle trees with an arity above two. Merging two
+    /// digests must give the same result as [`HashFn::merge`].
+    fn merge_many(values: &[Self::Digest]) -> Self::Digest {
+        let bytes = values
+            .iter()
+            .map(Digest::as_bytes)
+            .collect::<Vec<[u8; 32]>>();
+        Self::hash_chunks(bytes.iter().map(|b| &b[..]))
+    }
+
     /// Returns hash(`seed` || `value`). This method is intended for use in PRNG
     /// and proof-of-work contexts.
     fn merge_with_int(seed: &Self::Digest, value: u64) -> Self::Digest;
@@ -40,6 +58,38 @@ pub trait ElementHashFn<F: Field>: HashFn {
     fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest;
 }
 
+/// Defines a hash function built from a permutation over a prime field.
+///
+/// Exposes the underlying permutation so field-native sponge constructions
+/// (e.g. [`SpongePublicCoin`](crate::random::SpongePublicCoin)) can absorb and
+/// squeeze field elements directly rather than going through bytes.
+pub trait AlgebraicHashFn<F: PrimeField>: ElementHashFn<F> {
+    /// Number of field elements in the permutation state.
+    const STATE_WIDTH: usize;
+
+    /// Number of state elements that are never directly absorbed into or
+    /// squeezed from. The remaining elements make up the rate.
+    const CAPACITY: usize;
+
+    /// Applies the permutation to a state of [`Self::STATE_WIDTH`] elements.
+    fn permute(state: &mut [F]);
+
+    /// Returns the field elements that make up a digest.
+    fn digest_elements(digest: &Self::Digest) -> Vec<F>;
+}
+
+/// Hashes the uncompressed serialization of field elements with a byte
+/// oriented hash function
+fn hash_serialized_elements<F: Field, H: HashFn>(
+    elements: impl IntoIterator<Item = F>,
+) -> H::Digest {
+    let mut byte_buffer = Vec::new();
+    for element in elements {
+        element.serialize_uncompressed(&mut byte_buffer).unwrap();
+    }
+    H::hash_chunks([&*byte_buffer])
+}
+
 /// Defines output type for a cryptographic hash function.
 /// Digest trait from Winterfell: <https://github.com/facebook/winterfell/blob/main/crypto/src/hash/mod.rs#L69>
 pub trait Digest:
@@ -55,6 +105,8 @@ pub trait Digest:
     fn as_bytes(&self) -> [u8; 32];
 }
 
+pub type Sha256Digest = SerdeOutput<Sha256>;
+
 pub struct Sha256HashFn;
 
 impl HashFn for Sha256HashFn {
@@ -91,10 +143,314 @@ impl HashFn for Sha256HashFn {
 
 impl<F: Field> ElementHashFn<F> for Sha256HashFn {
     fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
-        let mut byte_buffer = Vec::new();
-        for element in elements {
-            element.serialize_uncompressed(&mut byte_buffer).unwrap();
+        hash_serialized_elements::<F, Self>(elements)
+    }
+}
+
+pub type Keccak256Digest = SerdeOutput<Keccak256>;
+
+/// Keccak-256 as used by Ethereum (not NIST SHA3-256)
+pub struct Keccak256HashFn;
+
+impl HashFn for Keccak256HashFn {
+    type Digest = SerdeOutput<Keccak256>;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> SerdeOutput<Keccak256> {
+        let mut hasher = Keccak256::new();
+        bytes.into_iter().for_each(|b| hasher.update([b]));
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn hash_chunks<'a>(slices: impl IntoIterator<Item = &'a [u8]>) -> SerdeOutput<Keccak256> {
+        let mut hasher = Keccak256::new();
+        slices.into_iter().for_each(|s| hasher.update(s));
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn merge(v0: &SerdeOutput<Keccak256>, v1: &SerdeOutput<Keccak256>) -> SerdeOutput<Keccak256> {
+        let mut hasher = Keccak256::new();
+        hasher.update(**v0);
+        hasher.update(**v1);
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn merge_with_int(seed: &SerdeOutput<Keccak256>, value: u64) -> SerdeOutput<Keccak256> {
+        let mut hasher = Keccak256::new();
+        hasher.update(**seed);
+        hasher.update(value.to_be_bytes());
+        SerdeOutput::new(hasher.finalize())
+    }
+}
+
+impl<F: Field> ElementHashFn<F> for Keccak256HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+        hash_serialized_elements::<F, Self>(elements)
+    }
+}
+
+/// Output of [`Blake3HashFn`]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, CanonicalSerialize, CanonicalDeserialize,
+)]
+pub struct Blake3Digest(pub [u8; 32]);
+
+impl Digest for Blake3Digest {
+    fn as_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl From<blake3::Hash> for Blake3Digest {
+    fn from(hash: blake3::Hash) -> Self {
+        Self(hash.into())
+    }
+}
+
+pub struct Blake3HashFn;
+
+impl HashFn for Blake3HashFn {
+    type Digest = Blake3Digest;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> Blake3Digest {
+        let mut hasher = blake3::Hasher::new();
+        bytes.into_iter().for_each(|b| {
+            hasher.update(&[b]);
+        });
+        hasher.finalize().into()
+    }
+
+    fn hash_chunks<'a>(slices: impl IntoIterator<Item = &'a [u8]>) -> Blake3Digest {
+        let mut hasher = blake3::Hasher::new();
+        slices.into_iter().for_each(|s| {
+            hasher.update(s);
+        });
+        hasher.finalize().into()
+    }
+
+    fn merge(v0: &Blake3Digest, v1: &Blake3Digest) -> Blake3Digest {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&v0.0);
+        hasher.update(&v1.0);
+        hasher.finalize().into()
+    }
+
+    fn merge_with_int(seed: &Blake3Digest, value: u64) -> Blake3Digest {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&seed.0);
+        hasher.update(&value.to_be_bytes());
+        hasher.finalize().into()
+    }
+}
+
+impl<F: Field> ElementHashFn<F> for Blake3HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+        hash_serialized_elements::<F, Self>(elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Blake3HashFn;
+    use super::Digest;
+    use super::ElementHashFn;
+    use super::HashFn;
+    use super::Keccak256HashFn;
+    use super::Rpo256;
+    use super::Rpo256HashFn;
+    use super::Sha256HashFn;
+    use ark_ff::One;
+    use ark_ff::Zero;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    fn from_hex(hex: &str) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
         }
-        Self::hash_chunks([&*byte_buffer])
+        bytes
+    }
+
+    #[test]
+    fn keccak256_matches_ethereum() {
+        let digest = Keccak256HashFn::hash_chunks([&b""[..]]);
+
+        assert_eq!(
+            digest.as_bytes(),
+            from_hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+    }
+
+    #[test]
+    fn blake3_matches_reference() {
+        let digest = Blake3HashFn::hash_chunks([&b""[..]]);
+
+        assert_eq!(
+            digest.as_bytes(),
+            from_hex("af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262")
+        );
+    }
+
+    /// Hashes with miden-crypto's sponge. It sets the first capacity element
+    /// to the input length modulo the rate and pads with zeros.
+    fn miden_rpo256_hash_elements(elements: &[Fp]) -> [Fp; 4] {
+        let mut state = [Fp::zero(); 12];
+        state[0] = Fp::from((elements.len() % 8) as u64);
+        for chunk in elements.chunks(8) {
+            state[4..].fill(Fp::zero());
+            state[4..4 + chunk.len()].copy_from_slice(chunk);
+            Rpo256::permute(&mut state);
+        }
+        state[4..8].try_into().unwrap()
+    }
+
+    #[test]
+    #[allow(clippy::unreadable_literal)]
+    fn rpo256_permutation_matches_miden() {
+        let elements = (0..4u64).map(Fp::from).collect::<Vec<Fp>>();
+
+        // first vectors of `EXPECTED` in miden-crypto's RPO tests
+        // (`src/hash/rescue/rpo/tests.rs`) i.e. the hashes of `[0, .., i)`
+        let expected: [[u64; 4]; 4] = [
+            [
+                18126731724905382595,
+                7388557040857728717,
+                14290750514634285295,
+                7852282086160480146,
+            ],
+            [
+                10139303045932500183,
+                2293916558361785533,
+                15496361415980502047,
+                17904948502382283940,
+            ],
+            [
+                17457546260239634015,
+                803990662839494686,
+                10386005777401424878,
+                18168807883298448638,
+            ],
+            [
+                13072499238647455740,
+                10174350003422057273,
+                9201651627651151113,
+                6872461887313298746,
+            ],
+        ];
+        for (i, expected) in expected.into_iter().enumerate() {
+            let digest = miden_rpo256_hash_elements(&elements[..=i]);
+            assert_eq!(
+                digest,
+                expected.map(Fp::from),
+                "mismatch hashing {} elements",
+                i + 1
+            );
+        }
+    }
+
+    #[test]
+    fn rpo256_pads_partial_rate() {
+        let elements = (0..10u64).map(Fp::from).collect::<Vec<Fp>>();
+
+        let digest = Rpo256HashFn::hash_elements(elements.iter().copied());
+
+        // a single one follows the input and the first capacity element is set
+        // since padding was applied. This matches the GPU kernels.
+        let mut state = [Fp::zero(); 12];
+        state[0] = Fp::one();
+        state[4..].copy_from_slice(&elements[..8]);
+        Rpo256::permute(&mut state);
+        state[4..].fill(Fp::zero());
+        state[4..6].copy_from_slice(&elements[8..]);
+        state[6] = Fp::one();
+        Rpo256::permute(&mut state);
+        assert_eq!(digest.0, state[4..8]);
+    }
+
+    #[test]
+    fn rpo256_full_rate_matches_miden() {
+        // inputs that fill the rate aren't padded by either sponge
+        let elements = (0..16u64).map(Fp::from).collect::<Vec<Fp>>();
+
+        let digest = Rpo256HashFn::hash_elements(elements.iter().copied());
+
+        assert_eq!(digest.0, miden_rpo256_hash_elements(&elements));
+    }
+
+    #[test]
+    #[cfg(all(feature = "gpu", target_arch = "aarch64", target_os = "macos"))]
+    fn gpu_rpo256_matches_cpu() {
+        use ministark_gpu::plan::GpuRpo256ColumnMajor;
+        use ministark_gpu::plan::GpuRpo256RowMajor;
+        use ministark_gpu::utils::page_aligned_uninit_vector;
+
+        let n = 2048;
+        let value = |row: usize, col: usize| Fp::from((row * 10 + col) as u64);
+
+        // 10 columns so the column major hasher pads the second absorption
+        let columns = (0..10)
+            .map(|col| {
+                let mut column = unsafe { page_aligned_uninit_vector(n) };
+                for (row, v) in column.iter_mut().enumerate() {
+                    *v = value(row, col);
+                }
+                column
+            })
+            .collect::<Vec<Vec<Fp>>>();
+        let mut column_major = GpuRpo256ColumnMajor::new(n, true);
+        for column in &columns {
+            column_major.update(column);
+        }
+        let column_major_digests = pollster::block_on(column_major.finish());
+
+        let mut rows: Vec<[Fp; 8]> = unsafe { page_aligned_uninit_vector(n) };
+        for (row, values) in rows.iter_mut().enumerate() {
+            *values = core::array::from_fn(|col| value(row, col));
+        }
+        let mut row_major = GpuRpo256RowMajor::new(n, false);
+        row_major.update(&rows);
+        let row_major_digests = pollster::block_on(row_major.finish());
+
+        let digests = column_major_digests.iter().zip(&row_major_digests);
+        for (row, (column_major_digest, row_major_digest)) in digests.enumerate() {
+            let cpu_digest = Rpo256HashFn::hash_elements((0..10).map(|col| value(row, col)));
+            assert_eq!(
+                *column_major_digest, cpu_digest.0,
+                "column major mismatch at {row}"
+            );
+            let cpu_digest = Rpo256HashFn::hash_elements(rows[row]);
+            assert_eq!(
+                *row_major_digest, cpu_digest.0,
+                "row major mismatch at {row}"
+            );
+        }
+    }
+
+    #[test]
+    fn rpo256_merge_matches_hash_of_full_rate() {
+        let v0 = Rpo256HashFn::hash_elements([Fp::from(1u64)]);
+        let v1 = Rpo256HashFn::hash_elements([Fp::from(2u64)]);
+
+        let merged = Rpo256HashFn::merge(&v0, &v1);
+
+        let elements = v0.0.into_iter().chain(v1.0);
+        assert_eq!(merged, Rpo256HashFn::hash_elements(elements));
+    }
+
+    #[test]
+    fn merge_many_of_two_digests_matches_merge() {
+        fn check<H: HashFn>() {
+            let v0 = H::hash_chunks([&b"left"[..]]);
+            let v1 = H::hash_chunks([&b"right"[..]]);
+            assert_eq!(H::merge_many(&[v0.clone(), v1.clone()]), H::merge(&v0, &v1));
+        }
+
+        check::<Sha256HashFn>();
+        check::<Keccak256HashFn>();
+        check::<Blake3HashFn>();
+        check::<Rpo256HashFn>();
     }
 }
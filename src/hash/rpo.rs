@@ -0,0 +1,559 @@
+//! Rescue Prime Optimized hash function for 128 bit security over the 64-bit
+//! Goldilocks field: <https://eprint.iacr.org/2022/1577.pdf>
+//!
+//! The permutation, sponge padding and Merkle node hashing match the Metal
+//! kernels in `ministark-gpu` so digests computed on the CPU and GPU agree.
+//! The permutation also matches miden-crypto's but partial rates are padded
+//! with a one rather than marking the input length in the capacity.
+#![allow(clippy::unreadable_literal)]
+
+use super::AlgebraicHashFn;
+use super::Digest;
+use super::ElementHashFn;
+use super::HashFn;
+use alloc::vec::Vec;
+use ark_ff::BigInt;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use core::marker::PhantomData;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+const STATE_WIDTH: usize = 12;
+const CAPACITY: usize = 4;
+const RATE: usize = 8;
+const DIGEST_SIZE: usize = 4;
+const NUM_ROUNDS: usize = 7;
+
+/// Exponent of the inverse S-box i.e. `7^(-1) mod (p - 1)`
+const INV_ALPHA: u64 = 10540996611094048183;
+
+/// Rescue Prime Optimized permutation with a state width of 12 field elements
+pub struct Rpo256;
+
+impl Rpo256 {
+    /// Applies the RPO permutation to the state. The first 4 elements
+    /// of the state hold the capacity and the remaining 8 hold the rate.
+    pub fn permute(state: &mut [Fp; STATE_WIDTH]) {
+        for round in 0..NUM_ROUNDS {
+            Self::apply_round(state, round);
+        }
+    }
+
+    fn apply_round(state: &mut [Fp; STATE_WIDTH], round: usize) {
+        // first half of the round
+        apply_mds(state);
+        for (v, c) in state.iter_mut().zip(ROUND_CONSTANTS_0[round]) {
+            *v = (*v + c).pow([7]);
+        }
+
+        // second half of the round
+        apply_mds(state);
+        for (v, c) in state.iter_mut().zip(ROUND_CONSTANTS_1[round]) {
+            *v = (*v + c).pow([INV_ALPHA]);
+        }
+    }
+}
+
+fn apply_mds(state: &mut [Fp; STATE_WIDTH]) {
+    let mut result = [Fp::ZERO; STATE_WIDTH];
+    for (v, row) in result.iter_mut().zip(MDS) {
+        *v = state.iter().zip(row).map(|(&s, m)| s * m).sum();
+    }
+    *state = result;
+}
+
+/// Output of [`Rpo256HashFn`]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, CanonicalSerialize, CanonicalDeserialize,
+)]
+pub struct Rpo256Digest(pub [Fp; DIGEST_SIZE]);
+
+impl Digest for Rpo256Digest {
+    fn as_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, element) in bytes.chunks_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&element.into_bigint().0[0].to_le_bytes());
+        }
+        bytes
+    }
+}
+
+pub struct Rpo256HashFn;
+
+impl HashFn for Rpo256HashFn {
+    type Digest = Rpo256Digest;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> Rpo256Digest {
+        // each element holds 7 bytes so it can't exceed the field modulus. A
+        // terminating 1 byte is appended to make the encoding injective.
+        let mut bytes = bytes.into_iter().collect::<Vec<u8>>();
+        bytes.push(1);
+        let elements = bytes.chunks(7).map(|chunk| {
+            let mut element_bytes = [0; 8];
+            element_bytes[..chunk.len()].copy_from_slice(chunk);
+            Fp::from(u64::from_le_bytes(element_bytes))
+        });
+        Self::hash_elements(elements)
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> Rpo256Digest {
+        Self::hash(chunks.into_iter().flatten().copied())
+    }
+
+    fn merge(v0: &Rpo256Digest, v1: &Rpo256Digest) -> Rpo256Digest {
+        // matches the GPU merkle tree kernels: capacity is zeroed and the rate
+        // is filled by the two digests
+        let mut state = [Fp::ZERO; STATE_WIDTH];
+        state[CAPACITY..CAPACITY + DIGEST_SIZE].copy_from_slice(&v0.0);
+        state[CAPACITY + DIGEST_SIZE..].copy_from_slice(&v1.0);
+        Rpo256::permute(&mut state);
+        Rpo256Digest(state[CAPACITY..CAPACITY + DIGEST_SIZE].try_into().unwrap())
+    }
+
+    fn merge_many(values: &[Rpo256Digest]) -> Rpo256Digest {
+        // absorbing two digests fills the rate exactly so this agrees with `merge`
+        Self::hash_elements(values.iter().flat_map(|v| v.0))
+    }
+
+    fn merge_with_int(seed: &Rpo256Digest, value: u64) -> Rpo256Digest {
+        // split into 32-bit limbs since `value` can exceed the field modulus
+        let limbs = [value & 0xFFFF_FFFF, value >> 32].map(Fp::from);
+        Self::hash_elements(seed.0.into_iter().chain(limbs))
+    }
+}
+
+impl ElementHashFn<Fp> for Rpo256HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = Fp>) -> Rpo256Digest {
+        let elements = elements.into_iter().collect::<Vec<Fp>>();
+        let mut state = [Fp::ZERO; STATE_WIDTH];
+        // padding rule: "a single 1 element followed by as many zeros as are
+        // necessary to make the input length a multiple of the rate". The first
+        // capacity element records if padding was applied.
+        if elements.len() % RATE != 0 {
+            state[0] = Fp::ONE;
+        }
+
+        for chunk in elements.chunks(RATE) {
+            let rate = &mut state[CAPACITY..];
+            rate[..chunk.len()].copy_from_slice(chunk);
+            if chunk.len() != RATE {
+                rate[chunk.len()] = Fp::ONE;
+                rate[chunk.len() + 1..].fill(Fp::ZERO);
+            }
+            Rpo256::permute(&mut state);
+        }
+
+        Rpo256Digest(state[CAPACITY..CAPACITY + DIGEST_SIZE].try_into().unwrap())
+    }
+}
+
+impl ElementHashFn<Fq3> for Rpo256HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = Fq3>) -> Rpo256Digest {
+        let elements = elements
+            .into_iter()
+            .flat_map(|e| e.to_base_prime_field_elements());
+        <Self as ElementHashFn<Fp>>::hash_elements(elements)
+    }
+}
+
+impl AlgebraicHashFn<Fp> for Rpo256HashFn {
+    const STATE_WIDTH: usize = STATE_WIDTH;
+    const CAPACITY: usize = CAPACITY;
+
+    fn permute(state: &mut [Fp]) {
+        Rpo256::permute(state.try_into().unwrap());
+    }
+
+    fn digest_elements(digest: &Rpo256Digest) -> Vec<Fp> {
+        digest.0.to_vec()
+    }
+}
+
+/// Creates a field element from its Montgomery representation
+const fn fp(v: u64) -> Fp {
+    ark_ff::Fp(BigInt([v]), PhantomData)
+}
+
+// RPO's constants are stored in Montgomery form (R = 2^64) so they can be
+// compared against `hash_shaders.h.metal` directly
+
+/// RPO's 12x12 row major MDS matrix
+const MDS: [[Fp; STATE_WIDTH]; STATE_WIDTH] = [
+    [
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+    ],
+    [
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+    ],
+    [
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+    ],
+    [
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+    ],
+    [
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+    ],
+    [
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+    ],
+    [
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+    ],
+    [
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+    ],
+    [
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+    ],
+    [
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+        fp(34359738360),
+    ],
+    [
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+        fp(98784247785),
+    ],
+    [
+        fp(98784247785),
+        fp(34359738360),
+        fp(111669149670),
+        fp(55834574835),
+        fp(42949672950),
+        fp(38654705655),
+        fp(30064771065),
+        fp(25769803770),
+        fp(94489280490),
+        fp(90194313195),
+        fp(34359738360),
+        fp(30064771065),
+    ],
+];
+
+/// Round constants used in the first half of each round
+const ROUND_CONSTANTS_0: [[Fp; STATE_WIDTH]; NUM_ROUNDS] = [
+    [
+        fp(6936159699454947676),
+        fp(6871277616928621393),
+        fp(4226339945476756083),
+        fp(2261225084505152444),
+        fp(16808067423291017741),
+        fp(12862191241011323277),
+        fp(345720808813194915),
+        fp(10126368034161173654),
+        fp(840649715788759894),
+        fp(18155600607269645987),
+        fp(16577339120870559289),
+        fp(13749826054300849029),
+    ],
+    [
+        fp(16047969944113931191),
+        fp(10474334246235299199),
+        fp(15773847146013662260),
+        fp(14401231158322525155),
+        fp(6009395255763488383),
+        fp(2108579439821148946),
+        fp(13820200715803196660),
+        fp(15968614366574245570),
+        fp(7529997729792773654),
+        fp(9429194013557833999),
+        fp(11639903126146281421),
+        fp(15759666882357935738),
+    ],
+    [
+        fp(14807658266593669785),
+        fp(17258259860767641342),
+        fp(9534132615398591413),
+        fp(358719342502509866),
+        fp(7123090532818864651),
+        fp(734193187930710962),
+        fp(14873184913735487023),
+        fp(17965359964069906568),
+        fp(12664837478844326631),
+        fp(15575491070113731145),
+        fp(7221479899469196675),
+        fp(7328957460733188967),
+    ],
+    [
+        fp(15088355010936495340),
+        fp(16762963605345901631),
+        fp(15278161326153175940),
+        fp(6257793333052173411),
+        fp(8418953127708045776),
+        fp(6523475766574412380),
+        fp(15192936988185261803),
+        fp(1578086224854546096),
+        fp(10840553425559156784),
+        fp(7453417405109536362),
+        fp(5173069484734008228),
+        fp(3284492202065476384),
+    ],
+    [
+        fp(1724586709636399686),
+        fp(17997633752581871175),
+        fp(1284825320737914582),
+        fp(960534381847281815),
+        fp(6708901808183456837),
+        fp(8975591106768797316),
+        fp(52515315389099119),
+        fp(10009391031874081397),
+        fp(3091228317422201238),
+        fp(1063858230459024983),
+        fp(3396548655473917480),
+        fp(15046057790353688034),
+    ],
+    [
+        fp(4867464583127666756),
+        fp(13816959924674544309),
+        fp(13931201815459591565),
+        fp(11494116713280125381),
+        fp(16823081743980874023),
+        fp(6760771226809185048),
+        fp(5346741505458044699),
+        fp(15124596060558844029),
+        fp(5332565678905773189),
+        fp(17640389307200936126),
+        fp(14049814539797608740),
+        fp(8882709539093378074),
+    ],
+    [
+        fp(10507930462458090835),
+        fp(10669463960502417047),
+        fp(16753662827442720769),
+        fp(12967456627495301601),
+        fp(2989815121821278695),
+        fp(5894674479204135685),
+        fp(14187454698288462352),
+        fp(14795723369628125345),
+        fp(17260571099239679821),
+        fp(16009836214833755168),
+        fp(2009092225887788829),
+        fp(10838446069154019765),
+    ],
+];
+
+/// Round constants used in the second half of each round
+const ROUND_CONSTANTS_1: [[Fp; STATE_WIDTH]; NUM_ROUNDS] = [
+    [
+        fp(8939123259393952351),
+        fp(14708045228210488368),
+        fp(18125168669810517809),
+        fp(9309821433754818185),
+        fp(4714467145607136006),
+        fp(1302482025306688824),
+        fp(34829973686821040),
+        fp(5637233680011148778),
+        fp(227119480134509573),
+        fp(2530972937109017559),
+        fp(7210163798538732239),
+        fp(955913576003606833),
+    ],
+    [
+        fp(4449617297638325218),
+        fp(10843671682695268638),
+        fp(13198957499160452915),
+        fp(11541825028620451829),
+        fp(10963484480734735121),
+        fp(4752902142121643229),
+        fp(3015289210993491059),
+        fp(16344286514680205966),
+        fp(1811079964700766606),
+        fp(12735664961476037524),
+        fp(5775391330037813314),
+        fp(18223625362487900986),
+    ],
+    [
+        fp(7222477607687412281),
+        fp(4215615082079701144),
+        fp(6177508277476483691),
+        fp(3491362079220677263),
+        fp(10961785333913978630),
+        fp(1935408839283360916),
+        fp(13974192629927279950),
+        fp(18013556876298568088),
+        fp(7565676920589638093),
+        fp(9265825103386412558),
+        fp(8061587790235022972),
+        fp(6806849270604947860),
+    ],
+    [
+        fp(8066442548506952806),
+        fp(12791828131640457742),
+        fp(9268748809821748950),
+        fp(17496234860625277598),
+        fp(13583894547367420658),
+        fp(13920282495726802458),
+        fp(3933141341199584259),
+        fp(6658057712176150702),
+        fp(16812362035931029194),
+        fp(15160401867587809089),
+        fp(16411108749946146942),
+        fp(3390826434320009844),
+    ],
+    [
+        fp(18405475140095477472),
+        fp(13864039573264702148),
+        fp(496144052468360460),
+        fp(9791523668470936672),
+        fp(528582340156917005),
+        fp(15864481364569144493),
+        fp(682830611952089590),
+        fp(347158833826327515),
+        fp(13752775429919623417),
+        fp(10254722988306758482),
+        fp(8794150602427420596),
+        fp(2480344122229837853),
+    ],
+    [
+        fp(15462337562022968595),
+        fp(6729968753311049611),
+        fp(9250220857258211097),
+        fp(12031447985684644003),
+        fp(14538803180331344696),
+        fp(4055445230671851890),
+        fp(14764039661528567501),
+        fp(2047787218814287270),
+        fp(8977863094202715520),
+        fp(6560450968915612407),
+        fp(9976241128570886075),
+        fp(17877509887772213755),
+    ],
+    [
+        fp(3549624494907837709),
+        fp(4253629935471652443),
+        fp(2859199883984623807),
+        fp(1087607721547343649),
+        fp(7907517619951970198),
+        fp(11306402795121903516),
+        fp(10168009948206732524),
+        fp(9177440083248248246),
+        fp(13169036816957726187),
+        fp(12924186209140199217),
+        fp(9673006056831483321),
+        fp(747828276541750689),
+    ],
+];
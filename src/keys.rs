@@ -0,0 +1,127 @@
+//! Proving and verification keys that commit to the preprocessed columns of an
+//! AIR
+
+use crate::air::lde_domain;
+use crate::air::trace_domain;
+use crate::air::AirConfig;
+use crate::air::AirError;
+use crate::hash::Digest;
+use crate::merkle;
+use crate::merkle::MerkleCap;
+use crate::merkle::MerkleTree;
+use crate::stark::Stark;
+use crate::Matrix;
+use crate::ProofOptions;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use rand::RngCore;
+
+/// Low degree extension and commitment of the preprocessed columns of an AIR.
+/// A proving key is computed once and shared by every proof with the same
+/// trace length and proof options.
+pub struct ProvingKey<S: Stark> {
+    trace_len: usize,
+    options: ProofOptions,
+    preprocessed_trace: Option<PreprocessedTrace<S>>,
+}
+
+pub(crate) struct PreprocessedTrace<S: Stark> {
+    pub polys: Matrix<S::Fp>,
+    /// Evaluations over the LDE domain in bit-reversed order
+    pub lde: Matrix<S::Fp>,
+    pub tree: S::MerkleTree,
+}
+
+impl<S: Stark> ProvingKey<S> {
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * the trace length is not a power of two
+    /// * the number of preprocessed columns doesn't match the AIR
+    /// * the preprocessed columns don't have `trace_len` rows
+    ///
+    /// Hiding Merkle trees draw their salts from `rng`, which is only needed
+    /// if [`MatrixMerkleTree::HIDING`] is set.
+    ///
+    /// [`MatrixMerkleTree::HIDING`]: crate::merkle::MatrixMerkleTree::HIDING
+    pub fn new(
+        trace_len: usize,
+        options: ProofOptions,
+        rng: Option<&mut impl RngCore>,
+    ) -> Result<Self, AirError> {
+        if !trace_len.is_power_of_two() {
+            return Err(AirError::TraceLengthNotPowerOfTwo { len: trace_len });
+        }
+
+        let columns = S::AirConfig::preprocessed_columns(trace_len);
+        let num_columns = columns.as_ref().map_or(0, Matrix::num_cols);
+        let expected_num_columns = S::AirConfig::NUM_PREPROCESSED_COLUMNS;
+        if num_columns != expected_num_columns {
+            return Err(AirError::PreprocessedColumnCountMismatch {
+                expected: expected_num_columns,
+                actual: num_columns,
+            });
+        }
+
+        let preprocessed_trace = columns
+            .filter(|columns| columns.num_cols() != 0)
+            .map(|columns| {
+                let num_rows = columns.num_rows();
+                if num_rows != trace_len {
+                    return Err(AirError::PreprocessedRowCountMismatch {
+                        expected: trace_len,
+                        actual: num_rows,
+                    });
+                }
+                let trace_xs = trace_domain::<S::AirConfig>(trace_len);
+                let lde_xs =
+                    lde_domain::<S::AirConfig>(trace_len, options.lde_blowup_factor.into());
+                let polys = columns.interpolate(trace_xs);
+                let lde = polys.bit_reversed_evaluate(lde_xs);
+                let tree = merkle::commit_rows(&lde, rng);
+                Ok(PreprocessedTrace { polys, lde, tree })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            trace_len,
+            options,
+            preprocessed_trace,
+        })
+    }
+
+    pub const fn trace_len(&self) -> usize {
+        self.trace_len
+    }
+
+    pub const fn options(&self) -> ProofOptions {
+        self.options
+    }
+
+    pub(crate) const fn preprocessed_trace(&self) -> Option<&PreprocessedTrace<S>> {
+        self.preprocessed_trace.as_ref()
+    }
+
+    /// Returns the key verifiers check proofs against
+    pub fn verification_key(&self) -> VerificationKey<S::Digest> {
+        let cap_height = self.options.merkle_cap_height.into();
+        VerificationKey {
+            trace_len: self.trace_len,
+            options: self.options,
+            preprocessed_trace_commitment: self
+                .preprocessed_trace
+                .as_ref()
+                .map(|preprocessed_trace| preprocessed_trace.tree.cap(cap_height)),
+        }
+    }
+}
+
+/// Commitment to the preprocessed columns of an AIR. Proofs only verify
+/// against the verification key of the proving key they were generated with.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct VerificationKey<D: Digest> {
+    pub trace_len: usize,
+    pub options: ProofOptions,
+    /// `None` if the AIR has no preprocessed columns
+    pub preprocessed_trace_commitment: Option<MerkleCap<D>>,
+}
@@ -16,13 +16,11 @@
     allocator_api,
     let_chains,
     array_windows,
-    array_chunks,
     iter_partition_in_place,
     slice_flatten,
     slice_as_chunks,
     async_fn_in_trait,
     const_trait_impl,
-    cow_is_borrowed,
     exclusive_range_pattern,
     vec_into_raw_parts,
     return_position_impl_trait_in_trait,
@@ -33,8 +31,11 @@
 #[macro_use]
 pub mod macros;
 pub mod air;
+pub mod assertions;
+pub mod bytecode;
 pub mod challenges;
 pub mod channel;
+pub mod codegen;
 pub mod composer;
 pub mod constraints;
 pub mod debug;
@@ -44,8 +45,10 @@ pub mod expression;
 pub mod fri;
 pub mod hash;
 pub mod hints;
+pub mod keys;
 pub mod matrix;
 pub mod merkle;
+pub mod printer;
 pub mod proof;
 pub mod prover;
 pub mod random;
@@ -90,6 +93,12 @@ pub struct ProofOptions {
     pub grinding_factor: u8,
     pub fri_folding_factor: u8,
     pub fri_max_remainder_coeffs: u8,
+    /// Masks the trace and DEEP composition polynomials so proofs reveal
+    /// nothing about the witness beyond the validity of the statement.
+    pub zero_knowledge: bool,
+    /// Depth of the Merkle tree nodes that are committed to in place of the
+    /// root. Authentication paths stop at these nodes.
+    pub merkle_cap_height: u8,
 }
 
 impl ProofOptions {
@@ -98,6 +107,7 @@ impl ProofOptions {
     pub const MIN_BLOWUP_FACTOR: u8 = 1;
     pub const MAX_BLOWUP_FACTOR: u8 = 128;
     pub const MAX_GRINDING_FACTOR: u8 = 50;
+    pub const MAX_MERKLE_CAP_HEIGHT: u8 = 16;
 
     pub const fn new(
         num_queries: u8,
@@ -118,16 +128,58 @@ impl ProofOptions {
             grinding_factor,
             fri_folding_factor,
             fri_max_remainder_coeffs,
+            zero_knowledge: false,
+            merkle_cap_height: 0,
         }
     }
 
+    /// Enables zero-knowledge proving. Randomized trace polynomials have twice
+    /// the degree of the trace which halves the blowup factor FRI operates
+    /// with. FRI still needs a blowup factor of at least two so the LDE
+    /// blowup factor must be at least four.
+    pub const fn with_zero_knowledge(mut self) -> Self {
+        self.zero_knowledge = true;
+        assert!(self.fri_blowup_factor() >= 2);
+        self
+    }
+
+    /// Commits to the nodes at depth `merkle_cap_height` rather than a single
+    /// Merkle root. Trees with arity `k` have caps of up to
+    /// `k^merkle_cap_height` nodes. Larger caps make commitments bigger and
+    /// every authentication path shorter.
+    pub const fn with_merkle_cap_height(mut self, merkle_cap_height: u8) -> Self {
+        assert!(merkle_cap_height <= Self::MAX_MERKLE_CAP_HEIGHT);
+        self.merkle_cap_height = merkle_cap_height;
+        self
+    }
+
+    /// Returns the blowup factor of the DEEP composition polynomial over the
+    /// low degree extension domain.
+    pub const fn fri_blowup_factor(&self) -> u8 {
+        if self.zero_knowledge {
+            self.lde_blowup_factor / 2
+        } else {
+            self.lde_blowup_factor
+        }
+    }
+
+    /// Returns true if FRI supports these options. FRI supports folding
+    /// factors of 2, 4, 8 and 16 and the blowup factor and remainder must be
+    /// powers of two.
+    pub const fn has_supported_fri_options(&self) -> bool {
+        matches!(self.fri_folding_factor, 2 | 4 | 8 | 16)
+            && self.fri_max_remainder_coeffs.is_power_of_two()
+            && self.fri_blowup_factor().is_power_of_two()
+    }
+
     pub fn into_fri_options(self) -> FriOptions {
         // TODO: move fri params into struct
         FriOptions::new(
-            self.lde_blowup_factor.into(),
+            self.fri_blowup_factor().into(),
             self.fri_folding_factor.into(),
             self.fri_max_remainder_coeffs.into(),
         )
+        .with_merkle_cap_height(self.merkle_cap_height.into())
     }
 }
 
@@ -1,15 +1,21 @@
+use crate::hash::Blake3HashFn;
 use crate::hash::Digest;
 use crate::hash::ElementHashFn;
 use crate::hash::HashFn;
+use crate::hash::Keccak256HashFn;
+use crate::hash::Rpo256HashFn;
+use crate::hash::Sha256HashFn;
 use crate::Matrix;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use rand::RngCore;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
-use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::iter::zip;
 use std::marker::PhantomData;
@@ -34,39 +40,100 @@ pub trait MerkleTree: Sized + Send + Sync + Clone {
     /// Returns the root of the merkle tree
     fn root(&self) -> Self::Root;
 
+    /// Returns the nodes at depth `cap_height`. The height is clamped so the
+    /// cap never contains leaves.
+    fn cap(&self, cap_height: u32) -> MerkleCap<Self::Root>;
+
     /// Generates a merkle proof
     ///
     /// # Errors
     ///
     /// Returns an error if the leaf index is out of bounds.
-    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error>;
+    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
+        self.prove_to_cap(0, indices)
+    }
+
+    /// Generates a merkle proof with paths that end at the cap of the given
+    /// height rather than the root
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the leaf index is out of bounds.
+    fn prove_to_cap(&self, cap_height: u32, indices: &[usize]) -> Result<Self::Proof, Error>;
 
-    /// Verifies a merkle proof
+    /// Verifies a merkle proof of a tree with `2^height` leaves
     ///
     /// # Errors
     ///
     /// This function returns an error if the proof fails verification.
-    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error>;
+    fn verify(
+        root: &Self::Root,
+        height: u32,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        Self::verify_to_cap(&MerkleCap::from(root.clone()), height, 0, proof, indices)
+    }
+
+    /// Verifies a merkle proof of a tree with `2^height` leaves against its cap
+    /// of height `cap_height`. Cap heights are clamped like
+    /// [`MerkleTree::cap`].
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the proof fails verification or if
+    /// the proof or cap are for a tree of another height or cap height.
+    fn verify_to_cap(
+        cap: &MerkleCap<Self::Root>,
+        height: u32,
+        cap_height: u32,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error>;
 
     /// Returns the number of security bits
     fn security_level_bits() -> u32;
 }
 
+/// Nodes of a merkle tree at a fixed depth.
+///
+/// Committing to a cap rather than the root lets authentication paths stop
+/// `height` levels early. A cap of height `0` only contains the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleCap<D: Digest>(pub Vec<D>);
+
+impl<D: Digest> From<D> for MerkleCap<D> {
+    fn from(root: D) -> Self {
+        Self(vec![root])
+    }
+}
+
 // TODO: all these merkle tree abstractions are way out of control. need to
 // refactor
 pub trait MerkleTreeConfig: Send + Sync + Sized + 'static {
     type Digest: Digest;
     type Leaf: CanonicalDeserialize + CanonicalSerialize + Clone + Send + Sync + Sized + 'static;
 
-    fn hash_leaves(depth: u32, l0: &Self::Leaf, l1: &Self::Leaf) -> Self::Digest;
+    /// Number of children of each node. Must be a power of two.
+    const ARITY: usize = 2;
+
+    /// Hashes the children of a node at the given depth. The root can have
+    /// fewer than [`Self::ARITY`] children if the number of leaves isn't a
+    /// power of the arity.
+    fn hash_leaves(depth: u32, leaves: &[Self::Leaf]) -> Self::Digest;
 
-    fn hash_nodes(depth: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest;
+    fn hash_nodes(depth: u32, nodes: &[Self::Digest]) -> Self::Digest;
 
     fn security_level_bits() -> u32;
 }
 
 /// Merkle View contains information needed to verify multiple Merkle paths.
 ///
+/// Paths are batched into a single multiproof: nodes shared between queried
+/// leaves, or that can be computed from other queried leaves, are only included
+/// once. Row proofs from [`MatrixMerkleTree`] leave `initial_leaves` empty
+/// since the verifier recomputes them from the opened rows.
+///
 /// Inspired by Starkware's Solidity verifier
 /// <https://etherscan.io/address/0xe9664D230490d5A515ef7Ef30033d8075a8D0E24#code#F24#L1>
 #[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
@@ -77,6 +144,7 @@ pub struct MerkleView<
     pub nodes: Vec<N>,
     pub initial_leaves: Vec<L>,
     pub sibling_leaves: Vec<L>,
+    /// Log2 of the number of leaves in the tree
     pub height: u32,
 }
 
@@ -89,6 +157,10 @@ pub struct MerkleView<
 ///  / \     / \
 /// +   +   +   +  <- leaves
 /// ```
+///
+/// Nodes are stored level by level starting with the root at index `1`. For
+/// binary trees this is the usual heap layout where the children of node `i`
+/// are `2i` and `2i+1`.
 pub struct MerkleTreeImpl<C: MerkleTreeConfig> {
     pub nodes: Vec<C::Digest>,
     pub leaves: Vec<C::Leaf>,
@@ -104,6 +176,12 @@ impl<C: MerkleTreeConfig> Clone for MerkleTreeImpl<C> {
 }
 
 impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
+    /// Fails to compile for trees with an unsupported arity
+    const ARITY_IS_POWER_OF_TWO: () = assert!(
+        C::ARITY >= 2 && C::ARITY.is_power_of_two(),
+        "merkle tree arity must be a power of two"
+    );
+
     /// # Errors
     ///
     /// This function will return an error if:
@@ -111,6 +189,7 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
     /// * the number of leaves is not a power of two
     pub fn new(leaves: Vec<C::Leaf>) -> Result<Self, Error> {
         const MIN_LEAVES: usize = 2;
+        let () = Self::ARITY_IS_POWER_OF_TWO;
 
         let n = leaves.len();
         if n < MIN_LEAVES {
@@ -146,7 +225,19 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         self.nodes[1].clone()
     }
 
-    fn prove(&self, indices: &[usize]) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
+    fn cap(&self, cap_height: u32) -> MerkleCap<C::Digest> {
+        let sizes = level_sizes(self.leaves.len(), C::ARITY);
+        let offsets = level_offsets(&sizes);
+        let depth = (cap_height as usize).min(sizes.len() - 1);
+        let offset = offsets[depth];
+        MerkleCap(self.nodes[offset..offset + sizes[depth]].to_vec())
+    }
+
+    fn prove_to_cap(
+        &self,
+        cap_height: u32,
+        indices: &[usize],
+    ) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
         let num_leaves = self.leaves.len();
         for &i in indices {
             if i >= num_leaves {
@@ -154,48 +245,28 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
             }
         }
 
+        let sizes = level_sizes(num_leaves, C::ARITY);
+        let offsets = level_offsets(&sizes);
+        let leaf_depth = sizes.len();
+        let cap_height = (cap_height as usize).min(leaf_depth - 1);
+
         let mut indices = indices.to_vec();
         indices.sort_unstable();
         indices.dedup();
 
-        let mut initial_leaves = Vec::new();
-        let mut sibling_leaves = Vec::new();
-
         // handle leaves and specify the internal node indices
-        let mut node_queue = VecDeque::new();
-        let mut leaf_queue = VecDeque::from_iter(indices);
-        while let Some(index) = leaf_queue.pop_front() {
-            initial_leaves.push(self.leaves[index].clone());
-            node_queue.push_back((num_leaves + index) >> 1);
-
-            if let Some(&next_index) = leaf_queue.front() {
-                let are_siblings = index ^ 1 == next_index;
-                if are_siblings {
-                    initial_leaves.push(self.leaves[next_index].clone());
-                    leaf_queue.pop_front();
-                    continue;
-                }
-            }
+        let initial_leaves = indices.iter().map(|&i| self.leaves[i].clone()).collect();
+        let arity = num_leaves / sizes[leaf_depth - 1];
+        let (sibling_leaves, mut indices) = collect_siblings(&self.leaves, arity, &indices);
 
-            sibling_leaves.push(self.leaves[index ^ 1].clone());
-        }
-
-        // handle internal nodes
+        // handle internal nodes. the verifier already has the cap
         let mut nodes = Vec::new();
-        while let Some(index) = node_queue.pop_front() {
-            if index > 2 {
-                node_queue.push_back(index >> 1);
-            }
-
-            if let Some(next_index) = node_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    node_queue.pop_front();
-                    continue;
-                }
-            }
-
-            nodes.push(self.nodes[index ^ 1].clone());
+        for depth in (cap_height + 1..leaf_depth).rev() {
+            let level = &self.nodes[offsets[depth]..offsets[depth] + sizes[depth]];
+            let arity = sizes[depth] / sizes[depth - 1];
+            let (siblings, parent_indices) = collect_siblings(level, arity, &indices);
+            nodes.extend(siblings);
+            indices = parent_indices;
         }
 
         Ok(MerkleView {
@@ -206,13 +277,29 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         })
     }
 
-    fn verify(
-        root: &C::Digest,
+    fn verify_to_cap(
+        cap: &MerkleCap<C::Digest>,
+        height: u32,
+        cap_height: u32,
         proof: MerkleView<C::Digest, C::Leaf>,
         indices: &[usize],
     ) -> Result<(), Error> {
-        let height = proof.height;
-        let num_leaves = 1 << height;
+        let () = Self::ARITY_IS_POWER_OF_TWO;
+        if proof.height != height {
+            return Err(Error::InvalidProof);
+        }
+        let num_leaves = match 1usize.checked_shl(height) {
+            Some(n) if n >= 2 => n,
+            _ => return Err(Error::InvalidProof),
+        };
+
+        let sizes = level_sizes(num_leaves, C::ARITY);
+        let leaf_depth = sizes.len();
+        let cap_height = (cap_height as usize).min(leaf_depth - 1);
+        if cap.0.len() != sizes[cap_height] {
+            return Err(Error::InvalidProof);
+        }
+
         for &i in indices {
             if i >= num_leaves {
                 return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
@@ -222,74 +309,141 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         let mut indices = indices.to_vec();
         indices.sort_unstable();
         indices.dedup();
+        if indices.len() != proof.initial_leaves.len() {
+            return Err(Error::InvalidProof);
+        }
 
         // handle leaves and specify the internal node indices
-        let mut node_queue = VecDeque::new();
-        let mut siblings = VecDeque::from_iter(proof.sibling_leaves);
-        let mut leaf_queue = zip(indices, proof.initial_leaves).collect::<VecDeque<_>>();
-        while let Some((index, leaf)) = leaf_queue.pop_front() {
-            let node_index = (num_leaves + index) >> 1;
-
-            if let Some((next_index, next_leaf)) = leaf_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    let running_hash = C::hash_leaves(height - 1, &leaf, next_leaf);
-                    node_queue.push_back((node_index, running_hash));
-                    leaf_queue.pop_front();
-                    continue;
-                }
-            }
-
-            let sibling = siblings.pop_front().unwrap();
-            let running_hash = if index % 2 == 0 {
-                C::hash_leaves(height - 1, &leaf, &sibling)
-            } else {
-                C::hash_leaves(height - 1, &sibling, &leaf)
-            };
-            node_queue.push_back((node_index, running_hash));
+        let mut siblings = proof.sibling_leaves.into_iter();
+        let arity = num_leaves / sizes[leaf_depth - 1];
+        let depth = u32::try_from(leaf_depth - 1).unwrap();
+        let (mut indices, mut hashes) = hash_parents(
+            &indices,
+            proof.initial_leaves,
+            &mut siblings,
+            arity,
+            |leaves| C::hash_leaves(depth, leaves),
+        )?;
+        if siblings.next().is_some() {
+            return Err(Error::InvalidProof);
         }
-        assert!(siblings.is_empty());
 
         // handle internal nodes
-        let mut nodes = VecDeque::from_iter(proof.nodes);
-        while let Some((index, hash)) = node_queue.pop_front() {
-            let depth = index.ilog2();
-
-            if depth == 0 {
-                assert!(node_queue.is_empty());
-                // compare against the root
-                return if *root == hash {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidProof)
-                };
-            }
+        let mut nodes = proof.nodes.into_iter();
+        for depth in (cap_height + 1..leaf_depth).rev() {
+            let arity = sizes[depth] / sizes[depth - 1];
+            let parent_depth = u32::try_from(depth - 1).unwrap();
+            (indices, hashes) = hash_parents(&indices, hashes, &mut nodes, arity, |nodes| {
+                C::hash_nodes(parent_depth, nodes)
+            })?;
+        }
+        if nodes.next().is_some() {
+            return Err(Error::InvalidProof);
+        }
 
-            if let Some((next_index, next_hash)) = node_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    let running_hash = C::hash_nodes(depth - 1, &hash, next_hash);
-                    node_queue.push_back((index >> 1, running_hash));
-                    node_queue.pop_front();
-                    continue;
-                }
+        // compare against the cap
+        if zip(indices, hashes).all(|(i, hash)| cap.0[i] == hash) {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+
+    fn security_level_bits() -> u32 {
+        C::security_level_bits()
+    }
+}
+
+/// Returns the number of nodes at each depth of a tree, starting at the root.
+/// Nodes have `arity` children except for the root which has fewer if the
+/// number of leaves isn't a power of `arity`.
+fn level_sizes(num_leaves: usize, arity: usize) -> Vec<usize> {
+    assert!(arity >= 2 && arity.is_power_of_two());
+    let mut sizes = Vec::new();
+    let mut size = num_leaves;
+    while size > 1 {
+        size /= arity.min(size);
+        sizes.push(size);
+    }
+    sizes.reverse();
+    sizes
+}
+
+/// Returns the index of the first node at each depth
+fn level_offsets(sizes: &[usize]) -> Vec<usize> {
+    sizes
+        .iter()
+        .scan(1, |offset, size| {
+            let level_offset = *offset;
+            *offset += size;
+            Some(level_offset)
+        })
+        .collect()
+}
+
+/// Returns the items needed, in addition to the items at the sorted `indices`,
+/// to hash their parents along with the indices of those parents
+fn collect_siblings<T: Clone>(
+    items: &[T],
+    arity: usize,
+    indices: &[usize],
+) -> (Vec<T>, Vec<usize>) {
+    let mut siblings = Vec::new();
+    let mut parents: Vec<usize> = Vec::new();
+    for &index in indices {
+        let parent = index / arity;
+        if parents.last() == Some(&parent) {
+            continue;
+        }
+
+        parents.push(parent);
+        let start = parent * arity;
+        for (i, item) in (start..).zip(&items[start..start + arity]) {
+            if indices.binary_search(&i).is_err() {
+                siblings.push(item.clone());
             }
+        }
+    }
+    (siblings, parents)
+}
 
-            let sibling = nodes.pop_front().unwrap();
-            let running_hash = if index % 2 == 0 {
-                C::hash_nodes(depth - 1, &hash, &sibling)
-            } else {
-                C::hash_nodes(depth - 1, &sibling, &hash)
+/// Hashes the parents of the values at the sorted `indices`. Any other
+/// children are taken from `siblings`.
+fn hash_parents<T, D>(
+    indices: &[usize],
+    values: Vec<T>,
+    siblings: &mut impl Iterator<Item = T>,
+    arity: usize,
+    hash: impl Fn(&[T]) -> D,
+) -> Result<(Vec<usize>, Vec<D>), Error> {
+    let mut parents = Vec::new();
+    let mut hashes = Vec::new();
+    let mut values = zip(indices, values).peekable();
+    let mut children = Vec::with_capacity(arity);
+    while let Some(&(&index, _)) = values.peek() {
+        let parent = index / arity;
+        for i in parent * arity..(parent + 1) * arity {
+            let child = match values.next_if(|(&j, _)| i == j) {
+                Some((_, value)) => value,
+                None => siblings.next().ok_or(Error::InvalidProof)?,
             };
-            node_queue.push_back((index >> 1, running_hash));
+            children.push(child);
         }
 
-        Ok(())
+        parents.push(parent);
+        hashes.push(hash(&children));
+        children.clear();
     }
+    Ok((parents, hashes))
+}
 
-    fn security_level_bits() -> u32 {
-        C::security_level_bits()
-    }
+/// Commits to the rows of a matrix with salts drawn from `rng`. The prover
+/// only has an rng if the tree is hiding or zero-knowledge is enabled.
+pub(crate) fn commit_rows<T, M: MatrixMerkleTree<T>>(
+    m: &Matrix<T>,
+    rng: Option<&mut impl RngCore>,
+) -> M {
+    rng.map_or_else(|| M::from_matrix(m), |rng| M::from_matrix_with_rng(m, rng))
 }
 
 /// Merkle tree that supports proving/verifying rows of a matrix
@@ -297,25 +451,51 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
 /// Inspired by plonky3's MMCS
 /// <https://github.com/Plonky3/Plonky3/blob/main/commit/src/mmcs.rs>
 pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
+    /// Whether leaves are salted so commitments hide the unopened rows
+    const HIDING: bool = false;
+
     fn from_matrix(m: &Matrix<T>) -> Self;
 
-    fn prove_rows(&self, row_ids: &[usize]) -> Result<Self::Proof, Error> {
-        self.prove(row_ids)
+    /// Commits to the rows of a matrix. Hiding trees draw their salts from
+    /// `rng` so commitments can be reproduced with a seeded generator.
+    fn from_matrix_with_rng(m: &Matrix<T>, rng: &mut impl RngCore) -> Self {
+        let _ = rng;
+        Self::from_matrix(m)
+    }
+
+    /// Generates a proof for the given rows with paths that end at the cap of
+    /// the given height
+    fn prove_rows(&self, cap_height: u32, row_ids: &[usize]) -> Result<Self::Proof, Error> {
+        self.prove_to_cap(cap_height, row_ids)
     }
 
+    /// Verifies rows of a matrix with `2^height` rows against the cap of
+    /// height `cap_height`
     fn verify_rows(
-        root: &Self::Root,
+        cap: &MerkleCap<Self::Root>,
+        height: u32,
+        cap_height: u32,
         row_ids: &[usize],
         rows: &[impl AsRef<[T]>],
         proof: Self::Proof,
     ) -> Result<(), Error>;
 }
 
-pub struct MatrixMerkleTreeImpl<H: HashFn> {
-    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H>>,
+/// Commits to the rows of a matrix using a tree where each node has `ARITY`
+/// children
+pub struct MatrixMerkleTreeImpl<H: HashFn, const ARITY: usize = 2> {
+    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H, ARITY>>,
 }
 
-impl<H: HashFn> Clone for MatrixMerkleTreeImpl<H> {
+pub type Sha256MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+
+pub type Blake3MerkleTree = MatrixMerkleTreeImpl<Blake3HashFn>;
+
+pub type Keccak256MerkleTree = MatrixMerkleTreeImpl<Keccak256HashFn>;
+
+pub type Rpo256MerkleTree = MatrixMerkleTreeImpl<Rpo256HashFn>;
+
+impl<H: HashFn, const ARITY: usize> Clone for MatrixMerkleTreeImpl<H, ARITY> {
     fn clone(&self) -> Self {
         Self {
             merkle_tree: self.merkle_tree.clone(),
@@ -323,7 +503,7 @@ impl<H: HashFn> Clone for MatrixMerkleTreeImpl<H> {
     }
 }
 
-impl<H: HashFn> MatrixMerkleTreeImpl<H> {
+impl<H: HashFn, const ARITY: usize> MatrixMerkleTreeImpl<H, ARITY> {
     fn new(leaves: Vec<H::Digest>) -> Result<Self, Error> {
         assert!(leaves.len().is_power_of_two());
         Ok(Self {
@@ -332,7 +512,7 @@ impl<H: HashFn> MatrixMerkleTreeImpl<H> {
     }
 }
 
-impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
+impl<H: HashFn, const ARITY: usize> MerkleTree for MatrixMerkleTreeImpl<H, ARITY> {
     type Proof = MerkleView<H::Digest, H::Digest>;
     type Root = H::Digest;
 
@@ -340,12 +520,24 @@ impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
         self.merkle_tree.root()
     }
 
-    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
-        self.merkle_tree.prove(indices)
+    fn cap(&self, cap_height: u32) -> MerkleCap<Self::Root> {
+        self.merkle_tree.cap(cap_height)
+    }
+
+    fn prove_to_cap(&self, cap_height: u32, indices: &[usize]) -> Result<Self::Proof, Error> {
+        self.merkle_tree.prove_to_cap(cap_height, indices)
     }
 
-    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error> {
-        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, proof, indices)
+    fn verify_to_cap(
+        cap: &MerkleCap<Self::Root>,
+        height: u32,
+        cap_height: u32,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        MerkleTreeImpl::<HashedLeafConfig<H, ARITY>>::verify_to_cap(
+            cap, height, cap_height, proof, indices,
+        )
     }
 
     fn security_level_bits() -> u32 {
@@ -353,18 +545,27 @@ impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
     }
 }
 
-impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
-    for MatrixMerkleTreeImpl<H>
+impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static, const ARITY: usize> MatrixMerkleTree<F>
+    for MatrixMerkleTreeImpl<H, ARITY>
 {
     fn from_matrix(m: &Matrix<F>) -> Self {
         Self::new(hash_rows::<F, H>(m)).unwrap()
     }
 
+    fn prove_rows(&self, cap_height: u32, row_ids: &[usize]) -> Result<Self::Proof, Error> {
+        // the verifier recomputes the leaves from the rows
+        let mut proof = self.prove_to_cap(cap_height, row_ids)?;
+        proof.initial_leaves.clear();
+        Ok(proof)
+    }
+
     fn verify_rows(
-        root: &Self::Root,
+        cap: &MerkleCap<Self::Root>,
+        height: u32,
+        cap_height: u32,
         row_ids: &[usize],
         rows: &[impl AsRef<[F]>],
-        proof: Self::Proof,
+        mut proof: Self::Proof,
     ) -> Result<(), Error> {
         // remove duplicates and sort
         let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
@@ -372,36 +573,171 @@ impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
         instances.dedup_by(|(a, _), (b, _)| a == b);
 
         let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
-        let initial_leaves = rows
+        if !proof.initial_leaves.is_empty() {
+            return Err(Error::InvalidProof);
+        }
+
+        proof.initial_leaves = rows
             .iter()
             .map(|r| H::hash_elements(r.as_ref().iter().copied()))
+            .collect();
+        Self::verify_to_cap(cap, height, cap_height, proof, &indices)
+    }
+}
+
+/// Hiding variant of [`MatrixMerkleTreeImpl`].
+///
+/// Each leaf is the hash of a row merged with a random per-leaf salt so low
+/// entropy rows can't be recovered by brute force from the root. Salts are
+/// only revealed for opened rows.
+pub struct SaltedMatrixMerkleTreeImpl<H: HashFn, const ARITY: usize = 2> {
+    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H, ARITY>>,
+    salts: Vec<H::Digest>,
+}
+
+impl<H: HashFn, const ARITY: usize> Clone for SaltedMatrixMerkleTreeImpl<H, ARITY> {
+    fn clone(&self) -> Self {
+        Self {
+            merkle_tree: self.merkle_tree.clone(),
+            salts: self.salts.clone(),
+        }
+    }
+}
+
+/// Proof of rows of a [`SaltedMatrixMerkleTreeImpl`]
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+pub struct SaltedMerkleView<D: Digest> {
+    pub view: MerkleView<D, D>,
+    /// Salts of the opened leaves in ascending leaf order
+    pub salts: Vec<D>,
+}
+
+impl<H: HashFn, const ARITY: usize> SaltedMatrixMerkleTreeImpl<H, ARITY> {
+    fn new<F: Field>(m: &Matrix<F>, rng: &mut impl RngCore) -> Self
+    where
+        H: ElementHashFn<F>,
+    {
+        let row_hashes = hash_rows::<F, H>(m);
+        let salts = (0..row_hashes.len())
+            .map(|_| {
+                let mut bytes = [0; 32];
+                rng.fill_bytes(&mut bytes);
+                H::hash_chunks([&bytes[..]])
+            })
             .collect::<Vec<_>>();
-        if proof.initial_leaves == initial_leaves {
-            Self::verify(root, proof, &indices)
-        } else {
-            Err(Error::InvalidProof)
+        let leaves = zip(&row_hashes, &salts)
+            .map(|(row_hash, salt)| H::merge(row_hash, salt))
+            .collect();
+        Self {
+            merkle_tree: MerkleTreeImpl::new(leaves).unwrap(),
+            salts,
+        }
+    }
+}
+
+impl<H: HashFn, const ARITY: usize> MerkleTree for SaltedMatrixMerkleTreeImpl<H, ARITY> {
+    type Proof = SaltedMerkleView<H::Digest>;
+    type Root = H::Digest;
+
+    fn root(&self) -> Self::Root {
+        self.merkle_tree.root()
+    }
+
+    fn cap(&self, cap_height: u32) -> MerkleCap<Self::Root> {
+        self.merkle_tree.cap(cap_height)
+    }
+
+    fn prove_to_cap(&self, cap_height: u32, indices: &[usize]) -> Result<Self::Proof, Error> {
+        let view = self.merkle_tree.prove_to_cap(cap_height, indices)?;
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let salts = indices.iter().map(|&i| self.salts[i].clone()).collect();
+        Ok(SaltedMerkleView { view, salts })
+    }
+
+    fn verify_to_cap(
+        cap: &MerkleCap<Self::Root>,
+        height: u32,
+        cap_height: u32,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        MerkleTreeImpl::<HashedLeafConfig<H, ARITY>>::verify_to_cap(
+            cap, height, cap_height, proof.view, indices,
+        )
+    }
+
+    fn security_level_bits() -> u32 {
+        H::COLLISION_RESISTANCE
+    }
+}
+
+impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static, const ARITY: usize> MatrixMerkleTree<F>
+    for SaltedMatrixMerkleTreeImpl<H, ARITY>
+{
+    const HIDING: bool = true;
+
+    fn from_matrix(m: &Matrix<F>) -> Self {
+        Self::new(m, &mut ChaCha20Rng::from_entropy())
+    }
+
+    fn from_matrix_with_rng(m: &Matrix<F>, rng: &mut impl RngCore) -> Self {
+        Self::new(m, rng)
+    }
+
+    fn prove_rows(&self, cap_height: u32, row_ids: &[usize]) -> Result<Self::Proof, Error> {
+        // the verifier recomputes the leaves from the rows and salts
+        let mut proof = self.prove_to_cap(cap_height, row_ids)?;
+        proof.view.initial_leaves.clear();
+        Ok(proof)
+    }
+
+    fn verify_rows(
+        cap: &MerkleCap<Self::Root>,
+        height: u32,
+        cap_height: u32,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        mut proof: Self::Proof,
+    ) -> Result<(), Error> {
+        // remove duplicates and sort
+        let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
+        instances.sort_unstable_by_key(|(i, _)| **i);
+        instances.dedup_by(|(a, _), (b, _)| a == b);
+
+        let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
+        if proof.salts.len() != rows.len() || !proof.view.initial_leaves.is_empty() {
+            return Err(Error::InvalidProof);
         }
+
+        proof.view.initial_leaves = zip(&rows, &proof.salts)
+            .map(|(row, salt)| H::merge(&H::hash_elements(row.as_ref().iter().copied()), salt))
+            .collect();
+        Self::verify_to_cap(cap, height, cap_height, proof, &indices)
     }
 }
 
-pub struct HashedLeafConfig<H: HashFn>(PhantomData<H>);
+pub struct HashedLeafConfig<H: HashFn, const ARITY: usize = 2>(PhantomData<H>);
 
-impl<H: HashFn> Clone for HashedLeafConfig<H> {
+impl<H: HashFn, const ARITY: usize> Clone for HashedLeafConfig<H, ARITY> {
     fn clone(&self) -> Self {
         Self(PhantomData)
     }
 }
 
-impl<H: HashFn> MerkleTreeConfig for HashedLeafConfig<H> {
+impl<H: HashFn, const ARITY: usize> MerkleTreeConfig for HashedLeafConfig<H, ARITY> {
     type Digest = H::Digest;
     type Leaf = H::Digest;
 
-    fn hash_leaves(_: u32, l0: &H::Digest, l1: &H::Digest) -> H::Digest {
-        H::merge(l0, l1)
+    const ARITY: usize = ARITY;
+
+    fn hash_leaves(_: u32, leaves: &[H::Digest]) -> H::Digest {
+        H::merge_many(leaves)
     }
 
-    fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-        H::merge(n0, n1)
+    fn hash_nodes(_: u32, nodes: &[Self::Digest]) -> Self::Digest {
+        H::merge_many(nodes)
     }
 
     fn security_level_bits() -> u32 {
@@ -435,73 +771,29 @@ pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Di
     row_hashes
 }
 
-#[cfg(feature = "parallel")]
 pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Digest> {
-    let n = leaves.len();
-    let num_subtrees = core::cmp::min(rayon::current_num_threads().next_power_of_two(), n / 2);
-    let mut nodes = vec![C::Digest::default(); n];
-
-    // code adapted from winterfell
-    // https://github.com/facebook/winterfell
-    rayon::scope(|s| {
-        for i in 0..num_subtrees {
-            let nodes = unsafe { &mut *core::ptr::addr_of_mut!(nodes[..]) };
-            s.spawn(move |_| {
-                // generate first layer of nodes from leaf nodes
-                let batch_size = n / num_subtrees;
-                let leaf_offset = batch_size * i;
-                let depth = (n / 2).ilog2();
-                for j in (0..batch_size).step_by(2) {
-                    let lhs = &leaves[leaf_offset + j];
-                    let rhs = &leaves[leaf_offset + j + 1];
-                    nodes[(n + leaf_offset + j) / 2] = C::hash_leaves(depth, lhs, rhs);
-                }
-
-                // generate remaining nodes
-                let mut batch_size = n / num_subtrees / 4;
-                let mut start_idx = n / 4 + batch_size * i;
-                let mut depth = (n / 4).ilog2();
-                while start_idx >= num_subtrees {
-                    for k in (start_idx..(start_idx + batch_size)).rev() {
-                        nodes[k] = C::hash_nodes(depth, &nodes[k * 2], &nodes[k * 2 + 1]);
-                    }
-                    start_idx /= 2;
-                    batch_size /= 2;
-                    depth -= 1;
-                }
-            });
-        }
-    });
-
-    // finish the tip of the tree
-    for i in (1..num_subtrees).rev() {
-        let layer = i.ilog2();
-        nodes[i] = C::hash_nodes(layer, &nodes[i * 2], &nodes[i * 2 + 1]);
-    }
-
-    nodes
-}
-
-#[cfg(not(feature = "parallel"))]
-pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Digest> {
-    let n = leaves.len();
-    assert!(n.is_power_of_two());
-    let mut nodes = vec![C::Digest::default(); n];
+    let sizes = level_sizes(leaves.len(), C::ARITY);
+    let offsets = level_offsets(&sizes);
+    let leaf_depth = sizes.len();
+    let mut nodes = vec![C::Digest::default(); offsets[leaf_depth - 1] + sizes[leaf_depth - 1]];
 
     // generate first layer of nodes from leaf nodes
-    let depth = (n / 2).ilog2();
-    for i in 0..n / 2 {
-        nodes[n / 2 + i] = C::hash_leaves(depth, &leaves[i * 2], &leaves[i * 2 + 1]);
-    }
+    let arity = leaves.len() / sizes[leaf_depth - 1];
+    let depth = u32::try_from(leaf_depth - 1).unwrap();
+    ark_std::cfg_iter_mut!(nodes[offsets[leaf_depth - 1]..])
+        .zip(ark_std::cfg_chunks!(leaves, arity))
+        .for_each(|(node, children)| *node = C::hash_leaves(depth, children));
 
     // generate remaining nodes
-    let num_remaining_layers = (n / 2).ilog2();
-    for depth in (0..num_remaining_layers).rev() {
-        let size = 1 << depth;
-        let offset = size;
-        for i in offset..offset + size {
-            nodes[i] = C::hash_nodes(depth, &nodes[i * 2], &nodes[i * 2 + 1]);
-        }
+    for depth in (0..leaf_depth - 1).rev() {
+        let (upper, lower) = nodes.split_at_mut(offsets[depth + 1]);
+        let children = &lower[..sizes[depth + 1]];
+        let arity = sizes[depth + 1] / sizes[depth];
+        let level = &mut upper[offsets[depth]..];
+        let depth = u32::try_from(depth).unwrap();
+        ark_std::cfg_iter_mut!(level)
+            .zip(ark_std::cfg_chunks!(children, arity))
+            .for_each(|(node, children)| *node = C::hash_nodes(depth, children));
     }
 
     nodes
@@ -515,6 +807,7 @@ mod tests {
     use super::MerkleTree;
     use super::MerkleTreeConfig;
     use super::MerkleTreeImpl;
+    use super::SaltedMatrixMerkleTreeImpl;
     use crate::hash::HashFn;
     use crate::hash::Sha256HashFn;
     use crate::utils::GpuAllocator;
@@ -522,6 +815,8 @@ mod tests {
     use crate::Matrix;
     use ark_ff::MontFp as Fp;
     use digest::Digest;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
     use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
     use sha2::Sha256;
 
@@ -534,7 +829,7 @@ mod tests {
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, 3, proof, &[i])
     }
 
     #[test]
@@ -542,13 +837,172 @@ mod tests {
         let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
         let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
         let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
-        let commitment = tree.root();
+        let commitment = tree.cap(0);
         let row_ids = [0, 1, 2, 3];
         let rows = row_ids.map(|i| [column[i]]);
 
-        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 0, &row_ids)?;
+
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, 2, 0, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn row_proofs_share_nodes_and_omit_leaves() -> Result<(), Error> {
+        let column = (0..16u64).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let commitment = tree.cap(0);
+        let row_ids = [2, 0, 3, 1];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 0, &row_ids)?;
+
+        assert!(proof.initial_leaves.is_empty());
+        assert!(proof.sibling_leaves.is_empty());
+        // the four rows share a subtree so only its two uncles are needed
+        assert_eq!(proof.nodes.len(), 2);
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, 4, 0, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn prove_rows_to_cap() -> Result<(), Error> {
+        let column = (0..16u64).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let cap = tree.cap(2);
+        let row_ids = [5, 12];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 2, &row_ids)?;
+
+        assert_eq!(cap.0.len(), 4);
+        // cap heights are clamped to exclude the leaves
+        assert_eq!(tree.cap(10).0.len(), 8);
+        // each path stops one node below the cap
+        assert_eq!(proof.nodes.len(), 2);
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&cap, 4, 2, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn verify_rows_rejects_wrong_heights() -> Result<(), Error> {
+        type Tree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        let column = (0..16u64).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_matrix(&matrix);
+        let row_ids = [5, 12];
+        let rows = row_ids.map(|i| [column[i]]);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 2, &row_ids)?;
+
+        // the tree has 2^4 rows and a cap of height 2
+        Tree::verify_rows(&tree.cap(2), 4, 2, &row_ids, &rows, proof.clone())?;
+        let wrong_height = Tree::verify_rows(&tree.cap(2), 5, 2, &row_ids, &rows, proof.clone());
+        let wrong_cap = Tree::verify_rows(&tree.cap(1), 4, 1, &row_ids, &rows, proof.clone());
+        let wrong_cap_size = Tree::verify_rows(&tree.cap(1), 4, 2, &row_ids, &rows, proof);
+        assert!(matches!(wrong_height, Err(Error::InvalidProof)));
+        assert!(matches!(wrong_cap, Err(Error::InvalidProof)));
+        assert!(matches!(wrong_cap_size, Err(Error::InvalidProof)));
+        Ok(())
+    }
+
+    #[test]
+    fn prove_rows_with_higher_arity() -> Result<(), Error> {
+        // 32 leaves isn't a power of 4 so the root only has two children
+        let column = (0..32u64).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn, 4>::from_matrix(&matrix);
+        let row_ids = [1, 2, 7];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 0, &row_ids)?;
+        let cap_proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 1, &row_ids)?;
+
+        assert_eq!(tree.cap(1).0.len(), 2);
+        assert_eq!(tree.cap(2).0.len(), 8);
+        // leaves 1 and 2 share a parent which shares a parent with that of leaf 7
+        assert_eq!(proof.sibling_leaves.len(), 2 + 3);
+        assert_eq!(proof.nodes.len(), 2 + 1);
+        assert_eq!(cap_proof.nodes.len(), 2);
+        MatrixMerkleTreeImpl::<Sha256HashFn, 4>::verify_rows(
+            &tree.cap(0),
+            5,
+            0,
+            &row_ids,
+            &rows,
+            proof,
+        )?;
+        MatrixMerkleTreeImpl::<Sha256HashFn, 4>::verify_rows(
+            &tree.cap(1),
+            5,
+            1,
+            &row_ids,
+            &rows,
+            cap_proof,
+        )
+    }
+
+    #[test]
+    fn verify_fails_with_missing_node() {
+        let leaves = (0..1 << 4).collect::<Vec<u32>>();
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves).unwrap();
+        let commitment = tree.root();
+        let i = 13;
+
+        let mut proof = tree.prove(&[i]).unwrap();
+        assert_eq!(proof.nodes.len(), 3);
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, 4, proof.clone(), &[i]).unwrap();
+        proof.nodes.pop();
+
+        let result = MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, 4, proof, &[i]);
+        assert!(matches!(result, Err(Error::InvalidProof)));
+    }
+
+    #[test]
+    fn prove_salted_rows() -> Result<(), Error> {
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let tree =
+            SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix_with_rng(&matrix, &mut rng);
+        let commitment = tree.cap(0);
+        let row_ids = [3, 1];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 0, &row_ids)?;
+
+        assert_eq!(proof.salts.len(), row_ids.len());
+        SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &commitment,
+            2,
+            0,
+            &row_ids,
+            &rows,
+            proof,
+        )
+    }
+
+    #[test]
+    fn salted_rows_fail_with_wrong_salt() {
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let tree =
+            SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix_with_rng(&matrix, &mut rng);
+        let commitment = tree.cap(0);
+        let row_ids = [2];
+        let rows = row_ids.map(|i| [column[i]]);
 
-        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, &row_ids, &rows, proof)
+        let mut proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, 0, &row_ids).unwrap();
+        proof.salts[0] = Sha256HashFn::hash_chunks([&b"salt"[..]]);
+
+        let result = SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &commitment,
+            2,
+            0,
+            &row_ids,
+            &rows,
+            proof,
+        );
+        assert!(matches!(result, Err(Error::InvalidProof)));
     }
 
     #[test]
@@ -565,7 +1019,7 @@ mod tests {
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<HashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<HashedLeafConfig>::verify(&commitment, 3, proof, &[i])
     }
 
     #[test]
@@ -577,7 +1031,7 @@ mod tests {
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, 10, proof, &[i])
     }
 
     struct HashedLeafConfig;
@@ -586,16 +1040,12 @@ mod tests {
         type Digest = SerdeOutput<Sha256>;
         type Leaf = SerdeOutput<Sha256>;
 
-        fn hash_leaves(
-            _: u32,
-            l0: &SerdeOutput<Sha256>,
-            l1: &SerdeOutput<Sha256>,
-        ) -> SerdeOutput<Sha256> {
-            Sha256HashFn::merge(l0, l1)
+        fn hash_leaves(_: u32, leaves: &[SerdeOutput<Sha256>]) -> SerdeOutput<Sha256> {
+            Sha256HashFn::merge_many(leaves)
         }
 
-        fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-            Sha256HashFn::merge(n0, n1)
+        fn hash_nodes(_: u32, nodes: &[Self::Digest]) -> Self::Digest {
+            Sha256HashFn::merge_many(nodes)
         }
 
         fn security_level_bits() -> u32 {
@@ -609,14 +1059,13 @@ mod tests {
         type Digest = SerdeOutput<Sha256>;
         type Leaf = u32;
 
-        fn hash_leaves(_: u32, l0: &u32, l1: &u32) -> SerdeOutput<Sha256> {
-            let l0_bytes = l0.to_be_bytes();
-            let l1_bytes = l1.to_be_bytes();
-            Sha256HashFn::hash_chunks([&l0_bytes[..], &l1_bytes[..]])
+        fn hash_leaves(_: u32, leaves: &[u32]) -> SerdeOutput<Sha256> {
+            let bytes = leaves.iter().map(|l| l.to_be_bytes()).collect::<Vec<_>>();
+            Sha256HashFn::hash_chunks(bytes.iter().map(|b| &b[..]))
         }
 
-        fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-            Sha256HashFn::merge(n0, n1)
+        fn hash_nodes(_: u32, nodes: &[Self::Digest]) -> Self::Digest {
+            Sha256HashFn::merge_many(nodes)
         }
 
         fn security_level_bits() -> u32 {
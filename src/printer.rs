@@ -0,0 +1,312 @@
+//! Renders constraint expressions as infix algebra or LaTeX
+
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Names;
+use crate::constraints::VirtualColumn;
+use crate::expression::Expr;
+use crate::expression::P;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use core::fmt::Write;
+use std::sync::Arc;
+
+/// Output format of an [`ExprPrinter`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Notation {
+    /// Infix algebra e.g. `col3[+1] - col6 * col7`
+    #[default]
+    Plain,
+    /// LaTeX math mode for audit documents
+    Latex,
+}
+
+/// Prints an expression in infix notation. Sub-expressions that are shared
+/// (see [`Expr::reuse_shared_nodes`]) are printed once as let-bindings.
+pub struct ExprPrinter<'a, T: 'static> {
+    expr: &'a Expr<AlgebraicItem<T>>,
+    names: Option<&'a Names>,
+    notation: Notation,
+}
+
+impl<'a, T> ExprPrinter<'a, T> {
+    pub const fn new(expr: &'a Expr<AlgebraicItem<T>>) -> Self {
+        Self {
+            expr,
+            names: None,
+            notation: Notation::Plain,
+        }
+    }
+
+    /// Prints columns, challenges and hints by name where they have one
+    #[must_use]
+    pub const fn with_names(mut self, names: &'a Names) -> Self {
+        self.names = Some(names);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+}
+
+impl<T: Display + Ord> Display for ExprPrinter<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut num_parents = BTreeMap::new();
+        count_parents(self.expr, &mut num_parents);
+        let mut printer = Printer {
+            names: self.names,
+            notation: self.notation,
+            num_parents,
+            bindings: BTreeMap::new(),
+            periodic_columns: BTreeMap::new(),
+            lines: Vec::new(),
+        };
+        printer.bind_shared_nodes(self.expr);
+        let (root, _) = printer.render(self.expr);
+        for line in &printer.lines {
+            writeln!(f, "{line}")?;
+        }
+        f.write_str(&root)
+    }
+}
+
+impl<T: Display + Ord> Display for Expr<AlgebraicItem<T>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        ExprPrinter::new(self).fmt(f)
+    }
+}
+
+impl<T: Display + Ord> Display for Constraint<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if let Some(name) = &self.metadata().name {
+            write!(f, "{name}: ")?;
+        }
+        ExprPrinter::new(self).fmt(f)
+    }
+}
+
+/// Binding strength of an operator. Operands that bind weaker are wrapped in
+/// parentheses.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Add,
+    Mul,
+    Neg,
+    Pow,
+    Atom,
+}
+
+type NodeId<T> = *const Expr<AlgebraicItem<T>>;
+
+/// Counts the number of parents of each node in the expression DAG
+fn count_parents<T>(expr: &Expr<AlgebraicItem<T>>, num_parents: &mut BTreeMap<NodeId<T>, usize>) {
+    for child in children(expr) {
+        let count = num_parents.entry(Arc::as_ptr(child)).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            count_parents(child, num_parents);
+        }
+    }
+}
+
+fn children<T>(expr: &Expr<T>) -> Vec<&P<Expr<T>>> {
+    match expr {
+        Expr::Leaf(_) => vec![],
+        Expr::Neg(a) | Expr::Pow(a, _) => vec![a],
+        Expr::Add(a, b) | Expr::Mul(a, b) | Expr::Div(a, b) => vec![a, b],
+    }
+}
+
+struct Printer<'a, T: 'static> {
+    names: Option<&'a Names>,
+    notation: Notation,
+    num_parents: BTreeMap<NodeId<T>, usize>,
+    /// Names of shared nodes that have been printed as let-bindings
+    bindings: BTreeMap<NodeId<T>, String>,
+    /// Periodic columns are numbered in order of appearance
+    periodic_columns: BTreeMap<(*const T, usize), usize>,
+    lines: Vec<String>,
+}
+
+impl<T: Display + Ord> Printer<'_, T> {
+    /// Visits nodes bottom up and creates a let-binding for each internal node
+    /// with more than one parent
+    fn bind_shared_nodes(&mut self, expr: &Expr<AlgebraicItem<T>>) {
+        for child in children(expr) {
+            let id = Arc::as_ptr(child);
+            if self.bindings.contains_key(&id) {
+                continue;
+            }
+            self.bind_shared_nodes(child);
+            if self.num_parents[&id] > 1 && !matches!(**child, Expr::Leaf(_)) {
+                let (value, _) = self.render(child);
+                let name = match self.notation {
+                    Notation::Plain => format!("t{}", self.bindings.len()),
+                    Notation::Latex => format!("t_{{{}}}", self.bindings.len()),
+                };
+                self.lines.push(match self.notation {
+                    Notation::Plain => format!("let {name} = {value};"),
+                    Notation::Latex => format!("{name} = {value} \\\\"),
+                });
+                self.bindings.insert(id, name);
+            }
+        }
+    }
+
+    fn render_child(&mut self, child: &P<Expr<AlgebraicItem<T>>>) -> (String, Precedence) {
+        // can't use if_let_else because it causes borrow errors
+        #[allow(clippy::option_if_let_else)]
+        match self.bindings.get(&Arc::as_ptr(child)) {
+            Some(name) => (name.clone(), Precedence::Atom),
+            None => self.render(child),
+        }
+    }
+
+    /// Renders an operand and wraps it in parentheses if it binds weaker than
+    /// `min_precedence`
+    fn render_operand(
+        &mut self,
+        child: &P<Expr<AlgebraicItem<T>>>,
+        min_precedence: Precedence,
+    ) -> String {
+        let (value, precedence) = self.render_child(child);
+        if precedence >= min_precedence {
+            value
+        } else if self.notation == Notation::Latex {
+            format!("\\left({value}\\right)")
+        } else {
+            format!("({value})")
+        }
+    }
+
+    fn render(&mut self, expr: &Expr<AlgebraicItem<T>>) -> (String, Precedence) {
+        let latex = self.notation == Notation::Latex;
+        match expr {
+            Expr::Leaf(item) => (self.render_item(item), Precedence::Atom),
+            Expr::Neg(a) => {
+                let a = self.render_operand(a, Precedence::Neg);
+                (format!("-{a}"), Precedence::Neg)
+            }
+            Expr::Add(a, b) => {
+                let a = self.render_operand(a, Precedence::Add);
+                // subtraction is represented as the addition of a negation
+
+                let is_shared = self.bindings.contains_key(&Arc::as_ptr(b));
+                let res = match &**b {
+                    Expr::Neg(c) if !is_shared => {
+                        let c = self.render_operand(c, Precedence::Mul);
+                        format!("{a} - {c}")
+                    }
+                    _ => {
+                        let b = self.render_operand(b, Precedence::Add);
+                        format!("{a} + {b}")
+                    }
+                };
+                (res, Precedence::Add)
+            }
+            Expr::Mul(a, b) => {
+                let a = self.render_operand(a, Precedence::Mul);
+                let b = self.render_operand(b, Precedence::Mul);
+                let op = if latex { " \\cdot " } else { " * " };
+                (format!("{a}{op}{b}"), Precedence::Mul)
+            }
+            Expr::Div(a, b) if latex => {
+                let (a, _) = self.render_child(a);
+                let (b, _) = self.render_child(b);
+                (format!("\\frac{{{a}}}{{{b}}}"), Precedence::Atom)
+            }
+            Expr::Div(a, b) => {
+                let a = self.render_operand(a, Precedence::Mul);
+                let b = self.render_operand(b, Precedence::Neg);
+                (format!("{a} / {b}"), Precedence::Mul)
+            }
+            Expr::Pow(a, e) => {
+                let a = self.render_operand(a, Precedence::Atom);
+                let res = if latex {
+                    format!("{{{a}}}^{{{e}}}")
+                } else {
+                    format!("{a}^{e}")
+                };
+                (res, Precedence::Pow)
+            }
+        }
+    }
+
+    fn render_item(&mut self, item: &AlgebraicItem<T>) -> String {
+        let latex = self.notation == Notation::Latex;
+        let names = self.names;
+        match item {
+            AlgebraicItem::X => "x".into(),
+            AlgebraicItem::Constant(c) => c.to_string(),
+            &AlgebraicItem::Challenge(round, i) => {
+                match names.and_then(|n| n.challenge(round, i)) {
+                    Some(name) => self.render_name(name),
+                    // challenges of the first round are rendered without their round
+                    None if latex && round == 0 => format!("\\alpha_{{{i}}}"),
+                    None if latex => format!("\\alpha^{{({round})}}_{{{i}}}"),
+                    None if round == 0 => format!("challenge{i}"),
+                    None => format!("challenge{round}_{i}"),
+                }
+            }
+            &AlgebraicItem::Hint(i) => match names.and_then(|n| n.hint(i)) {
+                Some(name) => self.render_name(name),
+                None if latex => format!("h_{{{i}}}"),
+                None => format!("hint{i}"),
+            },
+            AlgebraicItem::Periodic(col) => {
+                let num_periodic_columns = self.periodic_columns.len();
+                let i = *self
+                    .periodic_columns
+                    .entry((col.coeffs().as_ptr(), col.interval_size()))
+                    .or_insert(num_periodic_columns);
+                if latex {
+                    format!("p_{{{i}}}")
+                } else {
+                    format!("periodic{i}")
+                }
+            }
+            &AlgebraicItem::Trace(col, offset) => {
+                let virtual_column = names.and_then(|n| n.virtual_column(col, offset));
+                let (mut res, offset) = match virtual_column {
+                    Some((column, offset)) => (self.render_virtual_column(&column), offset),
+                    None => match names.and_then(|n| n.column(col)) {
+                        Some(name) => (self.render_name(name), offset),
+                        None if latex => (format!("\\mathrm{{col}}_{{{col}}}"), offset),
+                        None => (format!("col{col}"), offset),
+                    },
+                };
+                if offset != 0 {
+                    write!(res, "[{offset:+}]").unwrap();
+                }
+                res
+            }
+        }
+    }
+
+    /// Renders a virtual column by name or as the rows of the physical column
+    /// it reads e.g. `col0[1::4]`
+    fn render_virtual_column(&self, column: &VirtualColumn) -> String {
+        if let Some(name) = column.name() {
+            return self.render_name(name);
+        }
+        let (col, first_row, step) = (column.index(), column.first_row(), column.step());
+        match self.notation {
+            Notation::Plain => format!("col{col}[{first_row}::{step}]"),
+            Notation::Latex => format!("\\mathrm{{col}}_{{{col}}}[{first_row}::{step}]"),
+        }
+    }
+
+    fn render_name(&self, name: &str) -> String {
+        match self.notation {
+            Notation::Plain => name.into(),
+            Notation::Latex => format!("\\mathrm{{{}}}", name.replace('_', "\\_")),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::fri::FriProof;
+use crate::merkle::MerkleCap;
 use crate::merkle::MerkleTree;
 use crate::random::PublicCoin;
 use crate::stark::Stark;
@@ -13,9 +14,10 @@ use ark_serialize::Valid;
 pub struct Proof<C: Stark> {
     pub options: ProofOptions,
     pub trace_len: usize,
-    pub base_trace_commitment: C::Digest,
-    pub extension_trace_commitment: Option<C::Digest>,
-    pub composition_trace_commitment: C::Digest,
+    pub base_trace_commitment: MerkleCap<C::Digest>,
+    /// Commitments to each extension trace segment
+    pub extension_trace_commitments: Vec<MerkleCap<C::Digest>>,
+    pub composition_trace_commitment: MerkleCap<C::Digest>,
     pub fri_proof: FriProof<C::Fq, C::Digest, C::MerkleTree>,
     pub pow_nonce: u64,
     pub trace_queries: Queries<C>,
@@ -29,7 +31,7 @@ impl<C: Stark> Clone for Proof<C> {
             options: self.options,
             trace_len: self.trace_len,
             base_trace_commitment: self.base_trace_commitment.clone(),
-            extension_trace_commitment: self.extension_trace_commitment.clone(),
+            extension_trace_commitments: self.extension_trace_commitments.clone(),
             composition_trace_commitment: self.composition_trace_commitment.clone(),
             fri_proof: self.fri_proof.clone(),
             pow_nonce: self.pow_nonce,
@@ -50,7 +52,7 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
         self.trace_len.serialize_with_mode(&mut writer, compress)?;
         self.base_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
-        self.extension_trace_commitment
+        self.extension_trace_commitments
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
@@ -69,7 +71,7 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
         self.options.serialized_size(compress)
             + self.trace_len.serialized_size(compress)
             + self.base_trace_commitment.serialized_size(compress)
-            + self.extension_trace_commitment.serialized_size(compress)
+            + self.extension_trace_commitments.serialized_size(compress)
             + self.composition_trace_commitment.serialized_size(compress)
             + self.fri_proof.serialized_size(compress)
             + self.pow_nonce.serialized_size(compress)
@@ -96,7 +98,7 @@ impl<C: Stark> CanonicalDeserialize for Proof<C> {
             options: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             trace_len: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             base_trace_commitment: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            extension_trace_commitment: <_>::deserialize_with_mode(
+            extension_trace_commitments: <_>::deserialize_with_mode(
                 &mut reader,
                 compress,
                 validate,
@@ -132,7 +134,7 @@ impl<C: Stark> Proof<C> {
 
         let fri_query_security = {
             let grinding_factor = u32::from(self.options.grinding_factor);
-            let security_per_query = self.options.lde_blowup_factor.ilog2();
+            let security_per_query = self.options.fri_blowup_factor().ilog2();
             let num_fri_quiries = u32::from(self.options.num_queries);
             security_per_query * num_fri_quiries + grinding_factor
         };
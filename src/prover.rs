@@ -1,11 +1,22 @@
+use crate::air::num_extension_columns;
 use crate::air::AirConfig;
+use crate::air::AirError;
 use crate::challenges::Challenges;
 use crate::channel::ProverChannel;
 use crate::composer::DeepPolyComposer;
+use crate::debug::validate_base_constraints;
+use crate::debug::validate_extension_constraints;
+use crate::debug::AirLint;
+use crate::debug::ConstraintViolations;
 use crate::fri::FriProver;
+use crate::hash::Digest;
+use crate::keys::ProvingKey;
+use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleCap;
 use crate::merkle::MerkleTree;
 use crate::random::draw_multiple;
+use crate::random::ZkRng;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::utils::GpuAllocator;
@@ -15,67 +26,193 @@ use crate::Matrix;
 use crate::Proof;
 use crate::ProofOptions;
 use crate::Trace;
+use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use ark_ff::Field;
+use ark_ff::Zero;
 use ark_poly::EvaluationDomain;
+use ark_std::rand::Rng;
 use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::GpuField;
+use rand_chacha::ChaCha20Rng;
+use snafu::Snafu;
+use std::time::Duration;
 use std::time::Instant;
 
-#[allow(clippy::too_many_lines)]
 pub fn default_prove<S: Stark>(
     this: &S,
     options: ProofOptions,
     witness: S::Witness,
 ) -> Result<Proof<S>, ProvingError> {
-    let now = Instant::now();
+    default_prove_with_report(this, options, witness).map(|(proof, _)| proof)
+}
+
+/// Generates a proof with a proving key computed for this proof alone
+pub fn default_prove_with_report<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+) -> Result<(Proof<S>, ProverReport<S::Digest>), ProvingError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("prove").entered();
+    let mut phase_durations = Vec::new();
+
+    let timer = PhaseTimer::start(ProvingPhase::TraceGeneration);
     let trace = this.generate_trace(witness);
-    println!(
-        "Generated execution trace (cols={}, rows={}) in {:.0?}",
-        trace.base_columns().num_cols(),
-        trace.base_columns().num_rows(),
-        now.elapsed(),
-    );
+    timer.finish(&mut phase_durations);
 
-    let now = Instant::now();
-    let air = Air::new(trace.len(), this.get_public_inputs(), options);
+    prove_trace(this, options, None, trace, phase_durations)
+}
+
+/// Generates a proof with the preprocessed columns committed to in `key`
+pub fn default_prove_with_key<S: Stark>(
+    this: &S,
+    key: &ProvingKey<S>,
+    witness: S::Witness,
+) -> Result<Proof<S>, ProvingError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("prove").entered();
+    let mut phase_durations = Vec::new();
+
+    let timer = PhaseTimer::start(ProvingPhase::TraceGeneration);
+    let trace = this.generate_trace(witness);
+    timer.finish(&mut phase_durations);
+
+    prove_trace(this, key.options(), Some(key), trace, phase_durations).map(|(proof, _)| proof)
+}
+
+/// Proves a generated trace. AIRs without preprocessed columns don't need a
+/// proving key so one is only required if the AIR has preprocessed columns.
+#[allow(clippy::too_many_lines)]
+fn prove_trace<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    key: Option<&ProvingKey<S>>,
+    trace: S::Trace,
+    mut phase_durations: Vec<(ProvingPhase, Duration)>,
+) -> Result<(Proof<S>, ProverReport<S::Digest>), ProvingError> {
+    let timer = PhaseTimer::start(ProvingPhase::AirInit);
+    check_fri_options(options)?;
+    let generated_key;
+    let key = if let Some(key) = key {
+        if key.trace_len() != trace.len() {
+            return Err(ProvingError::TraceLengthMismatch {
+                expected: key.trace_len(),
+                actual: trace.len(),
+            });
+        }
+        key
+    } else if S::AirConfig::NUM_PREPROCESSED_COLUMNS != 0 {
+        return Err(ProvingError::ProvingKeyRequired);
+    } else {
+        // there are no preprocessed columns to salt
+        generated_key = ProvingKey::new(trace.len(), options, None::<&mut ChaCha20Rng>)?;
+        &generated_key
+    };
+    let preprocessed_trace = key.preprocessed_trace();
+    let air = Air::new(trace.len(), this.get_public_inputs(), options)?;
+    let mut lints = air.lints().to_vec();
     let public_coin = this.gen_public_coin(&air);
     let mut channel = ProverChannel::<S>::new(&air, public_coin);
-    println!("Init air: {:?}", now.elapsed());
+    let cap_height = options.merkle_cap_height.into();
+    let preprocessed_trace_commitment = preprocessed_trace.map(|t| t.tree.cap(cap_height));
+    if let Some(commitment) = &preprocessed_trace_commitment {
+        channel.commit_preprocessed_trace(commitment);
+    }
+    timer.finish(&mut phase_durations);
 
-    let now = Instant::now();
+    let timer = PhaseTimer::start(ProvingPhase::BaseTraceCommitment);
     let trace_xs = air.trace_domain();
     let lde_xs = air.lde_domain();
     let base_trace = trace.base_columns();
-    assert_eq!(S::AirConfig::NUM_BASE_COLUMNS, base_trace.num_cols());
-    let base_trace_polys = base_trace.interpolate(trace_xs);
+    if base_trace.num_cols() != S::AirConfig::NUM_BASE_COLUMNS {
+        return Err(ProvingError::BaseColumnCountMismatch {
+            expected: S::AirConfig::NUM_BASE_COLUMNS,
+            actual: base_trace.num_cols(),
+        });
+    }
+    let validate_constraints = this.validate_constraints_before_proving();
+    if validate_constraints {
+        validate_base_constraints::<S::AirConfig>(air.constraints(), base_trace)?;
+    }
+    // masks and salts are only drawn if the proof hides the trace. Each tree
+    // draws from its own stream of a generator seeded for this proof.
+    let hiding = <S::MerkleTree as MatrixMerkleTree<S::Fp>>::HIDING;
+    let zk_rng = (options.zero_knowledge || hiding)
+        .then(|| ZkRng::for_proof(this.gen_zk_rng(), options, base_trace));
+    let zk_stream = |stream| zk_rng.as_ref().map(|rng| rng.stream(stream));
+    let num_extension_segments = S::AirConfig::NUM_EXTENSION_COLUMNS.len() as u64;
+    let mut base_trace_polys = base_trace.interpolate(trace_xs);
+    let mut rng = zk_stream(0);
+    if let Some(rng) = rng.as_mut().filter(|_| options.zero_knowledge) {
+        randomize_trace_polys(&mut base_trace_polys, rng);
+    }
     let mut base_trace_lde = base_trace_polys.bit_reversed_evaluate(lde_xs);
-    let base_trace_tree = S::MerkleTree::from_matrix(&base_trace_lde);
-    println!("Base trace commitment: {:?}", now.elapsed());
+    let base_trace_tree: S::MerkleTree = merkle::commit_rows(&base_trace_lde, rng.as_mut());
+    channel.commit_base_trace(base_trace_tree.cap(cap_height));
+    timer.finish(&mut phase_durations);
 
-    channel.commit_base_trace(base_trace_tree.root());
-    let num_challenges = air.num_challenges();
-    let challenges = Challenges::new(draw_multiple(&mut channel.public_coin, num_challenges));
+    let mut challenges = Challenges::default();
+    challenges.push_round(draw_multiple(
+        &mut channel.public_coin,
+        air.num_challenges(0),
+    ));
+
+    // each extension trace segment is built with the challenges drawn so far
+    // and committed to before the next round of challenges is drawn
+    let timer = PhaseTimer::start(ProvingPhase::ExtensionTraceCommitment);
+    let mut extension_trace = Vec::new();
+    let mut extension_trace_polys: Option<Matrix<S::Fq>> = None;
+    let mut extension_trace_ldes = Vec::new();
+    let mut extension_trace_trees = Vec::new();
+    for (segment, &num_cols) in S::AirConfig::NUM_EXTENSION_COLUMNS.iter().enumerate() {
+        let columns = trace.build_extension_columns(segment, &challenges, &extension_trace);
+        let num_segment_cols = columns.as_ref().map_or(0, Matrix::num_cols);
+        let Some(columns) = columns.filter(|_| num_segment_cols == num_cols) else {
+            return Err(ProvingError::ExtensionColumnCountMismatch {
+                segment,
+                expected: num_cols,
+                actual: num_segment_cols,
+            });
+        };
+        let mut polys = columns.interpolate(trace_xs);
+        let mut rng = zk_stream(1 + segment as u64);
+        if let Some(rng) = rng.as_mut().filter(|_| options.zero_knowledge) {
+            randomize_trace_polys(&mut polys, rng);
+        }
+        let lde = polys.bit_reversed_evaluate(lde_xs);
+        let tree: S::MerkleTree = merkle::commit_rows(&lde, rng.as_mut());
+        channel.commit_extension_trace(tree.cap(cap_height));
+        let num_challenges = air.num_challenges(segment + 1);
+        challenges.push_round(draw_multiple(&mut channel.public_coin, num_challenges));
+
+        if let Some(extension_trace_polys) = &mut extension_trace_polys {
+            extension_trace_polys.append(polys);
+        } else {
+            extension_trace_polys = Some(polys);
+        }
+        extension_trace.push(columns);
+        extension_trace_ldes.push(lde);
+        extension_trace_trees.push(tree);
+    }
     let hints = air.gen_hints(&challenges);
+    lints.extend(air.lint_hints(&hints));
+    if validate_constraints {
+        validate_extension_constraints::<S::AirConfig>(
+            air.constraints(),
+            &challenges,
+            &hints,
+            base_trace,
+            &extension_trace,
+        )?;
+    }
+    timer.finish(&mut phase_durations);
 
-    let now = Instant::now();
-    let extension_trace = trace.build_extension_columns(&challenges);
-    let num_extension_cols = extension_trace.as_ref().map_or(0, Matrix::num_cols);
-    assert_eq!(S::AirConfig::NUM_EXTENSION_COLUMNS, num_extension_cols);
-    let extension_trace_polys = extension_trace.as_ref().map(|t| t.interpolate(trace_xs));
-    let mut extension_trace_lde = extension_trace_polys
-        .as_ref()
-        .map(|p| p.bit_reversed_evaluate(lde_xs));
-    let extension_trace_tree = extension_trace_lde.as_ref().map(S::MerkleTree::from_matrix);
-    if let Some(t) = extension_trace_tree.as_ref() {
-        channel.commit_extension_trace(t.root());
-    }
-    println!("Extension trace commitment: {:?}", now.elapsed());
-
-    #[cfg(debug_assertions)]
-    this.validate_constraints(&challenges, &hints, base_trace, extension_trace.as_ref());
     drop((trace, extension_trace));
 
     let composition_trace_polys: Matrix<S::Fq>;
+    let masking_poly: Option<GpuVec<S::Fq>>;
     let composition_trace_lde: Matrix<S::Fq>;
     let composition_trace_tree: S::MerkleTree;
     {
@@ -85,59 +222,112 @@ pub fn default_prove<S: Stark>(
         // called again at the end of the block.
         let ce_lde_xs = air.ce_domain();
         let ce_domain_size = ce_lde_xs.size();
-        let base_trace_ce_cols = bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
-        let extension_trace_ce_cols = extension_trace_lde
-            .as_mut()
-            .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
+        // preprocessed columns are shared between proofs so are copied rather
+        // than reordered in place
+        let preprocessed_trace_ce_cols = preprocessed_trace.map_or(vec![], |t| {
+            t.lde
+                .iter()
+                .map(|column| {
+                    let mut column = column[0..ce_domain_size].to_vec();
+                    bit_reverse(&mut column);
+                    column
+                })
+                .collect()
+        });
+        let mut base_trace_ce_cols = bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
+        base_trace_ce_cols.extend(preprocessed_trace_ce_cols.iter().map(Vec::as_slice));
+        // columns of all extension segments are evaluated together
+        let extension_trace_ce_cols = extension_trace_ldes
+            .iter_mut()
+            .flat_map(|t| bit_reverse_ce_trace(ce_domain_size, t))
+            .collect::<Vec<_>>();
 
         let num_composition_coeffs = air.num_composition_constraint_coeffs();
         let composition_coeffs = draw_multiple(&mut channel.public_coin, num_composition_coeffs);
         let x_lde = ce_lde_xs.elements().collect::<Vec<_>>();
 
-        let now = Instant::now();
+        let timer = PhaseTimer::start(ProvingPhase::ConstraintEvaluation);
         let composition_evals = S::AirConfig::eval_constraint(
             air.composition_constraint(),
             &challenges,
             &hints,
             &composition_coeffs,
-            air.ce_blowup_factor(),
+            ce_domain_size / air.trace_len(),
             x_lde.to_vec_in(GpuAllocator),
             &base_trace_ce_cols,
-            extension_trace_ce_cols.as_deref(),
+            (!extension_trace_ce_cols.is_empty()).then_some(&extension_trace_ce_cols),
         );
-        println!("Constraint eval: {:?}", now.elapsed());
+        timer.finish(&mut phase_durations);
 
-        let now = Instant::now();
+        let timer = PhaseTimer::start(ProvingPhase::CompositionTraceCommitment);
         let composition_poly =
-            GpuVec::try_from(composition_evals.into_polynomials(air.ce_domain())).unwrap();
-        let mut composition_trace_cols = (0..air.ce_blowup_factor())
-            .map(|_| Vec::with_capacity_in(air.trace_len(), GpuAllocator))
+            GpuVec::try_from(composition_evals.into_polynomials(air.ce_domain()))
+                .map_err(|reason| ProvingError::UnexpectedMatrixShape { reason })?;
+        // columns hold consecutive segments of the composition polynomial
+        let segment_len = air.composition_segment_len();
+        let mut composition_trace_cols = composition_poly
+            .chunks(segment_len)
+            .map(|segment| {
+                let mut column = Vec::with_capacity_in(air.trace_degree_bound(), GpuAllocator);
+                column.extend_from_slice(segment);
+                column.resize(air.trace_degree_bound(), S::Fq::zero());
+                column
+            })
             .collect::<Vec<_>>();
-        for chunk in composition_poly.chunks(composition_trace_cols.len()) {
-            for i in 0..composition_trace_cols.len() {
-                composition_trace_cols[i].push(chunk[i]);
-            }
+        let mut rng = zk_stream(1 + num_extension_segments);
+        if let Some(rng) = rng.as_mut().filter(|_| options.zero_knowledge) {
+            randomize_composition_trace_cols(&mut composition_trace_cols, segment_len, rng);
         }
         composition_trace_polys = Matrix::new(composition_trace_cols);
-        composition_trace_lde = composition_trace_polys.bit_reversed_evaluate(air.lde_domain());
-        composition_trace_tree = S::MerkleTree::from_matrix(&composition_trace_lde);
-        channel.commit_composition_trace(composition_trace_tree.root());
-        println!("Composition trace commitment: {:?}", now.elapsed());
+        // the masking polynomial is committed alongside the composition trace
+        // so it's fixed before the DEEP composition coefficients are drawn
+        masking_poly = rng
+            .as_mut()
+            .filter(|_| options.zero_knowledge)
+            .map(|rng| random_poly(air.trace_degree_bound(), rng));
+        let mut composition_trace_and_mask_polys = composition_trace_polys.clone();
+        composition_trace_and_mask_polys.extend(
+            masking_poly
+                .as_ref()
+                .map(|poly| poly.to_vec_in(GpuAllocator)),
+        );
+        composition_trace_lde =
+            composition_trace_and_mask_polys.into_bit_reversed_evaluations(lde_xs);
+        composition_trace_tree = merkle::commit_rows(&composition_trace_lde, rng.as_mut());
+        channel.commit_composition_trace(composition_trace_tree.cap(cap_height));
+        timer.finish(&mut phase_durations);
 
         bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
-        extension_trace_lde
-            .as_mut()
-            .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
+        for extension_trace_lde in &mut extension_trace_ldes {
+            bit_reverse_ce_trace(ce_domain_size, extension_trace_lde);
+        }
     }
 
-    let now = Instant::now();
+    let timer = PhaseTimer::start(ProvingPhase::DeepComposition);
     let z = channel.get_ood_point();
+    if let Some(preprocessed_trace) = preprocessed_trace {
+        // preprocessed polynomials aren't randomized so are padded to the
+        // degree bound of the base trace polynomials
+        let trace_degree_bound = air.trace_degree_bound();
+        base_trace_polys.append(Matrix::new(
+            preprocessed_trace
+                .polys
+                .iter()
+                .map(|poly| {
+                    let mut poly = poly.to_vec_in(GpuAllocator);
+                    poly.resize(trace_degree_bound, S::Fp::zero());
+                    poly
+                })
+                .collect(),
+        ));
+    }
     let mut deep_poly_composer = DeepPolyComposer::new(
         &air,
         z,
         base_trace_polys,
         extension_trace_polys,
         composition_trace_polys,
+        masking_poly,
     );
     let (execution_trace_oods, composition_trace_oods) = deep_poly_composer.get_ood_evals();
     channel.send_ood_evals(execution_trace_oods, composition_trace_oods);
@@ -145,39 +335,278 @@ pub fn default_prove<S: Stark>(
     let deep_coeffs = this.gen_deep_coeffs(&mut channel.public_coin, &air);
     let deep_composition_poly = deep_poly_composer.into_deep_poly(deep_coeffs);
     // let deep_xs = Radix2EvaluationDomain::new(lde_xs.size());
-    let deep_composition_lde = deep_composition_poly.into_bit_reversed_evaluations(lde_xs);
-    println!("Deep composition: {:?}", now.elapsed());
+    let deep_composition_lde =
+        GpuVec::try_from(deep_composition_poly.into_bit_reversed_evaluations(lde_xs))
+            .map_err(|reason| ProvingError::UnexpectedMatrixShape { reason })?;
+    timer.finish(&mut phase_durations);
 
-    let now = Instant::now();
+    let timer = PhaseTimer::start(ProvingPhase::Fri);
     let fri_options = options.into_fri_options();
     let mut fri_prover = FriProver::<S::Fq, S::Digest, S::MerkleTree>::new(fri_options);
-    fri_prover.build_layers(&mut channel, deep_composition_lde.try_into().unwrap());
-    println!("FRI: {:?}", now.elapsed());
+    let mut rng = zk_stream(2 + num_extension_segments);
+    fri_prover.build_layers(&mut channel, deep_composition_lde, rng.as_mut());
+    timer.finish(&mut phase_durations);
 
-    let now = Instant::now();
-    channel.grind_fri_commitments();
-    println!("Proof of work: {:?}", now.elapsed());
+    let timer = PhaseTimer::start(ProvingPhase::ProofOfWork);
+    channel.grind_fri_commitments()?;
+    timer.finish(&mut phase_durations);
 
+    let timer = PhaseTimer::start(ProvingPhase::Queries);
     let query_positions = Vec::from_iter(channel.get_fri_query_positions());
-    let fri_proof = fri_prover.into_proof(&query_positions);
+    let fri_proof = fri_prover.into_proof(&query_positions)?;
 
     let queries = Queries::new(
         &base_trace_lde,
-        extension_trace_lde.as_ref(),
+        preprocessed_trace.map(|t| &t.lde),
+        &extension_trace_ldes,
         &composition_trace_lde,
         &base_trace_tree,
-        extension_trace_tree.as_ref(),
+        preprocessed_trace.map(|t| &t.tree),
+        &extension_trace_trees,
         &composition_trace_tree,
+        cap_height,
         &query_positions,
+    )?;
+    timer.finish(&mut phase_durations);
+
+    let report = ProverReport {
+        phase_durations,
+        trace_len: air.trace_len(),
+        lde_domain_size: air.lde_domain().size(),
+        ce_domain_size: air.ce_domain().size(),
+        num_base_columns: S::AirConfig::NUM_BASE_COLUMNS,
+        num_extension_columns: num_extension_columns::<S::AirConfig>(),
+        base_trace_commitment: base_trace_tree.cap(cap_height),
+        preprocessed_trace_commitment,
+        extension_trace_commitments: extension_trace_trees
+            .iter()
+            .map(|t| t.cap(cap_height))
+            .collect(),
+        composition_trace_commitment: composition_trace_tree.cap(cap_height),
+        fri_layer_commitments: fri_proof
+            .layers
+            .iter()
+            .map(|l| l.commitment.clone())
+            .collect(),
+        lints,
+    };
+    #[cfg(feature = "tracing")]
+    for lint in &report.lints {
+        tracing::warn!("{lint}");
+    }
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        trace_len = report.trace_len,
+        lde_domain_size = report.lde_domain_size,
+        total_duration = ?report.total_duration(),
+        "generated proof"
     );
-    Ok(channel.build_proof(queries, fri_proof))
+    Ok((channel.build_proof(queries, fri_proof), report))
+}
+
+/// Phases of proof generation in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProvingPhase {
+    TraceGeneration,
+    AirInit,
+    BaseTraceCommitment,
+    ExtensionTraceCommitment,
+    ConstraintEvaluation,
+    CompositionTraceCommitment,
+    DeepComposition,
+    Fri,
+    ProofOfWork,
+    Queries,
+}
+
+impl ProvingPhase {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::TraceGeneration => "trace_generation",
+            Self::AirInit => "air_init",
+            Self::BaseTraceCommitment => "base_trace_commitment",
+            Self::ExtensionTraceCommitment => "extension_trace_commitment",
+            Self::ConstraintEvaluation => "constraint_evaluation",
+            Self::CompositionTraceCommitment => "composition_trace_commitment",
+            Self::DeepComposition => "deep_composition",
+            Self::Fri => "fri",
+            Self::ProofOfWork => "proof_of_work",
+            Self::Queries => "queries",
+        }
+    }
+}
+
+/// Measurements taken while generating a proof
+#[derive(Debug, Clone)]
+pub struct ProverReport<D: Digest> {
+    /// Time spent in each phase in the order the phases ran
+    pub phase_durations: Vec<(ProvingPhase, Duration)>,
+    pub trace_len: usize,
+    pub lde_domain_size: usize,
+    /// Size of the domain constraints are evaluated over
+    pub ce_domain_size: usize,
+    pub num_base_columns: usize,
+    pub num_extension_columns: usize,
+    pub base_trace_commitment: MerkleCap<D>,
+    /// Commitment from the verification key the proof is bound to
+    pub preprocessed_trace_commitment: Option<MerkleCap<D>>,
+    /// Commitments to each extension trace segment
+    pub extension_trace_commitments: Vec<MerkleCap<D>>,
+    pub composition_trace_commitment: MerkleCap<D>,
+    pub fri_layer_commitments: Vec<MerkleCap<D>>,
+    /// Likely mistakes in the AIR found by [`Air::lints`] and
+    /// [`Air::lint_hints`]
+    pub lints: Vec<AirLint>,
+}
+
+impl<D: Digest> ProverReport<D> {
+    /// Returns the time spent in the given phase
+    pub fn phase_duration(&self, phase: ProvingPhase) -> Option<Duration> {
+        self.phase_durations
+            .iter()
+            .find(|(p, _)| *p == phase)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// Returns the time spent across all phases
+    pub fn total_duration(&self) -> Duration {
+        self.phase_durations
+            .iter()
+            .map(|(_, duration)| duration)
+            .sum()
+    }
+}
+
+/// Times a proving phase. Phases are also entered as spans when the `tracing`
+/// feature is enabled.
+struct PhaseTimer {
+    phase: ProvingPhase,
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl PhaseTimer {
+    fn start(phase: ProvingPhase) -> Self {
+        Self {
+            phase,
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            _span: tracing::info_span!("proving_phase", phase = phase.name()).entered(),
+        }
+    }
+
+    fn finish(self, phase_durations: &mut Vec<(ProvingPhase, Duration)>) {
+        phase_durations.push((self.phase, self.start.elapsed()));
+    }
 }
 
 /// Errors that can occur during the proving stage
-#[derive(Debug)]
+#[derive(Debug, Snafu)]
 pub enum ProvingError {
-    Fail,
-    // TODO
+    #[snafu(display("expected `{expected}` base trace columns, but `{actual}` were provided"))]
+    BaseColumnCountMismatch { expected: usize, actual: usize },
+    #[snafu(display(
+        "expected `{expected}` columns in extension trace segment `{segment}`, but `{actual}` \
+         were provided"
+    ))]
+    ExtensionColumnCountMismatch {
+        segment: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[snafu(display(
+        "proving key is for traces of length `{expected}`, but the trace has length `{actual}`"
+    ))]
+    TraceLengthMismatch { expected: usize, actual: usize },
+    #[snafu(display("AIRs with preprocessed columns are proven with a proving key"))]
+    ProvingKeyRequired,
+    #[snafu(context(false))]
+    #[snafu(display("{source}"))]
+    InvalidAir { source: AirError },
+    #[snafu(display(
+        "fri folding factor `{folding_factor}` and max remainder coeffs `{max_remainder_coeffs}` \
+         are not supported"
+    ))]
+    InvalidFriOptions {
+        folding_factor: u8,
+        max_remainder_coeffs: u8,
+    },
+    #[snafu(display("matrix does not have a single column: {reason}"))]
+    UnexpectedMatrixShape { reason: String },
+    #[snafu(context(false))]
+    #[snafu(display("merkle proof generation failed: {source}"))]
+    Merkle { source: merkle::Error },
+    #[snafu(display("no proof of work nonce satisfies grinding factor `{grinding_factor}`"))]
+    GrindingExhausted { grinding_factor: u8 },
+    #[snafu(display("execution trace does not satisfy the AIR constraints:\n{details}"))]
+    ConstraintsNotSatisfied {
+        constraint: usize,
+        row: usize,
+        details: String,
+    },
+}
+
+impl<Fp: Field, Fq: Field> From<ConstraintViolations<Fp, Fq>> for ProvingError {
+    fn from(violations: ConstraintViolations<Fp, Fq>) -> Self {
+        let first = &violations.violations[0];
+        Self::ConstraintsNotSatisfied {
+            constraint: first.constraint.index,
+            row: first.row,
+            details: violations.to_string(),
+        }
+    }
+}
+
+const fn check_fri_options(options: ProofOptions) -> Result<(), ProvingError> {
+    if options.has_supported_fri_options() {
+        Ok(())
+    } else {
+        Err(ProvingError::InvalidFriOptions {
+            folding_factor: options.fri_folding_factor,
+            max_remainder_coeffs: options.fri_max_remainder_coeffs,
+        })
+    }
+}
+
+/// Adds a random multiple of the trace domain's vanishing polynomial `x^n - 1`
+/// to each column. Columns keep their values over the trace domain but have
+/// uniformly random evaluations everywhere else. Doubles the number of coeffs.
+fn randomize_trace_polys<F: GpuField + Field>(polys: &mut Matrix<F>, rng: &mut impl Rng) {
+    for column in polys.iter_mut() {
+        let n = column.len();
+        column.resize(2 * n, F::zero());
+        for i in 0..n {
+            let r = F::rand(rng);
+            column[i] -= r;
+            column[n + i] = r;
+        }
+    }
+}
+
+/// Hides the segments of the composition polynomial. `x^segment_len * r_i(x)`
+/// is added to column `i` and `r_i(x)` is subtracted from column `i + 1` so the
+/// columns still sum to the composition polynomial `Σ x^(i * segment_len) *
+/// h_i(x)`. Columns must have room for `2 * segment_len` coefficients.
+fn randomize_composition_trace_cols<F: Field>(
+    columns: &mut [GpuVec<F>],
+    segment_len: usize,
+    rng: &mut impl Rng,
+) {
+    for i in 1..columns.len() {
+        for j in 0..segment_len {
+            let r = F::rand(rng);
+            columns[i - 1][segment_len + j] += r;
+            columns[i][j] -= r;
+        }
+    }
+}
+
+/// Generates a polynomial with `num_coeffs` uniformly random coefficients
+fn random_poly<F: Field>(num_coeffs: usize, rng: &mut impl Rng) -> GpuVec<F> {
+    let mut coeffs = Vec::with_capacity_in(num_coeffs, GpuAllocator);
+    coeffs.extend((0..num_coeffs).map(|_| F::rand(rng)));
+    coeffs
 }
 
 /// Bit reverses the first ce_domain_size many values of the matrix columns.
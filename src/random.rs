@@ -1,10 +1,23 @@
+use crate::hash::AlgebraicHashFn;
+use crate::hash::Blake3HashFn;
 use crate::hash::Digest;
 use crate::hash::ElementHashFn;
 use crate::hash::HashFn;
+use crate::hash::Keccak256HashFn;
+use crate::hash::Rpo256HashFn;
+use crate::hash::Sha256HashFn;
+use crate::merkle::MerkleCap;
+use crate::Matrix;
+use crate::ProofOptions;
 use alloc::vec::Vec;
 use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_ff::Zero;
+use ark_serialize::CanonicalSerialize;
 use rand::Rng;
 use rand::RngCore;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use std::collections::BTreeSet;
@@ -32,6 +45,13 @@ pub trait PublicCoin: Sized + Send + Sync + Debug {
 
     fn reseed_with_digest(&mut self, val: &Self::Digest);
 
+    /// Reseeds with every node of a merkle cap
+    fn reseed_with_cap(&mut self, cap: &MerkleCap<Self::Digest>) {
+        for digest in &cap.0 {
+            self.reseed_with_digest(digest);
+        }
+    }
+
     fn reseed_with_field_elements(&mut self, vals: &[Self::Field]);
 
     fn reseed_with_field_element_vector(&mut self, vector: &[Self::Field]) {
@@ -59,6 +79,12 @@ pub trait PublicCoin: Sized + Send + Sync + Debug {
     fn security_level_bits() -> u32;
 }
 
+/// Public coin that can be seeded directly from bytes
+pub trait SeedablePublicCoin: PublicCoin {
+    /// Creates a public coin seeded with the hash of the provided bytes
+    fn new_from_seed(seed: &[u8]) -> Self;
+}
+
 pub struct PublicCoinImpl<F: Field, H: HashFn> {
     pub seed: H::Digest,
     counter: u64,
@@ -66,6 +92,14 @@ pub struct PublicCoinImpl<F: Field, H: HashFn> {
     _phantom: PhantomData<F>,
 }
 
+pub type Sha256PublicCoin<F> = PublicCoinImpl<F, Sha256HashFn>;
+
+pub type Blake3PublicCoin<F> = PublicCoinImpl<F, Blake3HashFn>;
+
+pub type Keccak256PublicCoin<F> = PublicCoinImpl<F, Keccak256HashFn>;
+
+pub type Rpo256PublicCoin<F> = PublicCoinImpl<F, Rpo256HashFn>;
+
 impl<F: Field, H: ElementHashFn<F>> PublicCoinImpl<F, H> {
     fn reseed_with_field_element(&mut self, val: &F) {
         let val_digest = H::hash_elements([*val]);
@@ -144,6 +178,12 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
     }
 }
 
+impl<F: Field, H: ElementHashFn<F>> SeedablePublicCoin for PublicCoinImpl<F, H> {
+    fn new_from_seed(seed: &[u8]) -> Self {
+        Self::new(H::hash_chunks([seed]))
+    }
+}
+
 impl<F: Field, H: HashFn> Iterator for PublicCoinImpl<F, H> {
     type Item = u8;
 
@@ -178,6 +218,192 @@ impl<F: Field, H: HashFn> RngCore for PublicCoinImpl<F, H> {
     }
 }
 
+/// Field-native public coin built as a duplex sponge over an algebraic hash.
+///
+/// Digests and field elements are absorbed straight into the rate of the
+/// permutation state and challenges are squeezed out as field elements. This
+/// avoids the byte conversions of [`PublicCoinImpl`] which makes the transcript
+/// cheap to arithmetize in a recursive verifier.
+pub struct SpongePublicCoin<F: Field, H: AlgebraicHashFn<F::BasePrimeField>> {
+    state: Vec<F::BasePrimeField>,
+    input_buffer: Vec<F::BasePrimeField>,
+    output_buffer: Vec<F::BasePrimeField>,
+    _phantom: PhantomData<(F, H)>,
+}
+
+pub type Rpo256SpongePublicCoin<F> = SpongePublicCoin<F, Rpo256HashFn>;
+
+impl<F: Field, H: AlgebraicHashFn<F::BasePrimeField>> SpongePublicCoin<F, H> {
+    const RATE: usize = H::STATE_WIDTH - H::CAPACITY;
+
+    fn absorb(&mut self, elements: impl IntoIterator<Item = F::BasePrimeField>) {
+        // squeezed elements are invalidated by any new input
+        self.output_buffer.clear();
+        for element in elements {
+            self.input_buffer.push(element);
+            if self.input_buffer.len() == Self::RATE {
+                self.duplex();
+            }
+        }
+    }
+
+    fn squeeze(&mut self) -> F::BasePrimeField {
+        if !self.input_buffer.is_empty() || self.output_buffer.is_empty() {
+            self.duplex();
+        }
+        self.output_buffer.pop().unwrap()
+    }
+
+    /// Overwrites the rate with the buffered input, applies the permutation
+    /// and makes the new rate available for squeezing.
+    ///
+    /// Partial blocks are padded with zeros and the number of absorbed
+    /// elements is added to the capacity so inputs of different lengths never
+    /// produce the same state. Squeezing without input leaves the rate as is.
+    fn duplex(&mut self) {
+        let num_elements = self.input_buffer.len();
+        if num_elements != 0 {
+            let rate = &mut self.state[H::CAPACITY..];
+            rate[..num_elements].copy_from_slice(&self.input_buffer);
+            rate[num_elements..].fill(F::BasePrimeField::zero());
+            self.state[0] += F::BasePrimeField::from(num_elements as u64);
+            self.input_buffer.clear();
+        }
+        H::permute(&mut self.state);
+        self.output_buffer.clear();
+        self.output_buffer
+            .extend_from_slice(&self.state[H::CAPACITY..]);
+    }
+}
+
+impl<F: Field, H: AlgebraicHashFn<F::BasePrimeField>> Clone for SpongePublicCoin<F, H> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            input_buffer: self.input_buffer.clone(),
+            output_buffer: self.output_buffer.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F: Field, H: AlgebraicHashFn<F::BasePrimeField>> Debug for SpongePublicCoin<F, H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpongePublicCoin")
+            .field("state", &self.state)
+            .field("input_buffer", &self.input_buffer)
+            .field("output_buffer", &self.output_buffer)
+            .finish()
+    }
+}
+
+impl<F: Field, H: AlgebraicHashFn<F::BasePrimeField>> PublicCoin for SpongePublicCoin<F, H> {
+    type Digest = H::Digest;
+    type Field = F;
+
+    fn new(digest: H::Digest) -> Self {
+        let mut coin = Self {
+            state: vec![F::BasePrimeField::zero(); H::STATE_WIDTH],
+            input_buffer: Vec::new(),
+            output_buffer: Vec::new(),
+            _phantom: PhantomData,
+        };
+        coin.reseed_with_digest(&digest);
+        coin
+    }
+
+    fn reseed_with_digest(&mut self, val: &H::Digest) {
+        self.absorb(H::digest_elements(val));
+    }
+
+    fn reseed_with_field_elements(&mut self, vals: &[F]) {
+        self.absorb(vals.iter().flat_map(F::to_base_prime_field_elements));
+    }
+
+    fn reseed_with_int(&mut self, val: u64) {
+        // split into 32-bit limbs since `val` can exceed the field modulus
+        self.absorb([val & 0xFFFF_FFFF, val >> 32].map(F::BasePrimeField::from));
+    }
+
+    fn draw(&mut self) -> F {
+        let elements = (0..F::extension_degree())
+            .map(|_| self.squeeze())
+            .collect::<Vec<F::BasePrimeField>>();
+        F::from_base_prime_field_elems(&elements).unwrap()
+    }
+
+    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+        assert!(domain_size.is_power_of_two());
+        // low bits of a uniform field element are close to uniform
+        let mask = domain_size as u64 - 1;
+        (0..max_n)
+            .map(|_| usize::try_from(self.squeeze().into_bigint().as_ref()[0] & mask).unwrap())
+            .collect()
+    }
+
+    fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
+        let mut coin = self.clone();
+        coin.reseed_with_int(nonce);
+        let element = coin.squeeze().into_bigint().as_ref()[0];
+        element.trailing_zeros() >= u32::from(proof_of_work_bits)
+    }
+
+    fn security_level_bits() -> u32 {
+        H::COLLISION_RESISTANCE
+    }
+}
+
+impl<F: Field, H: AlgebraicHashFn<F::BasePrimeField>> SeedablePublicCoin
+    for SpongePublicCoin<F, H>
+{
+    fn new_from_seed(seed: &[u8]) -> Self {
+        Self::new(H::hash_chunks([seed]))
+    }
+}
+
+/// Randomness for the masks and salts of the prover. Each Merkle tree draws
+/// from its own stream so a seeded [`Stark::gen_zk_rng`] never repeats
+/// randomness across trees, proofs or proving keys.
+///
+/// [`Stark::gen_zk_rng`]: crate::Stark::gen_zk_rng
+pub(crate) struct ZkRng(ChaCha20Rng);
+
+impl ZkRng {
+    /// Generator of a proving key
+    pub fn for_key(mut rng: impl RngCore) -> Self {
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        Self(ChaCha20Rng::from_seed(seed))
+    }
+
+    /// Generator of a proof. The seed is bound to the base trace and options
+    /// so proofs of different traces don't share masks or salts.
+    pub fn for_proof<F: Field>(
+        mut rng: impl RngCore,
+        options: ProofOptions,
+        base_trace: &Matrix<F>,
+    ) -> Self {
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
+        sha2::Digest::update(&mut hasher, seed);
+        options.serialize_compressed(&mut hasher).unwrap();
+        for column in base_trace.iter() {
+            column[..].serialize_compressed(&mut hasher).unwrap();
+        }
+        Self(ChaCha20Rng::from_seed(
+            sha2::Digest::finalize(hasher).into(),
+        ))
+    }
+
+    /// Generator of the stream with the given id
+    pub fn stream(&self, stream: u64) -> ChaCha20Rng {
+        let mut rng = self.0.clone();
+        rng.set_stream(stream);
+        rng
+    }
+}
+
 pub fn leading_zeros(hash: &[u8]) -> u32 {
     let mut zeros = 0;
     for byte in hash {
@@ -194,3 +420,60 @@ pub fn leading_zeros(hash: &[u8]) -> u32 {
 pub fn draw_multiple<P: PublicCoin>(public_coin: &mut P, n: usize) -> Vec<P::Field> {
     (0..n).map(|_| public_coin.draw()).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PublicCoin;
+    use super::Rpo256SpongePublicCoin;
+    use super::SeedablePublicCoin;
+    use crate::hash::AlgebraicHashFn;
+    use crate::hash::Rpo256HashFn;
+    use ark_ff::Zero;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+    #[test]
+    fn sponge_coin_is_deterministic() {
+        let mut coin_a = Rpo256SpongePublicCoin::<Fq3>::new_from_seed(b"seed");
+        let mut coin_b = Rpo256SpongePublicCoin::<Fq3>::new_from_seed(b"seed");
+        let mut coin_c = Rpo256SpongePublicCoin::<Fq3>::new_from_seed(b"seed");
+
+        coin_a.reseed_with_field_elements(&[Fq3::from(Fp::from(1u64))]);
+        coin_b.reseed_with_field_elements(&[Fq3::from(Fp::from(1u64))]);
+        coin_c.reseed_with_field_elements(&[Fq3::from(Fp::from(2u64))]);
+
+        assert_eq!(coin_a.draw(), coin_b.draw());
+        assert_eq!(coin_a.draw_queries(20, 1024), coin_b.draw_queries(20, 1024));
+        assert_ne!(coin_a.draw(), coin_c.draw());
+    }
+
+    #[test]
+    fn sponge_coin_separates_inputs_of_different_lengths() {
+        let mut coin = Rpo256SpongePublicCoin::<Fp>::new_from_seed(b"seed");
+        // leaves the squeezed rate in the state
+        coin.draw();
+        let mut coin_a = coin.clone();
+        let mut coin_b = coin.clone();
+        let mut coin_c = coin;
+
+        let stale = coin_a.state[<Rpo256HashFn as AlgebraicHashFn<Fp>>::CAPACITY + 1];
+        coin_a.reseed_with_field_elements(&[Fp::from(1u64)]);
+        coin_b.reseed_with_field_elements(&[Fp::from(1u64), stale]);
+        coin_c.reseed_with_field_elements(&[Fp::from(1u64), Fp::zero()]);
+
+        let draw_a = coin_a.draw();
+        assert_ne!(draw_a, coin_b.draw());
+        assert_ne!(draw_a, coin_c.draw());
+    }
+
+    #[test]
+    fn sponge_coin_grinds_proof_of_work() {
+        let mut coin = Rpo256SpongePublicCoin::<Fp>::new_from_seed(b"seed");
+        coin.reseed_with_int(42);
+
+        let nonce = coin.grind_proof_of_work(8).unwrap();
+
+        assert!(coin.verify_proof_of_work(8, nonce));
+        assert!(coin.draw_queries(30, 64).iter().all(|&i| i < 64));
+    }
+}
@@ -1,17 +1,26 @@
 use crate::air::AirConfig;
+use crate::air::AirError;
 use crate::challenges::Challenges;
 use crate::channel::VerifierChannelArtifacts;
 use crate::composer::DeepCompositionCoeffs;
 use crate::debug::default_validate_constraints;
+use crate::debug::ConstraintViolations;
 use crate::hash::Digest;
 use crate::hints::Hints;
+use crate::keys::ProvingKey;
+use crate::keys::VerificationKey;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
 use crate::prover::default_prove;
+use crate::prover::default_prove_with_key;
+use crate::prover::default_prove_with_report;
+use crate::prover::ProverReport;
 use crate::prover::ProvingError;
 use crate::random::draw_multiple;
 use crate::random::PublicCoin;
+use crate::random::ZkRng;
 use crate::verifier::default_verify;
+use crate::verifier::default_verify_with_key;
 use crate::verifier::VerificationError;
 use crate::Air;
 use crate::Matrix;
@@ -21,6 +30,9 @@ use crate::StarkExtensionOf;
 use crate::Trace;
 use ark_ff::FftField;
 use ministark_gpu::GpuFftField;
+use rand::RngCore;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 
 pub trait Stark: Sized + Send + Sync {
     type Fp: GpuFftField + FftField;
@@ -44,7 +56,7 @@ pub trait Stark: Sized + Send + Sync {
         air: &Air<Self::AirConfig>,
     ) -> DeepCompositionCoeffs<Self::Fq> {
         let num_execution_trace = air.trace_arguments().len();
-        let num_composition_trace = air.ce_blowup_factor();
+        let num_composition_trace = air.num_composition_trace_columns();
         DeepCompositionCoeffs {
             execution_trace: draw_multiple(public_coin, num_execution_trace),
             composition_trace: draw_multiple(public_coin, num_composition_trace),
@@ -54,6 +66,25 @@ pub trait Stark: Sized + Send + Sync {
 
     fn generate_trace(&self, witness: Self::Witness) -> Self::Trace;
 
+    /// Source of randomness for masking polynomials in zero-knowledge mode and
+    /// for the salts of hiding Merkle trees. Override with a seeded generator
+    /// to make proofs reproducible. Only called if zero-knowledge is enabled
+    /// or the Merkle tree is hiding. Every proving key and proof derives its
+    /// own seed from this generator and every tree uses a separate stream.
+    fn gen_zk_rng(&self) -> impl RngCore {
+        ChaCha20Rng::from_entropy()
+    }
+
+    /// Commits to the preprocessed columns of the AIR. The proving key can be
+    /// reused by every proof with the same trace length and options.
+    fn setup(&self, trace_len: usize, options: ProofOptions) -> Result<ProvingKey<Self>, AirError> {
+        let hiding = <Self::MerkleTree as MatrixMerkleTree<Self::Fp>>::HIDING;
+        let mut rng = hiding.then(|| ZkRng::for_key(self.gen_zk_rng()).stream(0));
+        ProvingKey::new(trace_len, options, rng.as_mut())
+    }
+
+    /// Generates a proof of an AIR without preprocessed columns. AIRs with
+    /// preprocessed columns are proven with [`Stark::prove_with_key`].
     async fn prove(
         &self,
         options: ProofOptions,
@@ -62,18 +93,49 @@ pub trait Stark: Sized + Send + Sync {
         default_prove(self, options, witness)
     }
 
-    /// Check the AIR constraints are valid
+    async fn prove_with_key(
+        &self,
+        key: &ProvingKey<Self>,
+        witness: Self::Witness,
+    ) -> Result<Proof<Self>, ProvingError> {
+        default_prove_with_key(self, key, witness)
+    }
+
+    /// Generates a proof along with timings and sizes of each proving phase
+    async fn prove_with_report(
+        &self,
+        options: ProofOptions,
+        witness: Self::Witness,
+    ) -> Result<(Proof<Self>, ProverReport<Self::Digest>), ProvingError> {
+        default_prove_with_report(self, options, witness)
+    }
+
+    /// Whether the prover checks the execution trace satisfies the AIR
+    /// constraints before the low degree extension. Enabled by default in
+    /// debug builds so invalid witnesses fail fast with the offending rows.
+    fn validate_constraints_before_proving(&self) -> bool {
+        cfg!(debug_assertions)
+    }
+
+    /// Check the AIR constraints hold over every row of the execution trace.
+    /// `extension_trace` holds the extension trace segments in order. The
+    /// prover doesn't call this but checks the base constraints before
+    /// committing to the base trace and the remaining constraints once the
+    /// extension trace is built.
     fn validate_constraints(
         &self,
         challenges: &Challenges<Self::Fq>,
         hints: &Hints<Self::Fq>,
         base_trace: &Matrix<Self::Fp>,
-        extension_trace: Option<&Matrix<Self::Fq>>,
-    ) {
-        // TODO: this is unfinished
-        default_validate_constraints(self, challenges, hints, base_trace, extension_trace);
+        extension_trace: &[Matrix<Self::Fq>],
+    ) -> Result<(), ConstraintViolations<Self::Fp, Self::Fq>> {
+        default_validate_constraints(self, challenges, hints, base_trace, extension_trace)
     }
 
+    /// Verifies a proof of an AIR without preprocessed columns. Proofs of AIRs
+    /// with preprocessed columns must be verified with
+    /// [`Stark::verify_with_key`] so verifiers don't commit to the preprocessed
+    /// columns themselves.
     #[allow(clippy::too_many_lines)]
     fn verify(
         &self,
@@ -82,4 +144,13 @@ pub trait Stark: Sized + Send + Sync {
     ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
         default_verify(self, proof, required_security_bits)
     }
+
+    fn verify_with_key(
+        &self,
+        proof: Proof<Self>,
+        key: &VerificationKey<Self::Digest>,
+        required_security_bits: u32,
+    ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
+        default_verify_with_key(self, proof, key, required_security_bits)
+    }
 }
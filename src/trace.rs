@@ -1,4 +1,5 @@
 use crate::challenges::Challenges;
+use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
 use crate::stark::Stark;
@@ -24,12 +25,16 @@ pub trait Trace: Send + Sync {
     /// Returns a reference to the base trace columns.
     fn base_columns(&self) -> &Matrix<Self::Fp>;
 
-    /// Builds and returns the extension trace columns
+    /// Builds and returns the columns of extension trace segment `segment`.
     /// These columns require auxiliary random elements to be constructed.
+    /// `challenges` holds the rounds drawn so far i.e. rounds `0..=segment`
+    /// and `extension_trace` holds the segments built before this one.
     /// Returns None if there are no columns that require this.
     fn build_extension_columns(
         &self,
+        _segment: usize,
         _challenges: &Challenges<Self::Fq>,
+        _extension_trace: &[Matrix<Self::Fq>],
     ) -> Option<Matrix<Self::Fq>> {
         None
     }
@@ -37,10 +42,13 @@ pub trait Trace: Send + Sync {
 
 pub struct Queries<C: Stark> {
     pub base_trace_values: Vec<C::Fp>,
-    pub extension_trace_values: Vec<C::Fq>,
+    pub preprocessed_trace_values: Vec<C::Fp>,
+    /// Values of each extension trace segment
+    pub extension_trace_values: Vec<Vec<C::Fq>>,
     pub composition_trace_values: Vec<C::Fq>,
     pub base_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
-    pub extension_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
+    pub preprocessed_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
+    pub extension_trace_proofs: Vec<<C::MerkleTree as MerkleTree>::Proof>,
     pub composition_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
 }
 
@@ -52,13 +60,17 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
     ) -> Result<(), ark_serialize::SerializationError> {
         self.base_trace_values
             .serialize_with_mode(&mut writer, compress)?;
+        self.preprocessed_trace_values
+            .serialize_with_mode(&mut writer, compress)?;
         self.extension_trace_values
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_values
             .serialize_with_mode(&mut writer, compress)?;
         self.base_trace_proof
             .serialize_with_mode(&mut writer, compress)?;
-        self.extension_trace_proof
+        self.preprocessed_trace_proof
+            .serialize_with_mode(&mut writer, compress)?;
+        self.extension_trace_proofs
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_proof
             .serialize_with_mode(&mut writer, compress)?;
@@ -67,10 +79,12 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
 
     fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
         self.base_trace_values.serialized_size(compress)
+            + self.preprocessed_trace_values.serialized_size(compress)
             + self.extension_trace_values.serialized_size(compress)
             + self.composition_trace_values.serialized_size(compress)
             + self.base_trace_proof.serialized_size(compress)
-            + self.extension_trace_proof.serialized_size(compress)
+            + self.preprocessed_trace_proof.serialized_size(compress)
+            + self.extension_trace_proofs.serialized_size(compress)
             + self.composition_trace_proof.serialized_size(compress)
     }
 }
@@ -89,10 +103,12 @@ impl<C: Stark> CanonicalDeserialize for Queries<C> {
     ) -> Result<Self, ark_serialize::SerializationError> {
         Ok(Self {
             base_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            preprocessed_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             extension_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             base_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            extension_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            preprocessed_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            extension_trace_proofs: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
         })
     }
@@ -102,57 +118,94 @@ impl<C: Stark> Clone for Queries<C> {
     fn clone(&self) -> Self {
         Self {
             base_trace_values: self.base_trace_values.clone(),
+            preprocessed_trace_values: self.preprocessed_trace_values.clone(),
             extension_trace_values: self.extension_trace_values.clone(),
             composition_trace_values: self.composition_trace_values.clone(),
             base_trace_proof: self.base_trace_proof.clone(),
-            extension_trace_proof: self.extension_trace_proof.clone(),
+            preprocessed_trace_proof: self.preprocessed_trace_proof.clone(),
+            extension_trace_proofs: self.extension_trace_proofs.clone(),
             composition_trace_proof: self.composition_trace_proof.clone(),
         }
     }
 }
 
 impl<C: Stark> Queries<C> {
+    /// # Errors
+    ///
+    /// Returns an error if a query position is out of bounds.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         base_trace_lde: &Matrix<C::Fp>,
-        extension_trace_lde: Option<&Matrix<C::Fq>>,
+        preprocessed_trace_lde: Option<&Matrix<C::Fp>>,
+        extension_trace_ldes: &[Matrix<C::Fq>],
         composition_trace_lde: &Matrix<C::Fq>,
         base_tree: &C::MerkleTree,
-        extension_tree: Option<&C::MerkleTree>,
+        preprocessed_tree: Option<&C::MerkleTree>,
+        extension_trees: &[C::MerkleTree],
         composition_tree: &C::MerkleTree,
+        cap_height: u32,
         positions: &[usize],
-    ) -> Self {
-        let base_trace_proof = MatrixMerkleTree::<C::Fp>::prove_rows(base_tree, positions).unwrap();
-        let extension_trace_proof = extension_tree.map(|extension_tree| {
-            MatrixMerkleTree::<C::Fq>::prove_rows(extension_tree, positions).unwrap()
-        });
+    ) -> Result<Self, merkle::Error> {
+        let base_trace_proof =
+            MatrixMerkleTree::<C::Fp>::prove_rows(base_tree, cap_height, positions)?;
+        let preprocessed_trace_proof = preprocessed_tree
+            .map(|preprocessed_tree| {
+                MatrixMerkleTree::<C::Fp>::prove_rows(preprocessed_tree, cap_height, positions)
+            })
+            .transpose()?;
+        let extension_trace_proofs = extension_trees
+            .iter()
+            .map(|extension_tree| {
+                MatrixMerkleTree::<C::Fq>::prove_rows(extension_tree, cap_height, positions)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
         let composition_trace_proof =
-            MatrixMerkleTree::<C::Fq>::prove_rows(composition_tree, positions).unwrap();
+            MatrixMerkleTree::<C::Fq>::prove_rows(composition_tree, cap_height, positions)?;
 
         let mut base_trace_values = Vec::new();
-        let mut extension_trace_values = Vec::new();
+        let mut preprocessed_trace_values = Vec::new();
+        let mut extension_trace_values = vec![Vec::new(); extension_trace_ldes.len()];
         let mut composition_trace_values = Vec::new();
         for &position in positions {
             // execution trace
-            let base_trace_row = base_trace_lde.get_row(position).unwrap();
+            let base_trace_row = query_row(base_trace_lde, position)?;
             base_trace_values.extend(base_trace_row);
 
-            if let Some(extension_trace_lde) = extension_trace_lde {
+            if let Some(preprocessed_trace_lde) = preprocessed_trace_lde {
+                let preprocessed_trace_row = query_row(preprocessed_trace_lde, position)?;
+                preprocessed_trace_values.extend(preprocessed_trace_row);
+            }
+
+            for (values, extension_trace_lde) in
+                extension_trace_values.iter_mut().zip(extension_trace_ldes)
+            {
                 // TODO: suport ark DomainCoeff on evaluate_at
-                let extension_trace_row = extension_trace_lde.get_row(position).unwrap();
-                extension_trace_values.extend(extension_trace_row);
+                let extension_trace_row = query_row(extension_trace_lde, position)?;
+                values.extend(extension_trace_row);
             }
 
             // composition trace
-            let composition_trace_row = composition_trace_lde.get_row(position).unwrap();
+            let composition_trace_row = query_row(composition_trace_lde, position)?;
             composition_trace_values.extend(composition_trace_row);
         }
-        Self {
+        Ok(Self {
             base_trace_values,
+            preprocessed_trace_values,
             extension_trace_values,
             composition_trace_values,
             base_trace_proof,
-            extension_trace_proof,
+            preprocessed_trace_proof,
+            extension_trace_proofs,
             composition_trace_proof,
-        }
+        })
     }
 }
+
+/// Returns the row of a low degree extension at a query position
+fn query_row<F: Field>(lde: &Matrix<F>, position: usize) -> Result<Vec<F>, merkle::Error> {
+    lde.get_row(position)
+        .ok_or_else(|| merkle::Error::LeafIndexOutOfBounds {
+            i: position,
+            n: lde.num_rows(),
+        })
+}
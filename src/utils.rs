@@ -460,7 +460,10 @@ unsafe impl Allocator for GpuAllocator {
 }
 
 pub fn gpu_vec_to_vec<T>(v: GpuVec<T>) -> Vec<T> {
-    let (ptr, length, capacity) = v.into_raw_parts();
+    let (ptr, length, capacity) = {
+        let (p, l, c, _) = v.into_raw_parts_with_alloc();
+        (p, l, c)
+    };
     unsafe { Vec::from_raw_parts(ptr, length, capacity) }
 }
 
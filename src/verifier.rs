@@ -1,12 +1,18 @@
+use crate::air::num_extension_columns;
 use crate::air::AirConfig;
+use crate::air::AirError;
 use crate::challenges::Challenges;
 use crate::channel::VerifierChannelArtifacts;
+use crate::codegen;
+use crate::codegen::OodInputs;
 use crate::composer::DeepCompositionCoeffs;
 use crate::constraints::AlgebraicItem;
 use crate::constraints::CompositionItem;
+use crate::constraints::PeriodicColumn;
 use crate::fri;
 use crate::fri::FriVerifier;
 use crate::hints::Hints;
+use crate::keys::VerificationKey;
 use crate::merkle::MatrixMerkleTree;
 use crate::random::draw_multiple;
 use crate::random::PublicCoin;
@@ -15,6 +21,7 @@ use crate::utils::horner_evaluate;
 use crate::utils::FieldVariant;
 use crate::Air;
 use crate::Proof;
+use crate::ProofOptions;
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use ark_ff::Field;
@@ -23,22 +30,51 @@ use ark_poly::EvaluationDomain;
 use ministark_gpu::utils::bit_reverse_index;
 use snafu::Snafu;
 
-#[allow(clippy::too_many_lines)]
+/// Verifies a proof of an AIR without preprocessed columns. Proofs of AIRs
+/// with preprocessed columns are verified against the verification key of
+/// their proving key with [`default_verify_with_key`].
 pub fn default_verify<S: Stark>(
     this: &S,
     proof: Proof<S>,
     required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    if S::AirConfig::NUM_PREPROCESSED_COLUMNS != 0 {
+        return Err(VerificationError::VerificationKeyRequired);
+    }
+    let key = VerificationKey {
+        trace_len: proof.trace_len,
+        options: proof.options,
+        preprocessed_trace_commitment: None,
+    };
+    default_verify_with_key(this, proof, &key, required_security_bits)
+}
+
+#[allow(clippy::too_many_lines)]
+pub fn default_verify_with_key<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    key: &VerificationKey<S::Digest>,
+    required_security_bits: u32,
 ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
     use VerificationError::*;
 
+    check_fri_options(proof.options)?;
     if proof.security_level_bits() < required_security_bits {
         return Err(InvalidProofSecurity);
     }
 
+    let has_preprocessed_columns = S::AirConfig::NUM_PREPROCESSED_COLUMNS != 0;
+    if key.trace_len != proof.trace_len
+        || key.options != proof.options
+        || key.preprocessed_trace_commitment.is_some() != has_preprocessed_columns
+    {
+        return Err(VerificationKeyMismatch);
+    }
+
     let Proof {
         options,
         base_trace_commitment,
-        extension_trace_commitment,
+        extension_trace_commitments,
         composition_trace_commitment,
         execution_trace_ood_evals,
         composition_trace_ood_evals,
@@ -49,22 +85,46 @@ pub fn default_verify<S: Stark>(
         ..
     } = proof;
 
-    let air = Air::new(trace_len, this.get_public_inputs(), options);
+    let air = Air::new(trace_len, this.get_public_inputs(), options)?;
+    let num_trace_arguments = air.trace_arguments().len();
+    if execution_trace_ood_evals.len() != num_trace_arguments {
+        return Err(ExecutionTraceOodEvalCountMismatch {
+            expected: num_trace_arguments,
+            actual: execution_trace_ood_evals.len(),
+        });
+    }
+    let num_composition_trace_cols = air.num_composition_trace_columns();
+    if composition_trace_ood_evals.len() != num_composition_trace_cols {
+        return Err(CompositionTraceOodEvalCountMismatch {
+            expected: num_composition_trace_cols,
+            actual: composition_trace_ood_evals.len(),
+        });
+    }
     let mut public_coin = this.gen_public_coin(&air);
 
-    public_coin.reseed_with_digest(&base_trace_commitment);
-    let num_challenges = air.num_challenges();
-    let air_challenges = Challenges::new(draw_multiple(&mut public_coin, num_challenges));
+    if let Some(commitment) = &key.preprocessed_trace_commitment {
+        public_coin.reseed_with_cap(commitment);
+    }
+    public_coin.reseed_with_cap(&base_trace_commitment);
+    let num_segments = S::AirConfig::NUM_EXTENSION_COLUMNS.len();
+    if extension_trace_commitments.len() != num_segments {
+        return Err(ExtensionTraceCommitmentCountMismatch {
+            expected: num_segments,
+            actual: extension_trace_commitments.len(),
+        });
+    }
+    let mut air_challenges = Challenges::default();
+    air_challenges.push_round(draw_multiple(&mut public_coin, air.num_challenges(0)));
+    for (segment, commitment) in extension_trace_commitments.iter().enumerate() {
+        public_coin.reseed_with_cap(commitment);
+        let num_challenges = air.num_challenges(segment + 1);
+        air_challenges.push_round(draw_multiple(&mut public_coin, num_challenges));
+    }
     let air_hints = air.gen_hints(&air_challenges);
 
-    let extension_trace_commitment = extension_trace_commitment.map(|commitment| {
-        public_coin.reseed_with_digest(&commitment);
-        commitment
-    });
-
     let num_composition_coeffs = air.num_composition_constraint_coeffs();
     let composition_coeffs = draw_multiple(&mut public_coin, num_composition_coeffs);
-    public_coin.reseed_with_digest(&composition_trace_commitment);
+    public_coin.reseed_with_cap(&composition_trace_commitment);
 
     let z = public_coin.draw();
     let ood_evals = [
@@ -88,7 +148,11 @@ pub fn default_verify<S: Stark>(
         z,
     );
 
-    let provided_ood_constraint_evaluation = horner_evaluate(&composition_trace_ood_evals, &z);
+    // composition trace columns hold consecutive segments of the composition
+    // polynomial
+    let z_segment = z.pow([air.composition_segment_len() as u64]);
+    let provided_ood_constraint_evaluation =
+        horner_evaluate(&composition_trace_ood_evals, &z_segment);
 
     if calculated_ood_constraint_evaluation != provided_ood_constraint_evaluation {
         return Err(InconsistentOodConstraintEvaluations);
@@ -99,7 +163,7 @@ pub fn default_verify<S: Stark>(
         &mut public_coin,
         options.into_fri_options(),
         fri_proof,
-        trace_len - 1,
+        air.trace_degree_bound() - 1,
     )?;
 
     if options.grinding_factor != 0 {
@@ -113,39 +177,109 @@ pub fn default_verify<S: Stark>(
     let query_positions =
         Vec::from_iter(public_coin.draw_queries(options.num_queries.into(), lde_domain_size));
 
+    // every trace must have a row for every query before rows are indexed
+    let num_queries = query_positions.len();
+    if trace_queries.base_trace_values.len() != S::AirConfig::NUM_BASE_COLUMNS * num_queries {
+        return Err(BaseTraceQueryDoesNotMatchCommitment);
+    }
+    let num_preprocessed_values = trace_queries.preprocessed_trace_values.len();
+    if num_preprocessed_values != S::AirConfig::NUM_PREPROCESSED_COLUMNS * num_queries {
+        return Err(PreprocessedTraceQueryDoesNotMatchCommitment);
+    }
+    // zero-knowledge proofs commit to a masking polynomial with the composition
+    // trace
+    let num_composition_trace_cols =
+        num_composition_trace_cols + usize::from(options.zero_knowledge);
+    if trace_queries.composition_trace_values.len() != num_composition_trace_cols * num_queries {
+        return Err(CompositionTraceQueryDoesNotMatchCommitment);
+    }
+
     let base_trace_rows = trace_queries
         .base_trace_values
         .chunks(S::AirConfig::NUM_BASE_COLUMNS)
         .collect::<Vec<_>>();
-    let extension_trace_rows = if S::AirConfig::NUM_EXTENSION_COLUMNS == 0 {
-        Vec::new()
-    } else {
+    let preprocessed_trace_rows = if has_preprocessed_columns {
         trace_queries
-            .extension_trace_values
-            .chunks(S::AirConfig::NUM_EXTENSION_COLUMNS)
+            .preprocessed_trace_values
+            .chunks(S::AirConfig::NUM_PREPROCESSED_COLUMNS)
             .collect::<Vec<_>>()
+    } else {
+        Vec::new()
     };
+    if trace_queries.extension_trace_values.len() != num_segments
+        || trace_queries.extension_trace_proofs.len() != num_segments
+    {
+        return Err(ExtensionTraceQueryDoesNotMatchCommitment);
+    }
+    let extension_trace_segment_rows = trace_queries
+        .extension_trace_values
+        .iter()
+        .zip(S::AirConfig::NUM_EXTENSION_COLUMNS)
+        .map(|(values, &num_cols)| {
+            if values.len() != num_cols * num_queries {
+                return Err(ExtensionTraceQueryDoesNotMatchCommitment);
+            }
+            Ok(values.chunks(num_cols).collect::<Vec<_>>())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // rows of all extension segments are joined for the DEEP composition
+    let extension_trace_rows = (0..num_queries)
+        .map(|i| {
+            let segment_rows = extension_trace_segment_rows.iter();
+            segment_rows.flat_map(|rows| rows[i]).copied().collect()
+        })
+        .collect::<Vec<Vec<S::Fq>>>();
+    let extension_trace_rows = extension_trace_rows
+        .iter()
+        .map(Vec::as_slice)
+        .collect::<Vec<_>>();
 
     let composition_trace_rows = trace_queries
         .composition_trace_values
-        .chunks(air.ce_blowup_factor())
+        .chunks(num_composition_trace_cols)
         .collect::<Vec<&[S::Fq]>>();
 
+    // all trace trees are built over the LDE domain
+    let lde_height = lde_domain_size.ilog2();
+    let cap_height = options.merkle_cap_height.into();
+
     // base trace positions
     S::MerkleTree::verify_rows(
         &base_trace_commitment,
+        lde_height,
+        cap_height,
         &query_positions,
         &base_trace_rows,
         trace_queries.base_trace_proof,
     )
     .map_err(|_| BaseTraceQueryDoesNotMatchCommitment)?;
 
-    if let Some(extension_trace_commitment) = extension_trace_commitment {
+    if let Some(preprocessed_trace_commitment) = &key.preprocessed_trace_commitment {
+        S::MerkleTree::verify_rows(
+            preprocessed_trace_commitment,
+            lde_height,
+            cap_height,
+            &query_positions,
+            &preprocessed_trace_rows,
+            trace_queries
+                .preprocessed_trace_proof
+                .ok_or(PreprocessedTraceQueryDoesNotMatchCommitment)?,
+        )
+        .map_err(|_| PreprocessedTraceQueryDoesNotMatchCommitment)?;
+    }
+
+    for ((commitment, rows), proof) in extension_trace_commitments
+        .iter()
+        .zip(&extension_trace_segment_rows)
+        .zip(trace_queries.extension_trace_proofs)
+    {
         S::MerkleTree::verify_rows(
-            &extension_trace_commitment,
+            commitment,
+            lde_height,
+            cap_height,
             &query_positions,
-            &extension_trace_rows,
-            trace_queries.extension_trace_proof.unwrap(),
+            rows,
+            proof,
         )
         .map_err(|_| ExtensionTraceQueryDoesNotMatchCommitment)?;
     }
@@ -153,6 +287,8 @@ pub fn default_verify<S: Stark>(
     // composition trace positions
     S::MerkleTree::verify_rows(
         &composition_trace_commitment,
+        lde_height,
+        cap_height,
         &query_positions,
         &composition_trace_rows,
         trace_queries.composition_trace_proof,
@@ -164,6 +300,7 @@ pub fn default_verify<S: Stark>(
         &query_positions,
         &deep_coeffs,
         &base_trace_rows,
+        &preprocessed_trace_rows,
         &extension_trace_rows,
         &composition_trace_rows,
         &trace_ood_eval_map,
@@ -182,11 +319,36 @@ pub fn default_verify<S: Stark>(
     })
 }
 
+/// Options are read from the proof so are checked before FRI uses them
+const fn check_fri_options(options: ProofOptions) -> Result<(), VerificationError> {
+    if options.has_supported_fri_options() {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidFriOptions {
+            folding_factor: options.fri_folding_factor,
+            max_remainder_coeffs: options.fri_max_remainder_coeffs,
+            blowup_factor: options.fri_blowup_factor(),
+        })
+    }
+}
+
 /// Errors that are returned during verification of a STARK proof
 #[derive(Debug, Snafu)]
 pub enum VerificationError {
     #[snafu(display("proof params do not satisfy security requirements"))]
     InvalidProofSecurity,
+    #[snafu(display(
+        "fri folding factor `{folding_factor}`, max remainder coeffs `{max_remainder_coeffs}` \
+         and blowup factor `{blowup_factor}` are not supported"
+    ))]
+    InvalidFriOptions {
+        folding_factor: u8,
+        max_remainder_coeffs: u8,
+        blowup_factor: u8,
+    },
+    #[snafu(context(false))]
+    #[snafu(display("proof params are invalid: {source}"))]
+    InvalidAir { source: AirError },
     #[snafu(display("constraint evaluations at the out-of-domain point are inconsistent"))]
     InconsistentOodConstraintEvaluations,
     #[snafu(context(false))]
@@ -194,12 +356,32 @@ pub enum VerificationError {
     FriVerification { source: fri::VerificationError },
     #[snafu(display("query does not resolve to the base trace commitment"))]
     BaseTraceQueryDoesNotMatchCommitment,
+    #[snafu(display("query does not resolve to the preprocessed trace commitment"))]
+    PreprocessedTraceQueryDoesNotMatchCommitment,
     #[snafu(display("query does not resolve to the extension trace commitment"))]
     ExtensionTraceQueryDoesNotMatchCommitment,
+    #[snafu(display(
+        "expected `{expected}` extension trace commitments, but the proof has `{actual}`"
+    ))]
+    ExtensionTraceCommitmentCountMismatch { expected: usize, actual: usize },
     #[snafu(display("query does not resolve to the composition trace commitment"))]
     CompositionTraceQueryDoesNotMatchCommitment,
+    #[snafu(display(
+        "expected `{expected}` execution trace out-of-domain evaluations, but the proof has \
+         `{actual}`"
+    ))]
+    ExecutionTraceOodEvalCountMismatch { expected: usize, actual: usize },
+    #[snafu(display(
+        "expected `{expected}` composition trace out-of-domain evaluations, but the proof has \
+         `{actual}`"
+    ))]
+    CompositionTraceOodEvalCountMismatch { expected: usize, actual: usize },
     #[snafu(display("insufficient proof of work on fri commitments"))]
     FriProofOfWork,
+    #[snafu(display("proof was not generated for the verification key"))]
+    VerificationKeyMismatch,
+    #[snafu(display("proofs of AIRs with preprocessed columns require a verification key"))]
+    VerificationKeyRequired,
 }
 
 pub fn ood_constraint_evaluation<A: AirConfig>(
@@ -212,22 +394,45 @@ pub fn ood_constraint_evaluation<A: AirConfig>(
 ) -> A::Fq {
     use AlgebraicItem::*;
     use CompositionItem::*;
-    air.composition_constraint()
+    let composition_constraint = air.composition_constraint();
+    let periodic_eval = |col: &PeriodicColumn<FieldVariant<A::Fp, A::Fq>>| {
+        let trace_len = air.trace_len();
+        let point = x.pow([(trace_len / col.interval_size()) as u64]);
+        let coeffs = col
+            .coeffs()
+            .iter()
+            .map(FieldVariant::as_fq)
+            .collect::<Vec<_>>();
+        horner_evaluate(&coeffs, &point)
+    };
+    if let Some(evaluator) = A::generated_evaluator() {
+        if evaluator.fingerprint == codegen::fingerprint(composition_constraint) {
+            let periodic_evals = codegen::periodic_columns(composition_constraint)
+                .iter()
+                .map(periodic_eval)
+                .collect::<Vec<_>>();
+            let constants = codegen::constants(composition_constraint)
+                .iter()
+                .map(FieldVariant::as_fq)
+                .collect::<Vec<_>>();
+            return (evaluator.eval_ood)(&OodInputs {
+                x,
+                challenges,
+                hints,
+                composition_coeffs: composition_coefficients,
+                constants: &constants,
+                trace_ood_evals: trace_ood_eval_map,
+                periodic_evals: &periodic_evals,
+            });
+        }
+    }
+    composition_constraint
         .graph_eval(&mut |leaf| match leaf {
             Item(X) => FieldVariant::Fq(x),
             &Item(Constant(v)) => v,
-            &Item(Challenge(i)) => FieldVariant::Fq(challenges[i]),
+            &Item(Challenge(round, i)) => FieldVariant::Fq(challenges[(round, i)]),
             &Item(Hint(i)) => FieldVariant::Fq(hints[i]),
-            &Item(Periodic(col)) => {
-                let trace_len = air.trace_len();
-                let point = x.pow([(trace_len / col.interval_size()) as u64]);
-                let coeffs = col
-                    .coeffs()
-                    .iter()
-                    .map(FieldVariant::as_fq)
-                    .collect::<Vec<_>>();
-                FieldVariant::Fq(horner_evaluate(&coeffs, &point))
-            }
+            Item(Periodic(col)) => FieldVariant::Fq(periodic_eval(col)),
             &Item(Trace(i, j)) => FieldVariant::Fq(trace_ood_eval_map[&(i, j)]),
             &CompositionCoeff(i) => FieldVariant::Fq(composition_coefficients[i]),
         })
@@ -240,6 +445,7 @@ pub fn deep_composition_evaluations<A: AirConfig>(
     query_positions: &[usize],
     composition_coeffs: &DeepCompositionCoeffs<A::Fq>,
     base_trace_rows: &[&[A::Fp]],
+    preprocessed_trace_rows: &[&[A::Fp]],
     extension_trace_rows: &[&[A::Fq]],
     composition_trace_rows: &[&[A::Fq]],
     execution_trace_ood_evals_map: &BTreeMap<(usize, isize), A::Fq>,
@@ -249,7 +455,6 @@ pub fn deep_composition_evaluations<A: AirConfig>(
     let trace_domain = air.trace_domain();
     let g = trace_domain.group_gen();
     let g_inv = trace_domain.group_gen_inv();
-    let z_n = z.pow([air.ce_blowup_factor() as u64]);
     let lde_domain = air.lde_domain();
     let lde_domain_size = lde_domain.size();
     let xs = query_positions
@@ -259,17 +464,22 @@ pub fn deep_composition_evaluations<A: AirConfig>(
 
     let mut evals = vec![A::Fq::zero(); query_positions.len()];
 
-    let num_columns = A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS;
+    // preprocessed columns follow the base columns
+    let num_base_columns = A::NUM_BASE_COLUMNS + A::NUM_PREPROCESSED_COLUMNS;
+    let num_columns = num_base_columns + num_extension_columns::<A>();
     let base_column_range = 0..A::NUM_BASE_COLUMNS;
-    let extension_column_range = A::NUM_BASE_COLUMNS..num_columns;
+    let preprocessed_column_range = A::NUM_BASE_COLUMNS..num_base_columns;
+    let extension_column_range = num_base_columns..num_columns;
 
     for (i, (&x, eval)) in xs.iter().zip(&mut evals).enumerate() {
         // execution trace
         for (j, ((column, offset), ood_eval)) in execution_trace_ood_evals_map.iter().enumerate() {
             let trace_value = if base_column_range.contains(column) {
                 A::Fq::from(base_trace_rows[i][*column])
+            } else if preprocessed_column_range.contains(column) {
+                A::Fq::from(preprocessed_trace_rows[i][column - A::NUM_BASE_COLUMNS])
             } else if extension_column_range.contains(column) {
-                extension_trace_rows[i][column - A::NUM_BASE_COLUMNS]
+                extension_trace_rows[i][column - num_base_columns]
             } else {
                 panic!("column {column} does not exist");
             };
@@ -280,10 +490,10 @@ pub fn deep_composition_evaluations<A: AirConfig>(
         }
 
         // composition trace
-        for (j, value) in composition_trace_rows[i].iter().enumerate() {
+        for (j, ood_eval) in composition_trace_ood_evals.iter().enumerate() {
             let alpha = composition_coeffs.composition_trace[j];
-            let ood_eval = composition_trace_ood_evals[j];
-            *eval += alpha * (*value - ood_eval) / (A::Fq::from(x) - z_n);
+            let value = composition_trace_rows[i][j];
+            *eval += alpha * (value - ood_eval) / (A::Fq::from(x) - z);
         }
     }
 
@@ -293,5 +503,12 @@ pub fn deep_composition_evaluations<A: AirConfig>(
         *eval *= alpha + beta * x;
     }
 
+    // apply the mask (last composition trace column) in zero-knowledge mode
+    if air.options().zero_knowledge {
+        for (row, eval) in composition_trace_rows.iter().zip(&mut evals) {
+            *eval += row.last().unwrap();
+        }
+    }
+
     evals
 }
@@ -0,0 +1,190 @@
+use ark_ff::One;
+use ministark::air::AirConfig;
+use ministark::air::ConstraintDegree;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ConstraintGroup;
+use ministark::constraints::ConstraintId;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::Names;
+use ministark::constraints::VirtualColumn;
+use ministark::debug::AirLint;
+use ministark::hints::Hints;
+use ministark::utils::FieldVariant;
+use ministark::Air;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+
+#[derive(Clone, Copy)]
+enum Column {
+    Value,
+    Step,
+    Unused,
+}
+
+impl ExecutionTraceColumn for Column {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+
+    fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Value => "value",
+            Self::Step => "step",
+            Self::Unused => "unused",
+        })
+    }
+}
+
+struct LintAirConfig;
+
+impl AirConfig for LintAirConfig {
+    const NUM_BASE_COLUMNS: usize = 3;
+    const NUM_CHALLENGES: &'static [usize] = &[3];
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn gen_hints(_: usize, _: &(), _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(vec![(0, Fp::one()), (1, Fp::one())])
+    }
+
+    fn names() -> Names {
+        Names::default().with_columns([Column::Value, Column::Step, Column::Unused])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        use AlgebraicItem::*;
+        use Column::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        let every_row = X.pow(trace_len) - &one;
+        vec![
+            Constraint::new((Value.curr() * Value.curr() - Challenge(0, 1) - Hint(0)) / &every_row)
+                .in_group(ConstraintGroup::Transition),
+            Constraint::new((Value.offset(100) * Step.curr() - &one) / &every_row).named("step"),
+            Constraint::new(one - Hint(2)),
+            Constraint::new(-(Challenge(0, 1) + Hint(0) - Value.curr().pow(2)) / &every_row)
+                .named("square"),
+        ]
+    }
+}
+
+#[test]
+fn lints_find_unconstrained_columns_and_unused_challenges() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+
+    let air = Air::<LintAirConfig>::new(256, (), options).unwrap();
+
+    assert_eq!(
+        air.lints()
+            .iter()
+            .map(AirLint::to_string)
+            .collect::<Vec<String>>(),
+        [
+            "step reads value at out of range offset 100",
+            "constraint 2 has degree zero",
+            "square is equivalent to transition constraint 0",
+            "unused is not referenced by any constraint",
+            "challenge 0 is not used by any constraint",
+            "challenge 2 is not used by any constraint",
+        ]
+    );
+}
+
+/// Reads every 128th row of a column
+struct VirtualColumnAirConfig;
+
+const SPARSE: VirtualColumn = VirtualColumn::new(0, 128, 0).named("sparse");
+
+impl AirConfig for VirtualColumnAirConfig {
+    const NUM_BASE_COLUMNS: usize = 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn names() -> Names {
+        Names::default().with_virtual_columns([SPARSE])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        vec![
+            (SPARSE.next() - SPARSE.curr()).every_except_last(128, 0, trace_len),
+            (SPARSE.offset(65) - SPARSE.curr()).every(128, 0, trace_len),
+        ]
+    }
+}
+
+#[test]
+fn lints_check_offsets_in_rows_of_virtual_columns() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+
+    let air = Air::<VirtualColumnAirConfig>::new(1 << 14, (), options).unwrap();
+
+    assert_eq!(
+        air.lints()
+            .iter()
+            .map(AirLint::to_string)
+            .collect::<Vec<String>>(),
+        ["constraint 1 reads sparse at out of range offset 65"]
+    );
+}
+
+#[test]
+fn lints_find_unused_and_missing_hints() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let air = Air::<LintAirConfig>::new(256, (), options).unwrap();
+
+    let hints = air.gen_hints(&Challenges::new(vec![Fp::one(); 3]));
+
+    assert_eq!(
+        air.lint_hints(&hints),
+        [
+            AirLint::UnusedHint {
+                index: 1,
+                name: None
+            },
+            AirLint::MissingHint {
+                index: 2,
+                name: None
+            }
+        ]
+    );
+}
+
+#[test]
+fn degree_report_lists_every_constraint() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let air = Air::<LintAirConfig>::new(256, (), options).unwrap();
+
+    let report = air.degree_report();
+
+    assert_eq!(report.trace_degree_bound, 256);
+    assert_eq!(report.constraints.len(), 4);
+    assert_eq!(
+        report.constraints[0],
+        ConstraintDegree {
+            constraint: ConstraintId {
+                index: 0,
+                name: None,
+                group: Some(ConstraintGroup::Transition),
+                location: None,
+            },
+            numerator_degree: 510,
+            denominator_degree: 256,
+            blowup_factor: 1,
+            degree_adjustment: 1,
+        }
+    );
+    assert_eq!(report.constraints[2].degree_adjustment, 255);
+    assert_eq!(report.max_degree_constraint(), Some(0));
+    let table = report.to_string();
+    assert_eq!(table.lines().count(), 6);
+    assert!(table
+        .lines()
+        .nth(2)
+        .unwrap()
+        .starts_with("transition constraint 0 |"));
+    assert!(table.lines().nth(3).unwrap().starts_with("step "));
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use ark_ff::One;
+use common::Claim;
+use common::TestTrace;
+use ministark::air::AirConfig;
+use ministark::air::AirError;
+use ministark::assertions::Assertion;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::prover::ProvingError;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const TRACE_LEN: usize = 64;
+const OPTIONS: ProofOptions = ProofOptions::new(16, 8, 0, 2, 4);
+const MARKER: u8 = 7;
+const SEQUENCE_STRIDE: usize = 8;
+
+struct CounterAirConfig;
+
+impl AirConfig for CounterAirConfig {
+    const NUM_BASE_COLUMNS: usize = 3;
+    type Fp = Fp;
+    type Fq = Fp;
+    /// The first value of the counter and the values of the third column in
+    /// rows `3`, `11`, `19`, ...
+    type PublicInputs = (Fp, Vec<Fp>);
+
+    fn assertions(_trace_len: usize, (start, sequence): &(Fp, Vec<Fp>)) -> Vec<Assertion<Fp>> {
+        vec![
+            Assertion::single(0, 0, *start),
+            Assertion::periodic(1, 1, 4, Fp::from(MARKER)),
+            Assertion::sequence(2, 3, SEQUENCE_STRIDE, sequence.clone()),
+        ]
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        vec![(0.next() - 0.curr() - one).every_row_except_last(trace_len)]
+    }
+}
+
+type CounterClaim = Claim<CounterAirConfig>;
+
+fn gen_trace(start: u64) -> (TestTrace, CounterClaim) {
+    let rows = (0..TRACE_LEN as u64)
+        .map(|i| {
+            let marker = if i % 4 == 1 { u64::from(MARKER) } else { i };
+            vec![Fp::from(start + i), Fp::from(marker), Fp::from(i * i)]
+        })
+        .collect::<Vec<Vec<Fp>>>();
+    let sequence = rows[3..]
+        .iter()
+        .step_by(SEQUENCE_STRIDE)
+        .map(|row| row[2])
+        .collect();
+    let claim = CounterClaim::new((Fp::from(start), sequence));
+    (TestTrace(Matrix::from_rows(rows)), claim)
+}
+
+#[test]
+fn proof_with_assertions_verifies() {
+    let (trace, claim) = gen_trace(5);
+
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+
+    claim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn proof_does_not_verify_for_other_public_inputs() {
+    let (trace, claim) = gen_trace(5);
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+    let other_claim = CounterClaim::new((Fp::from(6u8), claim.public_inputs.1));
+
+    assert!(other_claim.verify(proof, 1).is_err());
+}
+
+#[test]
+fn violated_sequence_assertion_is_an_error() {
+    let (mut trace, claim) = gen_trace(5);
+    trace.0[2][19] = Fp::one();
+
+    let error = match pollster::block_on(claim.prove(OPTIONS, trace)) {
+        Ok(_) => panic!("expected proving to fail"),
+        Err(error) => error,
+    };
+
+    // assertions follow the constraints of the AIR
+    assert!(matches!(
+        error,
+        ProvingError::ConstraintsNotSatisfied {
+            constraint: 3,
+            row: 19,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn assertion_outside_trace_is_an_error() {
+    let (_, claim) = gen_trace(5);
+    let (start, sequence) = claim.public_inputs;
+    let short_sequence = sequence[..4].to_vec();
+
+    let result = Air::<CounterAirConfig>::new(TRACE_LEN, (start, short_sequence), OPTIONS);
+
+    assert!(matches!(
+        result,
+        Err(AirError::AssertionOutOfBounds {
+            column: 2,
+            trace_len: TRACE_LEN
+        })
+    ));
+}
@@ -0,0 +1,278 @@
+#![feature(allocator_api)]
+
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::Claim;
+use ministark::air::AirConfig;
+use ministark::assertions::Assertion;
+use ministark::challenges::Challenges;
+use ministark::codegen;
+use ministark::codegen::GeneratedEvaluator;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::CompositionItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::PeriodicColumn;
+use ministark::debug::AirLint;
+use ministark::hints::Hints;
+use ministark::stark::Stark;
+use ministark::utils::horner_evaluate;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::verifier::ood_constraint_evaluation;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+use num_traits::Pow;
+use std::collections::BTreeMap;
+
+mod generated {
+    include!("generated/counter_air.rs");
+}
+
+const TRACE_LEN: usize = 64;
+const OPTIONS: ProofOptions = ProofOptions::new(16, 8, 0, 2, 4);
+const PERIODIC_VALUES: [u8; 2] = [3, 5];
+const START: u64 = 7;
+
+struct CounterAirConfig;
+
+impl AirConfig for CounterAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    const NUM_EXTENSION_COLUMNS: &'static [usize] = &[1];
+    const NUM_CHALLENGES: &'static [usize] = &[1];
+    type Fp = Fp;
+    type Fq = Fq3;
+    /// The first value of the counter
+    type PublicInputs = Fp;
+
+    fn gen_hints(trace_len: usize, start: &Fp, challenges: &Challenges<Fq3>) -> Hints<Fq3> {
+        let last_count = *start + Fp::from(trace_len as u64 - 1);
+        Hints::new(vec![(0, challenges[0] * last_count)])
+    }
+
+    fn assertions(_trace_len: usize, start: &Fp) -> Vec<Assertion<Fp>> {
+        vec![Assertion::single(0, 0, *start)]
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fq3>>> {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+        let every_row = X.pow(trace_len) - &one;
+        let [a, b] = PERIODIC_VALUES.map(|v| FieldVariant::Fp(Fp::from(v)));
+        // alternates between `a + b` and `a - b`
+        let periodic = Periodic(PeriodicColumn::new(vec![a, b], 2));
+        vec![
+            Constraint::new((0.next() - 0.curr() - &one) * (X - &last_x) / &every_row),
+            Constraint::new((1.curr() - periodic) * (X - &last_x) / &every_row),
+            Constraint::new((2.curr() - 0.curr() * Challenge(0, 0)) * (X - &last_x) / &every_row),
+            Constraint::new((2.curr() - Hint(0)) / (X - &last_x)),
+            // divides by a value that is the same for every row
+            Constraint::new((0.curr() - Hint(0) / Challenge(0, 0)) / (X - &last_x)),
+        ]
+    }
+
+    fn generated_evaluator() -> Option<GeneratedEvaluator<Fp, Fq3>> {
+        Some(generated::evaluator())
+    }
+}
+
+struct CounterTrace(Matrix<Fp>);
+
+impl Trace for CounterTrace {
+    type Fp = Fp;
+    type Fq = Fq3;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.0
+    }
+
+    fn build_extension_columns(
+        &self,
+        _: usize,
+        challenges: &Challenges<Fq3>,
+        _: &[Matrix<Fq3>],
+    ) -> Option<Matrix<Fq3>> {
+        let column = self.0[0]
+            .iter()
+            .map(|&v| challenges[0] * v)
+            .collect::<Vec<Fq3>>();
+        Some(Matrix::new(vec![column.to_vec_in(GpuAllocator)]))
+    }
+}
+
+type CounterClaim = Claim<CounterAirConfig, CounterTrace>;
+
+fn gen_trace(start: u64) -> CounterTrace {
+    let [a, b] = PERIODIC_VALUES.map(Fp::from);
+    let counter = (start..start + TRACE_LEN as u64)
+        .map(Fp::from)
+        .collect::<Vec<Fp>>();
+    let periodic = (0..TRACE_LEN)
+        .map(|i| if i % 2 == 0 { a + b } else { a - b })
+        .collect::<Vec<Fp>>();
+    CounterTrace(Matrix::new(vec![
+        counter.to_vec_in(GpuAllocator),
+        periodic.to_vec_in(GpuAllocator),
+    ]))
+}
+
+fn air() -> Air<CounterAirConfig> {
+    Air::new(TRACE_LEN, Fp::from(START), OPTIONS).unwrap()
+}
+
+#[test]
+fn generated_module_is_up_to_date() {
+    let air = air();
+
+    assert_eq!(
+        codegen::generate(&air),
+        include_str!("generated/counter_air.rs")
+    );
+    assert_eq!(
+        generated::FINGERPRINT,
+        codegen::fingerprint(&**air.composition_constraint())
+    );
+}
+
+#[test]
+fn generated_lde_evaluator_matches_interpreter() {
+    let air = air();
+    let mut rng = ark_std::test_rng();
+    let ce_domain = air.ce_domain();
+    let n = ce_domain.size();
+    let lde_step = n / air.trace_len();
+    let x_lde = ce_domain.elements().collect::<Vec<Fp>>();
+    let base_cols = [(); 2].map(|()| (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>());
+    let extension_col = (0..n).map(|_| Fq3::rand(&mut rng)).collect::<Vec<Fq3>>();
+    let challenges = Challenges::new(vec![Fq3::rand(&mut rng)]);
+    let hints = [Fq3::rand(&mut rng)];
+    let composition_coeffs = (0..air.num_composition_constraint_coeffs())
+        .map(|_| Fq3::rand(&mut rng))
+        .collect::<Vec<Fq3>>();
+    let eval_expr = air
+        .composition_constraint()
+        .map_leaves(&mut |leaf| match leaf {
+            CompositionItem::Item(item) => item.clone(),
+            CompositionItem::CompositionCoeff(i) => {
+                AlgebraicItem::Constant(FieldVariant::Fq(composition_coeffs[*i]))
+            }
+        });
+
+    let generated_evals = CounterAirConfig::eval_constraint(
+        air.composition_constraint(),
+        &challenges,
+        &hints,
+        &composition_coeffs,
+        lde_step,
+        x_lde.to_vec_in(GpuAllocator),
+        &[&base_cols[0], &base_cols[1]],
+        Some(&[&extension_col]),
+    );
+    let interpreted_evals = ministark::eval_cpu::eval(
+        &eval_expr,
+        &challenges,
+        &hints,
+        lde_step,
+        CounterAirConfig::domain_offset(),
+        &x_lde,
+        &[&base_cols[0], &base_cols[1]],
+        Some(&[&extension_col]),
+    );
+
+    assert_eq!(generated_evals.0, interpreted_evals.0);
+}
+
+#[test]
+fn generated_ood_evaluator_matches_interpreter() {
+    use AlgebraicItem::*;
+    use CompositionItem::*;
+    let air = air();
+    let mut rng = ark_std::test_rng();
+    let x = Fq3::rand(&mut rng);
+    let challenges = Challenges::new(vec![Fq3::rand(&mut rng)]);
+    let hints = Hints::new(vec![(0, Fq3::rand(&mut rng))]);
+    let composition_coeffs = (0..air.num_composition_constraint_coeffs())
+        .map(|_| Fq3::rand(&mut rng))
+        .collect::<Vec<Fq3>>();
+    let trace_ood_evals = air
+        .trace_arguments()
+        .into_iter()
+        .map(|arg| (arg, Fq3::rand(&mut rng)))
+        .collect::<BTreeMap<(usize, isize), Fq3>>();
+
+    let expected = air
+        .composition_constraint()
+        .graph_eval(&mut |leaf| match leaf {
+            Item(X) => FieldVariant::Fq(x),
+            Item(Constant(v)) => *v,
+            &Item(Challenge(round, i)) => FieldVariant::Fq(challenges[(round, i)]),
+            Item(Hint(i)) => FieldVariant::Fq(hints[*i]),
+            Item(Periodic(col)) => {
+                let point = x.pow([(TRACE_LEN / col.interval_size()) as u64]);
+                let coeffs = col.coeffs().iter().map(FieldVariant::as_fq);
+                FieldVariant::Fq(horner_evaluate(&coeffs.collect::<Vec<_>>(), &point))
+            }
+            Item(Trace(i, j)) => FieldVariant::Fq(trace_ood_evals[&(*i, *j)]),
+            CompositionCoeff(i) => FieldVariant::Fq(composition_coeffs[*i]),
+        })
+        .as_fq();
+
+    assert!(!expected.is_zero());
+    assert_eq!(
+        ood_constraint_evaluation(
+            &composition_coeffs,
+            &challenges,
+            &hints,
+            &trace_ood_evals,
+            &air,
+            x
+        ),
+        expected
+    );
+}
+
+#[test]
+fn proof_with_generated_evaluator_verifies() {
+    let claim = CounterClaim::new(Fp::from(START));
+    let (proof, report) =
+        pollster::block_on(claim.prove_with_report(OPTIONS, gen_trace(START))).unwrap();
+
+    assert!(report.lints.is_empty());
+    claim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn generated_evaluator_is_reused_for_other_public_inputs() {
+    let start = 100;
+    let air = Air::<CounterAirConfig>::new(TRACE_LEN, Fp::from(start), OPTIONS).unwrap();
+    assert!(air.lints().is_empty());
+
+    let claim = CounterClaim::new(Fp::from(start));
+    let proof = pollster::block_on(claim.prove(OPTIONS, gen_trace(start))).unwrap();
+
+    claim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn outdated_generated_evaluator_is_linted() {
+    let air = Air::<CounterAirConfig>::new(TRACE_LEN * 2, Fp::from(START), OPTIONS).unwrap();
+
+    let lints = air.lints();
+
+    assert!(matches!(
+        lints[..],
+        [AirLint::OutdatedGeneratedEvaluator { actual, .. }] if actual == generated::FINGERPRINT
+    ));
+}
@@ -0,0 +1,152 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTree;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::merkle::MerkleTree;
+use ministark::random::PublicCoinImpl;
+use ministark::random::SeedablePublicCoin;
+use ministark::stark::Stark;
+use ministark::utils::tests::gen_fib_matrix;
+use ministark::utils::FieldVariant;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::marker::PhantomData;
+
+/// Trace without extension columns
+pub struct TestTrace(pub Matrix<Fp>);
+
+impl Trace for TestTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.0
+    }
+}
+
+/// Marks the trace, public coin and Merkle tree types of a [`Claim`]
+type Types<T, P, M> = PhantomData<fn() -> (T, P, M)>;
+
+/// Proves the AIR `A` with traces of type `T`. The public coin `P` and Merkle
+/// tree `M` are the remaining associated types of the [`Stark`].
+pub struct Claim<
+    A: AirConfig,
+    T = TestTrace,
+    P = PublicCoinImpl<<A as AirConfig>::Fq, Sha256HashFn>,
+    M = MatrixMerkleTreeImpl<Sha256HashFn>,
+> {
+    pub public_inputs: A::PublicInputs,
+    /// Seed of the rng for masks and salts
+    pub zk_seed: u64,
+    _types: Types<T, P, M>,
+}
+
+impl<A: AirConfig, T, P, M> Claim<A, T, P, M> {
+    pub const fn new(public_inputs: A::PublicInputs) -> Self {
+        Self {
+            public_inputs,
+            zk_seed: 0,
+            _types: PhantomData,
+        }
+    }
+
+    pub const fn with_zk_seed(mut self, zk_seed: u64) -> Self {
+        self.zk_seed = zk_seed;
+        self
+    }
+}
+
+impl<A, T, P, M> Stark for Claim<A, T, P, M>
+where
+    A: AirConfig,
+    A::PublicInputs: Send + Sync,
+    T: Trace<Fp = A::Fp, Fq = A::Fq>,
+    P: SeedablePublicCoin<Digest = <M as MerkleTree>::Root, Field = A::Fq>,
+    M: MatrixMerkleTree<A::Fp> + MatrixMerkleTree<A::Fq>,
+{
+    type Fp = A::Fp;
+    type Fq = A::Fq;
+    type AirConfig = A;
+    type Digest = <M as MerkleTree>::Root;
+    type PublicCoin = P;
+    type MerkleTree = M;
+    type Witness = T;
+    type Trace = T;
+
+    fn get_public_inputs(&self) -> A::PublicInputs {
+        self.public_inputs.clone()
+    }
+
+    fn generate_trace(&self, witness: T) -> T {
+        witness
+    }
+
+    fn gen_zk_rng(&self) -> impl rand::RngCore {
+        ChaCha20Rng::seed_from_u64(self.zk_seed)
+    }
+
+    fn gen_public_coin(&self, air: &Air<A>) -> P {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        air.trace_len().serialize_compressed(&mut seed).unwrap();
+        air.options().serialize_compressed(&mut seed).unwrap();
+        P::new_from_seed(&seed)
+    }
+}
+
+/// Fibonacci sequence over two columns. The public input is the last value.
+pub struct FibAirConfig;
+
+impl AirConfig for FibAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(_: usize, claimed_last_value: &Fp, _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(vec![(0, *claimed_last_value)])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        use AlgebraicItem::*;
+        let trace_xs = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let first_trace_x = Constant(FieldVariant::Fp(trace_xs.element(0)));
+        let last_trace_x = Constant(FieldVariant::Fp(trace_xs.element(trace_len - 1)));
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        let every_row_except_last = (X - &last_trace_x) / (X.pow(trace_len) - &one);
+        vec![
+            (0.curr() - &one) / (X - &first_trace_x),
+            (1.curr() - &one) / (X - &first_trace_x),
+            (0.next() - 0.curr() - 1.curr()) * &every_row_except_last,
+            (1.next() - 0.next() - 1.curr()) * &every_row_except_last,
+            (1.curr() - Hint(0)) / (X - &last_trace_x),
+        ]
+        .into_iter()
+        .map(Constraint::new)
+        .collect()
+    }
+}
+
+/// Generates a Fibonacci trace with `n` rows and its last value
+pub fn gen_fib_trace(n: usize) -> (TestTrace, Fp) {
+    let matrix = gen_fib_matrix::<Fp>(n);
+    let last_value = *matrix[1].last().unwrap();
+    (TestTrace(matrix), last_value)
+}
@@ -9,12 +9,23 @@ use ark_poly::DenseUVPolynomial;
 use ark_poly::EvaluationDomain;
 use ark_poly::Polynomial;
 use ark_poly::Radix2EvaluationDomain;
+use ark_std::rand::rngs::StdRng;
 use ark_std::rand::seq::SliceRandom;
+use ark_std::rand::SeedableRng;
+use ministark::bytecode::Inputs;
+use ministark::bytecode::Program;
+use ministark::challenges::Challenges;
 use ministark::constraints::AlgebraicItem;
 use ministark::constraints::Constraint;
 use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::Names;
+use ministark::constraints::PeriodicColumn;
 use ministark::constraints::VerifierChallenge;
+use ministark::constraints::VirtualColumn;
 use ministark::expression::Expr;
+use ministark::expression::ExprArena;
+use ministark::printer::ExprPrinter;
+use ministark::printer::Notation;
 use ministark::utils;
 use ministark::utils::tests::gen_binary_valued_matrix;
 use ministark::utils::tests::gen_fib_matrix;
@@ -23,29 +34,130 @@ use ministark::utils::GpuAllocator;
 use ministark::Matrix;
 use ministark::StarkExtensionOf;
 use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
 use ministark_gpu::GpuFftField;
 use ministark_gpu::GpuField;
 use num_traits::Pow;
+use proptest::prelude::*;
+use std::sync::Arc;
 
-// TODO: handle
-// #[test]
-// fn expressions_are_equal() {
-//     use AlgebraicItem::*;
-//     let mut rng = ark_std::test_rng();
-//     let x = Fp::rand(&mut rng);
-//     let left: Expr<AlgebraicItem<Fp>> = X.into();
-//     let right: Expr<AlgebraicItem<Fp>> = X.pow(2) / X;
-//     assert_eq!(left.evaluation_hash(x), right.evaluation_hash(x));
-// }
-// #[test]
-// fn expressions_are_unequal() {
-//     use AlgebraicItem::*;
-//     let mut rng = ark_std::test_rng();
-//     let x = Fp::rand(&mut rng);
-//     let left: Expr<AlgebraicItem<Fp>> = X;
-//     let right: Expr<AlgebraicItem<Fp>> = X.pow(3) / X;
-//     assert_ne!(left.evaluation_hash(x), right.evaluation_hash(x));
-// }
+#[test]
+fn expressions_are_equal() {
+    use AlgebraicItem::*;
+    let mut rng = ark_std::test_rng();
+    let x = Fp::rand(&mut rng);
+    let left: Expr<AlgebraicItem<Fp>> = X.into();
+    let right: Expr<AlgebraicItem<Fp>> = X.pow(2) / X;
+    assert_eq!(left.evaluation_hash(x), right.evaluation_hash(x));
+}
+
+#[test]
+fn expressions_are_unequal() {
+    use AlgebraicItem::*;
+    let mut rng = ark_std::test_rng();
+    let x = Fp::rand(&mut rng);
+    let left: Expr<AlgebraicItem<Fp>> = X.into();
+    let right: Expr<AlgebraicItem<Fp>> = X.pow(3) / X;
+    assert_ne!(left.evaluation_hash(x), right.evaluation_hash(x));
+}
+
+#[test]
+fn semantically_equal_constraints() {
+    let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+    let left: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> =
+        (0.curr() + &one) * (0.curr() - &one) + 1.next() * 0.challenge();
+    let right = 0.challenge() * 1.next() + 0.curr().pow(2) - &one;
+    let different = 0.challenge() * 1.curr() + 0.curr().pow(2) - &one;
+
+    assert!(left.semantically_eq::<Fp>(&right));
+    assert!(!left.semantically_eq::<Fp>(&different));
+}
+
+#[test]
+fn row_helpers_divide_by_vanishing_polynomials() {
+    use AlgebraicItem::*;
+    let n = 64;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let x = |row| Constant(FieldVariant::Fp(trace_domain.element(row)));
+    let every_row = X.pow(n) - &one;
+    let expr: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = 0.next() - 0.curr() * 1.curr();
+
+    assert!(0.first().semantically_eq::<Fp>(&(0.curr() / (X - &one))));
+    assert!(0
+        .last(n)
+        .semantically_eq::<Fp>(&(0.curr() / (X - x(n - 1)))));
+    assert!(0
+        .at_row(5, n)
+        .semantically_eq::<Fp>(&(0.curr() / (X - x(5)))));
+    assert!(expr
+        .clone()
+        .every_row_except_last(n)
+        .semantically_eq::<Fp>(&(&expr * (X - x(n - 1)) / &every_row)));
+    // rows 3, 11, 19, ...
+    let every_8th_row = (0..n / 8).map(|i| X - x(3 + 8 * i)).product::<Expr<_>>();
+    assert!(0
+        .every(8, 3, n)
+        .semantically_eq::<Fp>(&(0.curr() / every_8th_row)));
+    assert!(0
+        .every(1, 0, n)
+        .semantically_eq::<Fp>(&(0.curr() / every_row)));
+}
+
+#[test]
+fn virtual_columns_read_interleaved_rows() {
+    use AlgebraicItem::*;
+    let n = 64;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let x = |row| Constant(FieldVariant::Fp(trace_domain.element(row)));
+    // rows 1, 5, 9, ... of column 3
+    let column = VirtualColumn::new(3, 4, 1);
+
+    assert_eq!(column.physical_row(2), 9);
+    assert_eq!(column.num_rows(n), 16);
+    assert!(column.next::<FieldVariant<Fp, Fp>>() == 3.offset(5));
+    assert!(column.offset::<FieldVariant<Fp, Fp>>(-1) == 3.offset(-3));
+    assert!(column
+        .last(n)
+        .semantically_eq::<Fp>(&(3.offset(1) / (X - x(60)))));
+    assert!(column
+        .every_row_except_last(n)
+        .semantically_eq::<Fp>(&(3.offset(1) * (X - x(60)) / (X.pow(16) - &one))));
+    // rows 1, 3, 5, ... of the virtual column
+    let every_other_row = (0..8).map(|i| X - x(4 + 8 * i)).product::<Expr<_>>();
+    assert!(column
+        .every(2, 1, n)
+        .semantically_eq::<Fp>(&(3.offset(1) / every_other_row)));
+}
+
+#[test]
+fn periodic_column_from_evals_matches_rows() {
+    let n = 16;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let mut rng = StdRng::seed_from_u64(0);
+    let base_evals = [3u8, 1, 4, 1].map(|v| FieldVariant::<Fp, Fq3>::Fp(Fp::from(v)));
+    let mut extension_evals = base_evals;
+    extension_evals[2] = FieldVariant::Fq(Fq3::rand(&mut rng));
+
+    for evals in [base_evals, extension_evals] {
+        let column = PeriodicColumn::from_evals(&evals);
+        let coeffs = column.coeffs().iter().map(FieldVariant::as_fq);
+        let coeffs = coeffs.collect::<Vec<Fq3>>();
+
+        assert_eq!(column.interval_size(), 4);
+        for (row, x) in trace_domain.elements().enumerate() {
+            let point = Fq3::from(x.pow([(n / 4) as u64]));
+            let eval = utils::horner_evaluate(&coeffs, &point);
+            assert_eq!(eval, evals[row % 4].as_fq());
+        }
+    }
+    let base_column = PeriodicColumn::from_evals(&base_evals);
+    assert!(base_column
+        .coeffs()
+        .iter()
+        .all(|coeff| matches!(coeff, FieldVariant::Fp(_))));
+}
 
 #[test]
 fn constraint_degree() {
@@ -74,7 +186,7 @@ fn constraint_with_challenges() {
             &Constant(v) => FieldVariant::Fp(v),
             &Hint(_) => unreachable!(),
             &Periodic(_) => todo!(),
-            &Challenge(i) => FieldVariant::Fp(challenges[i]),
+            &Challenge(_, i) => FieldVariant::Fp(challenges[i]),
             &Trace(i, j) => {
                 assert_eq!(0, j);
                 FieldVariant::Fp(col_values[i])
@@ -83,6 +195,320 @@ fn constraint_with_challenges() {
         .is_zero());
 }
 
+#[test]
+fn print_constraint() {
+    use AlgebraicItem::*;
+    let one = Constant(Fp::one());
+    let constraint: Expr<AlgebraicItem<Fp>> =
+        3.next() - 6.curr() * 7.curr() + (0.curr() - &one) / (Expr::from(X) - 0.challenge()).pow(2);
+
+    assert_eq!(
+        constraint.to_string(),
+        "col3[+1] - col6 * col7 + (col0 - 1) / (x - challenge0)^2"
+    );
+}
+
+#[test]
+fn print_shared_nodes_as_bindings() {
+    let shared: Expr<AlgebraicItem<Fp>> = 0.curr() * 1.offset(-1);
+    let constraint = (&shared + &shared) * (&shared - 2.curr());
+
+    let printed = ExprPrinter::new(&constraint.reuse_shared_nodes()).to_string();
+
+    assert_eq!(
+        printed,
+        "let t0 = col0 * col1[-1];\n(t0 + t0) * (t0 - col2)"
+    );
+}
+
+#[test]
+fn print_constraint_as_latex() {
+    use AlgebraicItem::*;
+    let constraint: Expr<AlgebraicItem<Fp>> = (0.next() - 0.curr().pow(2)) / (X - Hint(0));
+
+    let printed = ExprPrinter::new(&constraint)
+        .with_notation(Notation::Latex)
+        .to_string();
+
+    assert_eq!(
+        printed,
+        "\\frac{\\mathrm{col}_{0}[+1] - {\\mathrm{col}_{0}}^{2}}{x - h_{0}}"
+    );
+}
+
+#[test]
+fn print_virtual_columns_in_their_rows() {
+    let named = VirtualColumn::new(0, 4, 1).named("square");
+    let unnamed = VirtualColumn::new(0, 4, 2);
+    let names = Names::default().with_virtual_columns([named, unnamed]);
+    let constraint: Expr<AlgebraicItem<Fp>> = named.next() - unnamed.curr() * 0.curr();
+
+    let printed = ExprPrinter::new(&constraint).with_names(&names).to_string();
+
+    assert_eq!(names.virtual_column(0, 5), Some((named, 1)));
+    assert_eq!(names.virtual_column(0, 0), None);
+    assert_eq!(printed, "square[+1] - col0[2::4] * col0");
+}
+
+#[test]
+fn expressions_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>();
+    assert_send_sync::<ExprArena<AlgebraicItem<FieldVariant<Fp, Fp>>>>();
+}
+
+#[test]
+fn arena_stores_equal_subexpressions_once() {
+    let constraint: Expr<AlgebraicItem<Fp>> =
+        (0.curr() * 1.curr() + 2.curr()) * (0.curr() * 1.curr() - 2.curr());
+    let mut arena = ExprArena::new();
+
+    let root = arena.insert(&constraint);
+
+    // leaves col0, col1 and col2 and nodes col0 * col1, -col2, two additions
+    // and the multiplication at the root
+    assert_eq!(arena.len(), 8);
+    assert_eq!(root.index(), 7);
+    assert_eq!(arena.insert(&constraint.clone()), root);
+    let shared = arena.to_expr(root);
+    let Expr::Mul(a, b) = shared else {
+        panic!("expected multiplication")
+    };
+    let (Expr::Add(a, _), Expr::Add(b, _)) = (&*a, &*b) else {
+        panic!("expected addition")
+    };
+    assert!(Arc::ptr_eq(a, b));
+}
+
+#[test]
+fn traverse_mut_keeps_shared_nodes_shared() {
+    use AlgebraicItem::*;
+    let shared: Arc<Expr<AlgebraicItem<Fp>>> = Arc::new(0.curr() + 1.curr());
+    let mut constraint = Expr::Mul(Arc::clone(&shared), Arc::clone(&shared));
+    let mut num_visits = 0;
+
+    constraint.traverse_mut(&mut |node| {
+        num_visits += 1;
+        if let Expr::Leaf(Trace(col, _)) = node {
+            *col += 2;
+        }
+    });
+
+    // the leaves, the addition and the multiplication are each visited once
+    assert_eq!(num_visits, 4);
+    let Expr::Mul(a, b) = constraint else {
+        panic!("expected multiplication")
+    };
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(a.to_string(), "col2 + col3");
+    assert_eq!(shared.to_string(), "col0 + col1");
+}
+
+#[test]
+fn arena_evaluation_matches_tree_evaluation() {
+    use AlgebraicItem::*;
+    let mut rng = ark_std::test_rng();
+    let x = FieldVariant::<Fp, Fp>::Fp(Fp::rand(&mut rng));
+    let trace = [(); 2].map(|()| FieldVariant::<Fp, Fp>::Fp(Fp::rand(&mut rng)));
+    let shared: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = (Expr::from(X) - 0.curr()).pow(3);
+    let constraint = (&shared * 1.curr() + &shared) / (Expr::from(X) + 1.curr());
+    let mut eval_leaf = |leaf: &AlgebraicItem<FieldVariant<Fp, Fp>>| match *leaf {
+        X => x,
+        Trace(col, _) => trace[col],
+        _ => unreachable!(),
+    };
+
+    let expected = constraint.eval(&mut eval_leaf);
+    let mut arena = ExprArena::new();
+    let root = arena.insert(&constraint);
+
+    assert_eq!(arena.eval(root, &mut eval_leaf), expected);
+    assert_eq!(constraint.graph_eval(&mut eval_leaf), expected);
+}
+
+#[test]
+fn bytecode_evaluation_matches_tree_evaluation() {
+    use AlgebraicItem::*;
+    let n = 1024;
+    let lde_step = 4;
+    let mut rng = ark_std::test_rng();
+    let lde_domain = Radix2EvaluationDomain::<Fp>::new_coset(n, Fp::GENERATOR).unwrap();
+    let x_lde = lde_domain.elements().collect::<Vec<Fp>>();
+    let base_cols = [(); 2].map(|()| (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>());
+    let extension_col = (0..n).map(|_| Fq3::rand(&mut rng)).collect::<Vec<Fq3>>();
+    let challenges = Challenges::new(vec![Fq3::rand(&mut rng)]);
+    let hints = [Fq3::rand(&mut rng)];
+    let two = Constant(FieldVariant::Fp(Fp::from(2u8)));
+    let base: Expr<AlgebraicItem<FieldVariant<Fp, Fq3>>> = 0.curr() * 1.next() - two;
+    let constraint = (&base * 2.curr() - &base * Challenge(0, 0))
+        / (X.pow(n / lde_step) - 1.offset(-1))
+        + (0.curr() - 1.curr()).pow(3) * Hint(0)
+        + &base / &base;
+
+    let program = Program::compile(&constraint, 2, &challenges, &hints);
+    let inputs = Inputs {
+        lde_step,
+        x_lde: &x_lde,
+        base_trace_lde_cols: &[&base_cols[0], &base_cols[1]],
+        extension_trace_lde_cols: Some(&[&extension_col]),
+        periodic_column_ldes: &[],
+    };
+    let mut result = vec![Fq3::zero(); n];
+    program.eval::<256>(&inputs, &mut result);
+
+    for (i, (v, x)) in result.into_iter().zip(x_lde).enumerate() {
+        let expected = constraint.eval(&mut |leaf| match *leaf {
+            X => FieldVariant::Fp(x),
+            Constant(v) => v,
+            Hint(i) => FieldVariant::Fq(hints[i]),
+            Challenge(round, i) => FieldVariant::Fq(challenges[(round, i)]),
+            Periodic(_) => unreachable!(),
+            Trace(col, offset) => {
+                let pos = (i as isize + lde_step as isize * offset).rem_euclid(n as isize);
+                match col {
+                    0 | 1 => FieldVariant::Fp(base_cols[col][pos as usize]),
+                    _ => FieldVariant::Fq(extension_col[pos as usize]),
+                }
+            }
+        });
+        assert_eq!(v, expected.as_fq(), "mismatch at index {i}");
+    }
+}
+
+#[test]
+fn simplify_folds_constants_and_merges_powers() {
+    use AlgebraicItem::*;
+    let zero = Constant(FieldVariant::<Fp, Fp>::Fp(Fp::zero()));
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let two = Constant(FieldVariant::Fp(Fp::from(2u8)));
+    let three = Constant(FieldVariant::Fp(Fp::from(3u8)));
+    let constraint: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> =
+        (0.curr() * &one + &zero) * 0.curr().pow(2) + two * three + -(-1.curr()) * &zero;
+
+    assert_eq!(constraint.simplify().to_string(), "6 + col0^3");
+}
+
+#[test]
+fn simplify_orders_commutative_operands() {
+    let a: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = 1.curr() * 0.curr() + 2.next();
+    let b: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = 2.next() + 0.curr() * 1.curr();
+
+    assert_eq!(a.simplify(), b.simplify());
+}
+
+#[test]
+fn dedup_shares_algebraically_equal_subexpressions() {
+    let a = 0.curr() * (1.curr() + 2.curr());
+    let b = 0.curr() * 2.curr() + 1.curr() * 0.curr();
+    let expr: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = a * b;
+
+    let deduped = expr.dedup_semantically::<Fp>(&mut StdRng::seed_from_u64(0));
+
+    let Expr::Mul(a, b) = deduped else {
+        panic!("expected a multiplication");
+    };
+    assert!(Arc::ptr_eq(&a, &b));
+}
+
+#[test]
+fn expressions_that_divide_by_zero_are_not_semantically_equal() {
+    use AlgebraicItem::*;
+    let left: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = 0.curr() / (X - X);
+    let right = 1.curr() / (X - X);
+
+    assert!(!left.semantically_eq::<Fp>(&right));
+}
+
+#[test]
+fn simplify_compares_shared_subexpressions_once() {
+    let mut expr: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> = 0.curr() + 1.curr();
+    for _ in 0..64 {
+        expr = expr.clone() + expr;
+    }
+
+    let Expr::Add(a, b) = expr.simplify() else {
+        panic!("expected an addition");
+    };
+
+    assert_eq!(a, b);
+}
+
+fn arb_leaf() -> impl Strategy<Value = Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    prop_oneof![
+        Just(X.into()),
+        (0u64..4).prop_map(|v| Constant(FieldVariant::Fp(Fp::from(v))).into()),
+        (0usize..3, -1isize..=1).prop_map(|(col, offset)| Trace(col, offset).into()),
+        (0usize..2, 0usize..2).prop_map(|(round, index)| Challenge(round, index).into()),
+        (0usize..2).prop_map(|index| Hint(index).into()),
+        (0u64..4, 0u64..4).prop_map(|(a, b)| {
+            let coeffs = vec![FieldVariant::Fp(Fp::from(a)), FieldVariant::Fp(Fp::from(b))];
+            Periodic(PeriodicColumn::new(coeffs, 2)).into()
+        }),
+    ]
+}
+
+/// Divisors are built from leaves that never evaluate to zero
+fn arb_divisor() -> impl Strategy<Value = Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    prop_oneof![
+        Just(X.into()),
+        (1u64..4).prop_map(|v| Constant(FieldVariant::Fp(Fp::from(v))).into()),
+        (0usize..3).prop_map(|col| Trace(col, 0).into()),
+    ]
+    .prop_flat_map(|divisor: Expr<_>| (0usize..3).prop_map(move |e| divisor.clone().pow(e)))
+}
+
+fn arb_expr() -> impl Strategy<Value = Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    arb_leaf().prop_recursive(6, 64, 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|a| -a),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| a + b),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| a - b),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| a * b),
+            (inner.clone(), arb_divisor()).prop_map(|(a, b)| a / b),
+            (inner.clone(), 0usize..4).prop_map(|(a, e)| a.pow(e)),
+            // subexpressions shared between both operands
+            inner.clone().prop_map(|a| {
+                let a = Arc::new(a);
+                Expr::Add(Arc::clone(&a), a)
+            }),
+            (inner.clone(), inner).prop_map(|(a, b)| {
+                let a = Arc::new(a);
+                Expr::Mul(Arc::new(Expr::Add(Arc::clone(&a), Arc::new(b))), a)
+            }),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn simplify_preserves_evaluations(expr in arb_expr(), seed in any::<u64>()) {
+        use AlgebraicItem::*;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let x = Fp::rand(&mut rng);
+        let trace = [(); 3].map(|()| [(); 3].map(|()| Fp::rand(&mut rng)));
+        let challenges = [(); 2].map(|()| [(); 2].map(|()| Fp::rand(&mut rng)));
+        let hints = [(); 2].map(|()| Fp::rand(&mut rng));
+        let mut eval_leaf = |leaf: &AlgebraicItem<FieldVariant<Fp, Fp>>| match leaf {
+            X => FieldVariant::Fp(x),
+            &Constant(v) => v,
+            &Trace(col, offset) => FieldVariant::Fp(trace[col][(offset + 1) as usize]),
+            &Challenge(round, index) => FieldVariant::Fp(challenges[round][index]),
+            &Hint(index) => FieldVariant::Fp(hints[index]),
+            Periodic(col) => col.coeffs()[0] + col.coeffs()[1] * FieldVariant::Fp(x),
+        };
+        prop_assume!(!x.is_zero() && trace.iter().all(|col| !col[1].is_zero()));
+
+        let simplified = expr.simplify();
+
+        prop_assert_eq!(expr.eval(&mut eval_leaf), simplified.eval(&mut eval_leaf));
+        let (numerator_degree, _) = Constraint::new(expr).degree(1);
+        let (simplified_numerator_degree, _) = Constraint::new(simplified).degree(1);
+        prop_assert!(simplified_numerator_degree <= numerator_degree);
+    }
+}
+
 #[test]
 fn symbolic_evaluation_with_challenges() {
     let n = 2048;
@@ -141,7 +567,7 @@ fn constraint_multiplication() {
     let f = |val: FieldVariant<Fp, Fp>| {
         move |leaf: &AlgebraicItem<FieldVariant<Fp, Fp>>| match leaf {
             X => one,
-            Challenge(_) => unreachable!(),
+            Challenge(..) => unreachable!(),
             Hint(_) => unreachable!(),
             Periodic(_) => todo!(),
             &Constant(v) => v,
@@ -202,7 +628,7 @@ fn evaluate_binary_constraint() {
     // constrains column 0 values to 0 or 1
     use AlgebraicItem::*;
     let one = Constant(FieldVariant::Fp(Fp::one()));
-    let constraint = Constraint::new(0.curr() * (0.curr() - one));
+    let constraint = Constraint::new(0.curr() * (0.curr() - &one));
     let (numerator_degree, denominator_degree) = constraint.degree(n);
     let blowup = utils::ceil_power_of_two((numerator_degree - denominator_degree) / n);
     let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
@@ -369,8 +795,8 @@ fn evaluate_symbolic<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
             X => FieldVariant::Fp(x),
             &Constant(v) => v,
             &Hint(i) => FieldVariant::Fq(hints[i]),
-            &Challenge(i) => FieldVariant::Fq(challenges[i]),
-            &Periodic(_col) => todo!(),
+            &Challenge(_, i) => FieldVariant::Fq(challenges[i]),
+            Periodic(_) => todo!(),
             &Trace(col_idx, offset) => {
                 let pos = (i as isize + blowup_factor * offset).rem_euclid(n as isize) as usize;
                 let column = &lde_matrix[col_idx];
@@ -0,0 +1,233 @@
+// @generated by ministark::codegen. Do not edit.
+
+pub const FINGERPRINT: u64 = 0x97a18702f576350d;
+
+pub fn evaluator<Fp, Fq>() -> ::ministark::codegen::GeneratedEvaluator<Fp, Fq>
+where
+    Fp: ::ministark_gpu::GpuFftField<FftField = Fp> + ::ark_ff::FftField,
+    Fq: ::ministark::StarkExtensionOf<Fp>,
+{
+    ::ministark::codegen::GeneratedEvaluator {
+        fingerprint: FINGERPRINT,
+        eval_lde: eval_lde::<Fp, Fq>,
+        eval_ood: eval_ood::<Fp, Fq>,
+    }
+}
+
+/// Evaluates the composition constraint over the LDE domain
+pub fn eval_lde<Fp, Fq>(inputs: &::ministark::codegen::LdeInputs<'_, Fp, Fq>, result: &mut [Fq])
+where
+    Fp: ::ministark_gpu::GpuFftField<FftField = Fp> + ::ark_ff::FftField,
+    Fq: ::ministark::StarkExtensionOf<Fp>,
+{
+    if result.len() >= ::ministark::codegen::CHUNK_SIZE {
+        eval_lde_chunks::<Fp, Fq, { ::ministark::codegen::CHUNK_SIZE }>(inputs, result)
+    } else {
+        eval_lde_chunks::<Fp, Fq, 1>(inputs, result)
+    }
+}
+
+#[allow(clippy::all, clippy::pedantic, clippy::nursery)]
+fn eval_lde_chunks<Fp, Fq, const N: usize>(
+    inputs: &::ministark::codegen::LdeInputs<'_, Fp, Fq>,
+    result: &mut [Fq],
+)
+where
+    Fp: ::ministark_gpu::GpuFftField<FftField = Fp> + ::ark_ff::FftField,
+    Fq: ::ministark::StarkExtensionOf<Fp>,
+{
+    let ::ministark::utils::FieldVariant::Fp(v1) = inputs.constants[0] else { unreachable!() };
+    let ::ministark::utils::FieldVariant::Fp(v3) = inputs.constants[1] else { unreachable!() };
+    let v14: Fq = inputs.composition_coeffs[0];
+    let v16: Fq = inputs.composition_coeffs[1];
+    let v25: Fq = inputs.composition_coeffs[2];
+    let v27: Fq = inputs.composition_coeffs[3];
+    let v32: Fq = inputs.challenges[(0, 0)];
+    let i32: Fq = ::ark_ff::Field::inverse(&v32).unwrap();
+    let v38: Fq = inputs.composition_coeffs[4];
+    let v40: Fq = inputs.composition_coeffs[5];
+    let v44: Fq = inputs.hints[0];
+    let v45: Fq = -v44;
+    let v49: Fq = inputs.composition_coeffs[6];
+    let v51: Fq = inputs.composition_coeffs[7];
+    let v55: Fq = v44 * i32;
+    let v56: Fq = -v55;
+    let v59: Fq = inputs.composition_coeffs[8];
+    let v61: Fq = inputs.composition_coeffs[9];
+    let ::ministark::utils::FieldVariant::Fp(v65) = inputs.constants[2] else { unreachable!() };
+    let v69: Fq = inputs.composition_coeffs[10];
+    let v71: Fq = inputs.composition_coeffs[11];
+    let x_lde = inputs.lde.x_lde;
+    let g = x_lde[1 % x_lde.len()] / x_lde[0];
+    let g1 = ::ark_ff::Field::pow(&g, [1]);
+    let g63 = ::ark_ff::Field::pow(&g, [63]);
+    let g64 = ::ark_ff::Field::pow(&g, [64]);
+    ::ministark::codegen::for_each_chunk::<Fq, N>(result, |offset, chunk| {
+        let x = ::ministark::codegen::lde_chunk::<Fp, N>(inputs.lde.x_lde, 1, offset, 0);
+        let x1 = ::ministark::codegen::geometric::<Fp, N>(::ark_ff::Field::pow(&x_lde[offset], [1]), g1);
+        let x63 = ::ministark::codegen::geometric::<Fp, N>(::ark_ff::Field::pow(&x_lde[offset], [63]), g63);
+        let x64 = ::ministark::codegen::geometric::<Fp, N>(::ark_ff::Field::pow(&x_lde[offset], [64]), g64);
+        let t1 = ::ministark::codegen::lde_chunk::<Fp, N>(inputs.lde.base_trace_lde_cols[0], inputs.lde.lde_step, offset, 0);
+        let t0 = ::ministark::codegen::lde_chunk::<Fp, N>(inputs.lde.base_trace_lde_cols[0], inputs.lde.lde_step, offset, 1);
+        let t2 = ::ministark::codegen::lde_chunk::<Fp, N>(inputs.lde.base_trace_lde_cols[1], inputs.lde.lde_step, offset, 0);
+        let t3 = ::ministark::codegen::lde_chunk::<Fq, N>(inputs.lde.extension_trace_lde_cols.unwrap()[0], inputs.lde.lde_step, offset, 0);
+        let ::ministark::utils::FieldVariant::Fp(p0) = &inputs.lde.periodic_column_ldes[0] else { unreachable!() };
+        let p0 = ::ministark::codegen::lde_chunk::<Fp, N>(p0, 1, offset, 0);
+        let mut d11 = [<Fp as ::ark_ff::Field>::ZERO; N];
+        let mut d2 = [<Fp as ::ark_ff::Field>::ZERO; N];
+        let mut d67 = [<Fp as ::ark_ff::Field>::ZERO; N];
+        for i in 0..N {
+            let v0: Fp = x[i];
+            let v2: Fp = v0 + v1;
+            let v10: Fp = x64[i];
+            let v11: Fp = v3 + v10;
+            let v67: Fp = v0 + v3;
+            d11[i] = v11;
+            d2[i] = v2;
+            d67[i] = v67;
+        }
+        ::ark_ff::batch_inversion(&mut d11);
+        ::ark_ff::batch_inversion(&mut d2);
+        ::ark_ff::batch_inversion(&mut d67);
+        for i in 0..N {
+            let v0: Fp = x[i];
+            let v2: Fp = v0 + v1;
+            let v4: Fp = t0[i];
+            let v5: Fp = t1[i];
+            let v6: Fp = -v5;
+            let v7: Fp = v4 + v6;
+            let v8: Fp = v3 + v7;
+            let v9: Fp = v2 * v8;
+            let v12: Fp = v9 * d11[i];
+            let v13: Fp = x63[i];
+            let v15: Fq = v14 * v13;
+            let v17: Fq = v15 + v16;
+            let v18: Fq = v17 * v12;
+            let v19: Fp = t2[i];
+            let v20: Fp = p0[i];
+            let v21: Fp = -v20;
+            let v22: Fp = v19 + v21;
+            let v23: Fp = v2 * v22;
+            let v24: Fp = v23 * d11[i];
+            let v26: Fq = v25 * v13;
+            let v28: Fq = v26 + v27;
+            let v29: Fq = v28 * v24;
+            let v30: Fq = v18 + v29;
+            let v31: Fq = t3[i];
+            let v33: Fq = v32 * v5;
+            let v34: Fq = -v33;
+            let v35: Fq = v31 + v34;
+            let v36: Fq = v35 * v2;
+            let v37: Fq = v36 * d11[i];
+            let v39: Fq = v38 * v13;
+            let v41: Fq = v39 + v40;
+            let v42: Fq = v37 * v41;
+            let v43: Fq = v30 + v42;
+            let v46: Fq = v31 + v45;
+            let v47: Fq = v46 * d2[i];
+            let v48: Fp = x1[i];
+            let v50: Fq = v49 * v48;
+            let v52: Fq = v50 + v51;
+            let v53: Fq = v47 * v52;
+            let v54: Fq = v43 + v53;
+            let v57: Fq = v56 + v5;
+            let v58: Fq = v57 * d2[i];
+            let v60: Fq = v59 * v48;
+            let v62: Fq = v60 + v61;
+            let v63: Fq = v58 * v62;
+            let v64: Fq = v54 + v63;
+            let v66: Fp = v65 + v5;
+            let v68: Fp = v66 * d67[i];
+            let v70: Fq = v69 * v48;
+            let v72: Fq = v70 + v71;
+            let v73: Fq = v72 * v68;
+            let v74: Fq = v64 + v73;
+            chunk[i] = v74;
+        }
+    });
+}
+
+/// Evaluates the composition constraint at the out-of-domain point
+#[allow(clippy::all, clippy::pedantic, clippy::nursery)]
+pub fn eval_ood<Fp, Fq>(inputs: &::ministark::codegen::OodInputs<'_, Fq>) -> Fq
+where
+    Fp: ::ministark_gpu::GpuFftField<FftField = Fp> + ::ark_ff::FftField,
+    Fq: ::ministark::StarkExtensionOf<Fp>,
+{
+    let v0: Fq = inputs.x;
+    let v1: Fq = inputs.constants[0];
+    let v2: Fq = v0 + v1;
+    let v3: Fq = inputs.constants[1];
+    let v4: Fq = inputs.trace_ood_evals[&(0, 1)];
+    let v5: Fq = inputs.trace_ood_evals[&(0, 0)];
+    let v6: Fq = -v5;
+    let v7: Fq = v4 + v6;
+    let v8: Fq = v3 + v7;
+    let v9: Fq = v2 * v8;
+    let v10: Fq = ::ark_ff::Field::pow(&v0, [64]);
+    let v11: Fq = v3 + v10;
+    let v12: Fq = v9 / v11;
+    let v13: Fq = ::ark_ff::Field::pow(&v0, [63]);
+    let v14: Fq = inputs.composition_coeffs[0];
+    let v15: Fq = v13 * v14;
+    let v16: Fq = inputs.composition_coeffs[1];
+    let v17: Fq = v15 + v16;
+    let v18: Fq = v12 * v17;
+    let v19: Fq = inputs.trace_ood_evals[&(1, 0)];
+    let v20: Fq = inputs.periodic_evals[0];
+    let v21: Fq = -v20;
+    let v22: Fq = v19 + v21;
+    let v23: Fq = v2 * v22;
+    let v24: Fq = v23 / v11;
+    let v25: Fq = inputs.composition_coeffs[2];
+    let v26: Fq = v13 * v25;
+    let v27: Fq = inputs.composition_coeffs[3];
+    let v28: Fq = v26 + v27;
+    let v29: Fq = v24 * v28;
+    let v30: Fq = v18 + v29;
+    let v31: Fq = inputs.trace_ood_evals[&(2, 0)];
+    let v32: Fq = inputs.challenges[(0, 0)];
+    let v33: Fq = v32 * v5;
+    let v34: Fq = -v33;
+    let v35: Fq = v31 + v34;
+    let v36: Fq = v2 * v35;
+    let v37: Fq = v36 / v11;
+    let v38: Fq = inputs.composition_coeffs[4];
+    let v39: Fq = v13 * v38;
+    let v40: Fq = inputs.composition_coeffs[5];
+    let v41: Fq = v39 + v40;
+    let v42: Fq = v37 * v41;
+    let v43: Fq = v30 + v42;
+    let v44: Fq = inputs.hints[0];
+    let v45: Fq = -v44;
+    let v46: Fq = v31 + v45;
+    let v47: Fq = v46 / v2;
+    let v48: Fq = ::ark_ff::Field::pow(&v0, [1]);
+    let v49: Fq = inputs.composition_coeffs[6];
+    let v50: Fq = v48 * v49;
+    let v51: Fq = inputs.composition_coeffs[7];
+    let v52: Fq = v50 + v51;
+    let v53: Fq = v47 * v52;
+    let v54: Fq = v43 + v53;
+    let v55: Fq = v44 / v32;
+    let v56: Fq = -v55;
+    let v57: Fq = v5 + v56;
+    let v58: Fq = v57 / v2;
+    let v59: Fq = inputs.composition_coeffs[8];
+    let v60: Fq = v48 * v59;
+    let v61: Fq = inputs.composition_coeffs[9];
+    let v62: Fq = v60 + v61;
+    let v63: Fq = v58 * v62;
+    let v64: Fq = v54 + v63;
+    let v65: Fq = inputs.constants[2];
+    let v66: Fq = v65 + v5;
+    let v67: Fq = v0 + v3;
+    let v68: Fq = v66 / v67;
+    let v69: Fq = inputs.composition_coeffs[10];
+    let v70: Fq = v48 * v69;
+    let v71: Fq = inputs.composition_coeffs[11];
+    let v72: Fq = v70 + v71;
+    let v73: Fq = v68 * v72;
+    let v74: Fq = v64 + v73;
+    v74
+}
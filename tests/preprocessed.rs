@@ -0,0 +1,260 @@
+mod common;
+
+use ark_ff::One;
+use ark_serialize::CanonicalSerialize;
+use common::Claim;
+use common::TestTrace;
+use ministark::air::AirConfig;
+use ministark::assertions::Assertion;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MerkleCap;
+use ministark::merkle::SaltedMatrixMerkleTreeImpl;
+use ministark::prover::ProvingError;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::verifier::VerificationError;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Digest;
+use sha2::Sha256;
+
+const TRACE_LEN: usize = 64;
+const OPTIONS: ProofOptions = ProofOptions::new(16, 8, 0, 2, 4);
+
+const COUNTER: usize = 0;
+const PRODUCT: usize = 1;
+/// The first preprocessed column follows the base columns
+const CONSTANT: usize = 2;
+
+fn constant(row: usize) -> Fp {
+    let row = row as u64;
+    Fp::from(row * row * 7 + 3)
+}
+
+/// Multiplies a counter with constants that are the same in every proof
+struct ProductAirConfig;
+
+impl AirConfig for ProductAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    const NUM_PREPROCESSED_COLUMNS: usize = 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    /// The first value of the counter
+    type PublicInputs = Fp;
+
+    fn preprocessed_columns(trace_len: usize) -> Option<Matrix<Fp>> {
+        let constants = (0..trace_len).map(|row| vec![constant(row)]).collect();
+        Some(Matrix::from_rows(constants))
+    }
+
+    fn assertions(_trace_len: usize, start: &Fp) -> Vec<Assertion<Fp>> {
+        vec![Assertion::single(COUNTER, 0, *start)]
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        vec![
+            (COUNTER.next() - COUNTER.curr() - one).every_row_except_last(trace_len),
+            (PRODUCT.curr() - COUNTER.curr() * CONSTANT.curr()).every(1, 0, trace_len),
+        ]
+    }
+}
+
+type ProductClaim = Claim<ProductAirConfig>;
+
+/// Commits with a hiding Merkle tree salted by a seeded rng
+type SaltedProductClaim = Claim<
+    ProductAirConfig,
+    TestTrace,
+    PublicCoinImpl<Fp, Sha256HashFn>,
+    SaltedMatrixMerkleTreeImpl<Sha256HashFn>,
+>;
+
+fn prove_salted(
+    options: ProofOptions,
+    seed: u64,
+) -> (SaltedProductClaim, Proof<SaltedProductClaim>) {
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+    let claim = SaltedProductClaim::new(claim.public_inputs).with_zk_seed(seed);
+    let key = claim.setup(TRACE_LEN, options).unwrap();
+    let proof = pollster::block_on(claim.prove_with_key(&key, trace)).unwrap();
+    (claim, proof)
+}
+
+fn gen_trace(start: u64, trace_len: usize) -> (TestTrace, ProductClaim) {
+    let rows = (0..trace_len)
+        .map(|row| {
+            let count = Fp::from(start + row as u64);
+            vec![count, count * constant(row)]
+        })
+        .collect();
+    let trace = TestTrace(Matrix::from_rows(rows));
+    (trace, ProductClaim::new(Fp::from(start)))
+}
+
+#[test]
+fn proving_key_is_shared_across_proofs() {
+    let key = ProductClaim::new(Fp::one())
+        .setup(TRACE_LEN, OPTIONS)
+        .unwrap();
+    let verification_key = key.verification_key();
+
+    for start in [1, 5] {
+        let (trace, claim) = gen_trace(start, TRACE_LEN);
+        let proof = pollster::block_on(claim.prove_with_key(&key, trace)).unwrap();
+
+        claim.verify_with_key(proof, &verification_key, 1).unwrap();
+    }
+}
+
+#[test]
+fn proof_with_zero_knowledge_verifies() {
+    let options = OPTIONS.with_zero_knowledge();
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+    let key = claim.setup(TRACE_LEN, options).unwrap();
+
+    let proof = pollster::block_on(claim.prove_with_key(&key, trace)).unwrap();
+
+    claim
+        .verify_with_key(proof, &key.verification_key(), 1)
+        .unwrap();
+}
+
+#[test]
+fn proof_without_proving_key_is_an_error() {
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+
+    let result = pollster::block_on(claim.prove(OPTIONS, trace));
+
+    assert!(matches!(result, Err(ProvingError::ProvingKeyRequired)));
+}
+
+#[test]
+fn proof_without_verification_key_is_an_error() {
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+    let key = claim.setup(TRACE_LEN, OPTIONS).unwrap();
+    let proof = pollster::block_on(claim.prove_with_key(&key, trace)).unwrap();
+
+    assert!(matches!(
+        claim.verify(proof, 1),
+        Err(VerificationError::VerificationKeyRequired)
+    ));
+}
+
+#[test]
+fn proof_does_not_verify_with_other_preprocessed_commitment() {
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+    let key = claim.setup(TRACE_LEN, OPTIONS).unwrap();
+    let mut verification_key = key.verification_key();
+    let proof = pollster::block_on(claim.prove_with_key(&key, trace)).unwrap();
+    let other_root = SerdeOutput::new(Sha256::digest(b"other constants"));
+    verification_key.preprocessed_trace_commitment = Some(MerkleCap::from(other_root));
+
+    assert!(claim.verify_with_key(proof, &verification_key, 1).is_err());
+}
+
+#[test]
+fn proof_does_not_verify_with_key_for_other_trace_length() {
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+    let verification_key = claim
+        .setup(TRACE_LEN / 2, OPTIONS)
+        .unwrap()
+        .verification_key();
+    let key = claim.setup(TRACE_LEN, OPTIONS).unwrap();
+    let proof = pollster::block_on(claim.prove_with_key(&key, trace)).unwrap();
+
+    assert!(matches!(
+        claim.verify_with_key(proof, &verification_key, 1),
+        Err(VerificationError::VerificationKeyMismatch)
+    ));
+}
+
+#[test]
+fn proving_key_for_other_trace_length_is_an_error() {
+    let (trace, claim) = gen_trace(5, TRACE_LEN);
+    let key = claim.setup(TRACE_LEN / 2, OPTIONS).unwrap();
+
+    let result = pollster::block_on(claim.prove_with_key(&key, trace));
+
+    assert!(matches!(
+        result,
+        Err(ProvingError::TraceLengthMismatch {
+            expected: 32,
+            actual: TRACE_LEN
+        })
+    ));
+}
+
+#[test]
+fn violated_preprocessed_constraint_is_an_error() {
+    let (mut trace, claim) = gen_trace(5, TRACE_LEN);
+    trace.0[PRODUCT][9] = Fp::one();
+    let key = claim.setup(TRACE_LEN, OPTIONS).unwrap();
+
+    let error = match pollster::block_on(claim.prove_with_key(&key, trace)) {
+        Ok(_) => panic!("expected proving to fail"),
+        Err(error) => error,
+    };
+
+    assert!(matches!(
+        error,
+        ProvingError::ConstraintsNotSatisfied {
+            constraint: 1,
+            row: 9,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn salted_proof_verifies_with_key() {
+    for options in [OPTIONS, OPTIONS.with_zero_knowledge()] {
+        let (claim, proof) = prove_salted(options, 0);
+        let verification_key = claim.setup(TRACE_LEN, options).unwrap().verification_key();
+
+        claim.verify_with_key(proof, &verification_key, 1).unwrap();
+    }
+}
+
+#[test]
+fn salted_proofs_are_deterministic_with_seeded_rng() {
+    let to_bytes = |proof: &Proof<SaltedProductClaim>| {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+
+    let (_, proof_a) = prove_salted(OPTIONS, 1);
+    let (_, proof_b) = prove_salted(OPTIONS, 1);
+    let (_, proof_c) = prove_salted(OPTIONS, 2);
+
+    assert_eq!(to_bytes(&proof_a), to_bytes(&proof_b));
+    assert_ne!(to_bytes(&proof_a), to_bytes(&proof_c));
+}
+
+#[test]
+fn seeded_salts_are_not_reused_across_trees_or_proofs() {
+    let (claim, proof) = prove_salted(OPTIONS, 1);
+    let (other_trace, other_claim) = gen_trace(6, TRACE_LEN);
+    let other_claim =
+        SaltedProductClaim::new(other_claim.public_inputs).with_zk_seed(claim.zk_seed);
+    let key = other_claim.setup(TRACE_LEN, OPTIONS).unwrap();
+    let other_proof = pollster::block_on(other_claim.prove_with_key(&key, other_trace)).unwrap();
+
+    // every tree is opened at the same rows
+    let queries = proof.trace_queries;
+    let base_salts = queries.base_trace_proof.salts;
+    let preprocessed_salts = queries.preprocessed_trace_proof.unwrap().salts;
+    let composition_salts = queries.composition_trace_proof.salts;
+    let other_base_salts = other_proof.trace_queries.base_trace_proof.salts;
+    assert_ne!(base_salts, preprocessed_salts);
+    assert_ne!(base_salts, composition_salts);
+    assert_ne!(base_salts, other_base_salts);
+}
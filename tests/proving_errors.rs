@@ -0,0 +1,362 @@
+mod common;
+
+use ark_ff::One;
+use ark_ff::Zero;
+use common::Claim;
+use common::TestTrace;
+use ministark::air::AirConfig;
+use ministark::air::AirError;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::PeriodicColumn;
+use ministark::constraints::VirtualColumn;
+use ministark::hints::Hints;
+use ministark::prover::ProvingError;
+use ministark::prover::ProvingPhase;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::verifier::VerificationError;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+
+struct QuarticAirConfig;
+
+impl AirConfig for QuarticAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        // the constraint has degree four in the trace and applies to every row
+        vec![Constraint::new(
+            (0.curr() * 0.curr() * 0.curr() * 0.curr() - 1.curr() - &one)
+                / (X.pow(trace_len) - &one),
+        )
+        .named("quartic")]
+    }
+}
+
+type QuarticClaim = Claim<QuarticAirConfig>;
+
+/// A counter in the even rows and its square in the odd rows of one column
+struct InterleavedAirConfig;
+
+const COUNTER: VirtualColumn = VirtualColumn::new(0, 2, 0);
+const SQUARE: VirtualColumn = VirtualColumn::new(0, 2, 1);
+
+impl AirConfig for InterleavedAirConfig {
+    const NUM_BASE_COLUMNS: usize = 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        vec![
+            COUNTER.first(),
+            (COUNTER.next() - COUNTER.curr() - &one).every_except_last(2, 0, trace_len),
+            (SQUARE.curr() - COUNTER.curr() * COUNTER.curr())
+                .every(2, 0, trace_len)
+                .named("square"),
+        ]
+    }
+}
+
+type InterleavedClaim = Claim<InterleavedAirConfig>;
+
+fn gen_interleaved_trace(num_rows: usize) -> TestTrace {
+    let column = (0..num_rows as u64 / 2)
+        .flat_map(|i| [Fp::from(i), Fp::from(i * i)])
+        .collect::<Vec<Fp>>();
+    TestTrace(Matrix::from_rows(
+        column.into_iter().map(|v| vec![v]).collect(),
+    ))
+}
+
+/// Squares and adds a round constant in each row. The round constants are
+/// only known at runtime.
+struct RoundConstantsAirConfig;
+
+fn round_constants() -> Vec<Fp> {
+    (0..8u64).map(|i| Fp::from(i * i + 7)).collect()
+}
+
+impl AirConfig for RoundConstantsAirConfig {
+    const NUM_BASE_COLUMNS: usize = 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let evals = round_constants()
+            .into_iter()
+            .map(FieldVariant::Fp)
+            .collect::<Vec<_>>();
+        let round_constant = AlgebraicItem::Periodic(PeriodicColumn::from_evals(&evals));
+        vec![(0.next() - 0.curr() * 0.curr() - round_constant).every_row_except_last(trace_len)]
+    }
+}
+
+type RoundConstantsClaim = Claim<RoundConstantsAirConfig>;
+
+fn gen_round_constants_trace(num_rows: usize) -> TestTrace {
+    let constants = round_constants();
+    let column = (0..num_rows).scan(Fp::zero(), |acc, i| {
+        let value = *acc;
+        *acc = value * value + constants[i % constants.len()];
+        Some(vec![value])
+    });
+    TestTrace(Matrix::from_rows(column.collect()))
+}
+
+const OPTIONS: ProofOptions = ProofOptions::new(16, 8, 0, 2, 4);
+
+/// Generates a trace that satisfies the constraints when it has two columns
+fn gen_trace(num_rows: usize, num_cols: usize) -> TestTrace {
+    let mut row = vec![Fp::zero(); num_cols];
+    row[0] = Fp::one();
+    TestTrace(Matrix::from_rows(vec![row; num_rows]))
+}
+
+fn prove(options: ProofOptions, trace: TestTrace) -> ProvingError {
+    match pollster::block_on(QuarticClaim::new(()).prove(options, trace)) {
+        Ok(_) => panic!("expected proving to fail"),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn report_covers_every_phase() {
+    let (proof, report) =
+        pollster::block_on(QuarticClaim::new(()).prove_with_report(OPTIONS, gen_trace(64, 2)))
+            .unwrap();
+
+    assert_eq!(report.phase_durations.len(), 10);
+    assert!(report.phase_duration(ProvingPhase::Fri).is_some());
+    assert_eq!(report.trace_len, 64);
+    assert_eq!(report.lde_domain_size, 64 * 8);
+    assert_eq!(report.base_trace_commitment, proof.base_trace_commitment);
+    assert_eq!(
+        report.fri_layer_commitments.len(),
+        proof.fri_proof.layers.len()
+    );
+    assert!(report.lints.is_empty());
+    QuarticClaim::new(()).verify(proof, 1).unwrap();
+}
+
+#[test]
+fn column_count_mismatch_is_an_error() {
+    let error = prove(OPTIONS, gen_trace(64, 3));
+
+    assert!(matches!(
+        error,
+        ProvingError::BaseColumnCountMismatch {
+            expected: 2,
+            actual: 3
+        }
+    ));
+}
+
+#[test]
+fn trace_length_not_power_of_two_is_an_error() {
+    let error = prove(OPTIONS, gen_trace(48, 2));
+
+    assert!(matches!(
+        error,
+        ProvingError::InvalidAir {
+            source: AirError::TraceLengthNotPowerOfTwo { len: 48 }
+        }
+    ));
+}
+
+#[test]
+fn blowup_factor_too_small_is_an_error() {
+    let options = ProofOptions::new(16, 2, 0, 2, 4);
+
+    let error = prove(options, gen_trace(64, 2));
+
+    assert!(matches!(
+        error,
+        ProvingError::InvalidAir {
+            source: AirError::BlowupFactorTooSmall { actual: 2, .. }
+        }
+    ));
+}
+
+#[test]
+fn unsupported_fri_folding_factor_is_an_error() {
+    let options = ProofOptions::new(16, 8, 0, 3, 4);
+
+    let error = prove(options, gen_trace(64, 2));
+
+    assert!(matches!(
+        error,
+        ProvingError::InvalidFriOptions {
+            folding_factor: 3,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn proof_with_unsupported_fri_options_is_rejected() {
+    let proof = pollster::block_on(QuarticClaim::new(()).prove(OPTIONS, gen_trace(64, 2))).unwrap();
+    let mut bad_folding_factor = proof.clone();
+    bad_folding_factor.options.fri_folding_factor = 3;
+    let mut bad_blowup_factor = proof;
+    bad_blowup_factor.options.lde_blowup_factor = 0;
+
+    assert!(matches!(
+        QuarticClaim::new(()).verify(bad_folding_factor, 1),
+        Err(VerificationError::InvalidFriOptions {
+            folding_factor: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        QuarticClaim::new(()).verify(bad_blowup_factor, 1),
+        Err(VerificationError::InvalidFriOptions {
+            blowup_factor: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn proof_with_missing_values_is_rejected() {
+    let proof = pollster::block_on(QuarticClaim::new(()).prove(OPTIONS, gen_trace(64, 2))).unwrap();
+    let mut missing_trace_ood_eval = proof.clone();
+    missing_trace_ood_eval.execution_trace_ood_evals.pop();
+    let mut missing_composition_ood_eval = proof.clone();
+    missing_composition_ood_eval
+        .composition_trace_ood_evals
+        .pop();
+    let mut missing_base_row = proof.clone();
+    missing_base_row.trace_queries.base_trace_values.clear();
+    let mut missing_composition_row = proof;
+    missing_composition_row
+        .trace_queries
+        .composition_trace_values
+        .clear();
+
+    assert!(matches!(
+        QuarticClaim::new(()).verify(missing_trace_ood_eval, 1),
+        Err(VerificationError::ExecutionTraceOodEvalCountMismatch { .. })
+    ));
+    assert!(matches!(
+        QuarticClaim::new(()).verify(missing_composition_ood_eval, 1),
+        Err(VerificationError::CompositionTraceOodEvalCountMismatch { .. })
+    ));
+    assert!(matches!(
+        QuarticClaim::new(()).verify(missing_base_row, 1),
+        Err(VerificationError::BaseTraceQueryDoesNotMatchCommitment)
+    ));
+    assert!(matches!(
+        QuarticClaim::new(()).verify(missing_composition_row, 1),
+        Err(VerificationError::CompositionTraceQueryDoesNotMatchCommitment)
+    ));
+}
+
+#[test]
+fn unsatisfied_constraint_is_an_error() {
+    let mut trace = gen_trace(64, 2);
+    trace.0[1][5] = Fp::one();
+
+    let error = prove(OPTIONS, trace);
+
+    assert!(matches!(
+        error,
+        ProvingError::ConstraintsNotSatisfied {
+            constraint: 0,
+            row: 5,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn constraint_violations_include_trace_cells() {
+    let mut trace = gen_trace(64, 2);
+    trace.0[0][9] = Fp::from(2u8);
+    let challenges = Challenges::new(vec![]);
+    let hints = Hints::new(vec![]);
+
+    let violations = QuarticClaim::new(())
+        .validate_constraints(&challenges, &hints, &trace.0, &[])
+        .unwrap_err();
+
+    assert_eq!(violations.violations.len(), 1);
+    let violation = &violations.violations[0];
+    assert_eq!(violation.row, 9);
+    assert!(violations
+        .to_string()
+        .starts_with("quartic failed at row 9"));
+    assert_eq!(
+        violation.trace_cells,
+        vec![
+            (0, 0, FieldVariant::Fp(Fp::from(2u8))),
+            (1, 0, FieldVariant::Fp(Fp::zero()))
+        ]
+    );
+}
+
+#[test]
+fn proof_with_virtual_columns_verifies() {
+    let proof =
+        pollster::block_on(InterleavedClaim::new(()).prove(OPTIONS, gen_interleaved_trace(64)))
+            .unwrap();
+
+    InterleavedClaim::new(()).verify(proof, 1).unwrap();
+}
+
+#[test]
+fn virtual_column_violations_are_reported_at_physical_rows() {
+    let mut trace = gen_interleaved_trace(64);
+    // the square of row 5 of the counter
+    trace.0[0][11] = Fp::one();
+
+    let violations = InterleavedClaim::new(())
+        .validate_constraints(&Challenges::new(vec![]), &Hints::new(vec![]), &trace.0, &[])
+        .unwrap_err();
+
+    assert_eq!(violations.violations.len(), 1);
+    let violation = &violations.violations[0];
+    assert_eq!(violation.row, 10);
+    assert_eq!(
+        violation.trace_cells,
+        vec![
+            (0, 0, FieldVariant::Fp(Fp::from(5u8))),
+            (0, 1, FieldVariant::Fp(Fp::one()))
+        ]
+    );
+}
+
+#[test]
+fn proof_with_runtime_periodic_column_verifies() {
+    let trace = gen_round_constants_trace(64);
+
+    let proof = pollster::block_on(RoundConstantsClaim::new(()).prove(OPTIONS, trace)).unwrap();
+
+    RoundConstantsClaim::new(()).verify(proof, 1).unwrap();
+}
+
+#[test]
+fn runtime_periodic_column_violations_are_reported() {
+    let mut trace = gen_round_constants_trace(64);
+    trace.0[0][13] = Fp::zero();
+
+    let violations = RoundConstantsClaim::new(())
+        .validate_constraints(&Challenges::new(vec![]), &Hints::new(vec![]), &trace.0, &[])
+        .unwrap_err();
+
+    let rows = violations.violations.iter().map(|v| v.row);
+    assert_eq!(rows.collect::<Vec<usize>>(), [12, 13]);
+}
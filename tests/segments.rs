@@ -0,0 +1,242 @@
+mod common;
+
+use ark_ff::One;
+use common::Claim;
+use ministark::air::AirConfig;
+use ministark::air::AirError;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::VerifierChallenge;
+use ministark::prover::ProvingError;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::verifier::VerificationError;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const TRACE_LEN: usize = 64;
+const OPTIONS: ProofOptions = ProofOptions::new(16, 8, 0, 2, 4);
+
+const COUNTER: usize = 0;
+/// Column of the first extension segment
+const COMBINATION: usize = 1;
+/// Column of the second extension segment
+const PRODUCT: usize = 2;
+
+#[derive(Clone, Copy)]
+enum Challenge {
+    /// Combines the counter and its square into a single value
+    Alpha,
+    /// Drawn after the combinations are committed to
+    Beta,
+}
+
+impl VerifierChallenge for Challenge {
+    fn index(&self) -> usize {
+        0
+    }
+
+    fn round(&self) -> usize {
+        match self {
+            Self::Alpha => 0,
+            Self::Beta => 1,
+        }
+    }
+}
+
+struct RunningProductTrace {
+    base: Matrix<Fp>,
+    /// Row of the running product to overwrite with an invalid value
+    corrupted_row: Option<usize>,
+}
+
+impl Trace for RunningProductTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.base
+    }
+
+    fn build_extension_columns(
+        &self,
+        segment: usize,
+        challenges: &Challenges<Fp>,
+        extension_trace: &[Matrix<Fp>],
+    ) -> Option<Matrix<Fp>> {
+        let column: Vec<Fp> = match segment {
+            0 => {
+                let alpha = challenges[Challenge::Alpha];
+                let counters = self.base[COUNTER].iter();
+                counters.map(|&c| c + alpha * c * c).collect()
+            }
+            1 => {
+                let beta = challenges[Challenge::Beta];
+                let mut acc = Fp::one();
+                let mut products: Vec<Fp> = extension_trace[0][0]
+                    .iter()
+                    .map(|&combination| {
+                        acc *= beta - combination;
+                        acc
+                    })
+                    .collect();
+                if let Some(row) = self.corrupted_row {
+                    products[row] += Fp::one();
+                }
+                products
+            }
+            _ => return None,
+        };
+        Some(Matrix::from_rows(
+            column.into_iter().map(|v| vec![v]).collect(),
+        ))
+    }
+}
+
+/// Running product over random linear combinations of a counter and its
+/// square. The product depends on challenges from two rounds.
+struct RunningProductAirConfig;
+
+impl AirConfig for RunningProductAirConfig {
+    const NUM_BASE_COLUMNS: usize = 1;
+    const NUM_EXTENSION_COLUMNS: &'static [usize] = &[1, 1];
+    const NUM_CHALLENGES: &'static [usize] = &[1, 1];
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let alpha = Challenge::Alpha.challenge();
+        let beta = Challenge::Beta.challenge();
+        vec![
+            COUNTER.curr().first(),
+            (COUNTER.next() - COUNTER.curr() - one).every_row_except_last(trace_len),
+            (COMBINATION.curr() - COUNTER.curr() - alpha * COUNTER.curr() * COUNTER.curr())
+                .every(1, 0, trace_len),
+            (PRODUCT.curr() - (beta.clone() - COMBINATION.curr())).first(),
+            (PRODUCT.next() - PRODUCT.curr() * (beta - COMBINATION.next()))
+                .every_row_except_last(trace_len),
+        ]
+    }
+}
+
+/// Declares a single challenge round but uses a challenge of the second
+struct UndrawnChallengeAirConfig;
+
+impl AirConfig for UndrawnChallengeAirConfig {
+    const NUM_BASE_COLUMNS: usize = 1;
+    const NUM_EXTENSION_COLUMNS: &'static [usize] = &[1, 1];
+    const NUM_CHALLENGES: &'static [usize] = &[1];
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = ();
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        RunningProductAirConfig::constraints(trace_len)
+    }
+}
+
+type RunningProductClaim = Claim<RunningProductAirConfig, RunningProductTrace>;
+
+fn gen_trace() -> RunningProductTrace {
+    let rows = (0..TRACE_LEN as u64)
+        .map(|row| vec![Fp::from(row)])
+        .collect();
+    RunningProductTrace {
+        base: Matrix::from_rows(rows),
+        corrupted_row: None,
+    }
+}
+
+#[test]
+fn proof_with_two_challenge_rounds_verifies() {
+    let proof =
+        pollster::block_on(RunningProductClaim::new(()).prove(OPTIONS, gen_trace())).unwrap();
+
+    assert_eq!(proof.extension_trace_commitments.len(), 2);
+    RunningProductClaim::new(()).verify(proof, 1).unwrap();
+}
+
+#[test]
+fn proof_with_two_challenge_rounds_and_zero_knowledge_verifies() {
+    let options = OPTIONS.with_zero_knowledge();
+
+    let proof =
+        pollster::block_on(RunningProductClaim::new(()).prove(options, gen_trace())).unwrap();
+
+    RunningProductClaim::new(()).verify(proof, 1).unwrap();
+}
+
+#[test]
+fn proof_does_not_verify_with_swapped_segment_commitments() {
+    let mut proof =
+        pollster::block_on(RunningProductClaim::new(()).prove(OPTIONS, gen_trace())).unwrap();
+    proof.extension_trace_commitments.swap(0, 1);
+
+    assert!(RunningProductClaim::new(()).verify(proof, 1).is_err());
+}
+
+#[test]
+fn proof_without_every_segment_commitment_is_an_error() {
+    let mut proof =
+        pollster::block_on(RunningProductClaim::new(()).prove(OPTIONS, gen_trace())).unwrap();
+    proof.extension_trace_commitments.pop();
+
+    assert!(matches!(
+        RunningProductClaim::new(()).verify(proof, 1),
+        Err(VerificationError::ExtensionTraceCommitmentCountMismatch {
+            expected: 2,
+            actual: 1
+        })
+    ));
+}
+
+#[test]
+fn proof_with_missing_extension_trace_values_is_an_error() {
+    let mut proof =
+        pollster::block_on(RunningProductClaim::new(()).prove(OPTIONS, gen_trace())).unwrap();
+    proof.trace_queries.extension_trace_values[1].clear();
+
+    assert!(matches!(
+        RunningProductClaim::new(()).verify(proof, 1),
+        Err(VerificationError::ExtensionTraceQueryDoesNotMatchCommitment)
+    ));
+}
+
+#[test]
+fn violated_constraint_over_later_round_reports_challenge_round() {
+    let mut trace = gen_trace();
+    trace.corrupted_row = Some(9);
+
+    let error = match pollster::block_on(RunningProductClaim::new(()).prove(OPTIONS, trace)) {
+        Ok(_) => panic!("expected proving to fail"),
+        Err(error) => error,
+    };
+
+    let ProvingError::ConstraintsNotSatisfied {
+        constraint,
+        row,
+        details,
+    } = error
+    else {
+        panic!("expected unsatisfied constraints, got {error}");
+    };
+    assert_eq!((constraint, row), (4, 8));
+    assert!(details.contains("challenge 0 of round 1"));
+}
+
+#[test]
+fn challenge_of_undeclared_round_is_an_error() {
+    let result = Air::<UndrawnChallengeAirConfig>::new(TRACE_LEN, (), OPTIONS);
+
+    assert!(matches!(
+        result,
+        Err(AirError::ChallengeOutOfBounds { round: 1, index: 0 })
+    ));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use ark_ff::One;
+use common::gen_fib_trace;
+use common::Claim;
+use common::FibAirConfig;
+use common::TestTrace;
+use ministark::merkle::Rpo256MerkleTree;
+use ministark::random::Rpo256SpongePublicCoin;
+use ministark::stark::Stark;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+/// Uses a field-native transcript and Merkle tree over Goldilocks
+type FibClaim = Claim<FibAirConfig, TestTrace, Rpo256SpongePublicCoin<Fp>, Rpo256MerkleTree>;
+
+const OPTIONS: ProofOptions = ProofOptions::new(16, 8, 4, 2, 4);
+
+fn gen_trace() -> (TestTrace, FibClaim) {
+    let (trace, last_value) = gen_fib_trace(64);
+    (trace, FibClaim::new(last_value))
+}
+
+#[test]
+fn sponge_public_coin_proof_verifies() {
+    let (trace, claim) = gen_trace();
+
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+
+    claim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn sponge_public_coin_proof_does_not_verify_other_claim() {
+    let (trace, claim) = gen_trace();
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+
+    let other_claim = FibClaim::new(claim.public_inputs + Fp::one());
+
+    assert!(other_claim.verify(proof, 1).is_err());
+}
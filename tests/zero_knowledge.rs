@@ -0,0 +1,62 @@
+mod common;
+
+use ark_serialize::CanonicalSerialize;
+use common::gen_fib_trace;
+use common::Claim;
+use common::FibAirConfig;
+use ministark::stark::Stark;
+use ministark::Proof;
+use ministark::ProofOptions;
+
+type FibClaim = Claim<FibAirConfig>;
+
+const OPTIONS: ProofOptions = ProofOptions::new(16, 4, 0, 2, 4);
+
+fn prove(options: ProofOptions, zk_seed: u64) -> (FibClaim, Proof<FibClaim>) {
+    let (trace, last_value) = gen_fib_trace(64);
+    let claim = FibClaim::new(last_value).with_zk_seed(zk_seed);
+    let proof = pollster::block_on(claim.prove(options, trace)).unwrap();
+    (claim, proof)
+}
+
+fn to_bytes(proof: &Proof<FibClaim>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn zero_knowledge_proofs_verify() {
+    let (claim, proof) = prove(OPTIONS.with_zero_knowledge(), 0);
+
+    assert!(proof.options.zero_knowledge);
+    claim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn zero_knowledge_proofs_are_deterministic_with_seeded_rng() {
+    let (_, proof_a) = prove(OPTIONS.with_zero_knowledge(), 1);
+    let (_, proof_b) = prove(OPTIONS.with_zero_knowledge(), 1);
+    let (_, proof_c) = prove(OPTIONS.with_zero_knowledge(), 2);
+
+    assert_eq!(to_bytes(&proof_a), to_bytes(&proof_b));
+    assert_ne!(to_bytes(&proof_a), to_bytes(&proof_c));
+}
+
+#[test]
+fn zero_knowledge_halves_query_security() {
+    let (claim, proof) = prove(OPTIONS, 0);
+    let (zk_claim, zk_proof) = prove(OPTIONS.with_zero_knowledge(), 0);
+
+    // 16 queries with 2 bits of security each, halved by zero-knowledge
+    assert_eq!(proof.security_level_bits(), 32);
+    assert_eq!(zk_proof.security_level_bits(), 16);
+    claim.verify(proof, 32).unwrap();
+    zk_claim.verify(zk_proof, 16).unwrap();
+}
+
+#[test]
+#[should_panic]
+fn zero_knowledge_rejects_blowup_factor_of_two() {
+    let _ = ProofOptions::new(16, 2, 0, 2, 4).with_zero_knowledge();
+}